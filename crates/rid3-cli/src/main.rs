@@ -0,0 +1,621 @@
+//! Headless tagging for build pipelines, sharing `rid3-core` with the web editor.
+
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+use clap::{Args, Parser, Subcommand};
+use rid3_core::id3::frame::{Comment, ExtendedLink, ExtendedText};
+use rid3_core::id3::{Content, Frame, Tag, TagLike, Version};
+use rid3_core::layout::Trailer;
+use rid3_core::{art, chapters, frame, links, lyrics, toc, version, Mp3File, TagSource};
+use serde_json::{json, Value};
+
+#[derive(Parser)]
+#[command(name = "rid3", version, about = "Read and edit ID3 tags of MP3 files")]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Print the tag.
+    Show {
+        file: PathBuf,
+        /// Print machine-readable JSON.
+        #[arg(long)]
+        json: bool,
+    },
+    /// Set frames, e.g. `TIT2=Title`, `TXXX:CATALOGNUMBER=123`,
+    /// `WXXX:shop=https://…`, `COMM:notes=Text` or `COMM:deu:notes=Text`.
+    Set {
+        file: PathBuf,
+        #[arg(required = true, value_name = "FRAME=VALUE")]
+        assignments: Vec<String>,
+        /// Language of comments given without one, as an ISO 639-2 code.
+        #[arg(long, default_value = "eng", value_parser = parse_lang)]
+        lang: String,
+        #[command(flatten)]
+        write: WriteArgs,
+    },
+    /// Remove every frame with the given IDs (`TXXX:DESC`, `WXXX:DESC`,
+    /// `COMM:DESC` and `COMM:LANG:DESC` remove only the matching ones).
+    Remove {
+        file: PathBuf,
+        #[arg(required = true, value_name = "FRAME")]
+        frames: Vec<String>,
+        #[command(flatten)]
+        write: WriteArgs,
+    },
+    /// List, add or remove chapters.
+    #[command(subcommand)]
+    Chapters(ChaptersCommand),
+    /// Set or extract the cover art.
+    #[command(subcommand)]
+    Art(ArtCommand),
+    /// Remove the ID3v2 tag entirely.
+    Strip {
+        file: PathBuf,
+        /// Also remove trailing ID3v1 and APE tags and an appended ID3v2 tag.
+        #[arg(long)]
+        all: bool,
+        /// Write to this file instead of modifying the input in place.
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+}
+
+#[derive(Subcommand)]
+enum ChaptersCommand {
+    /// List chapters.
+    List {
+        file: PathBuf,
+        /// Print machine-readable JSON.
+        #[arg(long)]
+        json: bool,
+    },
+    /// Add a chapter. Times are milliseconds or `HH:MM:SS.mmm`.
+    Add {
+        file: PathBuf,
+        #[arg(long, value_parser = parse_time)]
+        start: u32,
+        #[arg(long, value_parser = parse_time)]
+        end: u32,
+        #[arg(long, default_value = "")]
+        title: String,
+        #[command(flatten)]
+        write: WriteArgs,
+    },
+    /// Remove a chapter by element ID.
+    Remove {
+        file: PathBuf,
+        element_id: String,
+        #[command(flatten)]
+        write: WriteArgs,
+    },
+}
+
+#[derive(Subcommand)]
+enum ArtCommand {
+    /// Replace the front cover with an image file.
+    Set {
+        file: PathBuf,
+        image: PathBuf,
+        /// MIME type of the image; guessed from its extension by default.
+        #[arg(long)]
+        mime: Option<String>,
+        #[command(flatten)]
+        write: WriteArgs,
+    },
+    /// Save the front cover (or the first picture) to a file.
+    Extract {
+        file: PathBuf,
+        /// Defaults to `cover.<ext>` next to the MP3 file.
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+}
+
+#[derive(Args)]
+struct WriteArgs {
+    /// Write to this file instead of modifying the input in place.
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+    /// ID3v2 version to write (2.2, 2.3 or 2.4). Defaults to the version read,
+    /// or 2.3 for files that had no ID3v2 tag.
+    #[arg(long = "id3-version", value_parser = parse_version)]
+    version: Option<Version>,
+    /// Remove a trailing ID3v1 tag instead of keeping it.
+    #[arg(long)]
+    strip_id3v1: bool,
+    /// Remove a trailing APE tag instead of keeping it.
+    #[arg(long)]
+    strip_ape: bool,
+    /// Remove an ID3v2 tag appended after the audio instead of keeping it.
+    #[arg(long)]
+    strip_appended_id3v2: bool,
+}
+
+type CliResult<T> = Result<T, Box<dyn Error>>;
+
+fn main() -> ExitCode {
+    match run(Cli::parse()) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("rid3: {}", err);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run(cli: Cli) -> CliResult<()> {
+    match cli.command {
+        Command::Show { file, json } => {
+            let mp3 = load(&file)?;
+            if json {
+                println!("{}", serde_json::to_string_pretty(&tag_json(&mp3))?);
+            } else {
+                print_tag(&mp3);
+            }
+        }
+        Command::Set {
+            file,
+            assignments,
+            lang,
+            write,
+        } => {
+            let mut mp3 = load(&file)?;
+            for assignment in &assignments {
+                let (id, value) = assignment
+                    .split_once('=')
+                    .ok_or_else(|| format!("expected FRAME=VALUE, got `{}`", assignment))?;
+                set_frame(&mut mp3, id, value, &lang)?;
+            }
+            save(mp3, &file, &write)?;
+        }
+        Command::Remove {
+            file,
+            frames,
+            write,
+        } => {
+            let mut mp3 = load(&file)?;
+            for id in &frames {
+                remove_frame(&mut mp3, id);
+            }
+            save(mp3, &file, &write)?;
+        }
+        Command::Chapters(ChaptersCommand::List { file, json }) => {
+            let mp3 = load(&file)?;
+            if json {
+                let list = chapters_json(&mp3);
+                println!("{}", serde_json::to_string_pretty(&list)?);
+            } else {
+                for chapter in mp3.tag.chapters() {
+                    println!(
+                        "{}\t{}\t{}\t{}",
+                        chapter.element_id,
+                        chapters::format_time(chapter.start_time),
+                        chapters::format_time(chapter.end_time),
+                        chapters::chapter_title(chapter).unwrap_or("")
+                    );
+                }
+            }
+        }
+        Command::Chapters(ChaptersCommand::Add {
+            file,
+            start,
+            end,
+            title,
+            write,
+        }) => {
+            if end < start {
+                return Err("chapter ends before it starts".into());
+            }
+            let mut mp3 = load(&file)?;
+            let element_id = edit_chapters(&mut mp3.tag, |tag| {
+                chapters::add_chapter(tag, start, end, &title)
+            });
+            save(mp3, &file, &write)?;
+            println!("{}", element_id);
+        }
+        Command::Chapters(ChaptersCommand::Remove {
+            file,
+            element_id,
+            write,
+        }) => {
+            let mut mp3 = load(&file)?;
+            if !edit_chapters(&mut mp3.tag, |tag| {
+                chapters::remove_chapter(tag, &element_id)
+            }) {
+                return Err(format!("no chapter with element ID `{}`", element_id).into());
+            }
+            save(mp3, &file, &write)?;
+        }
+        Command::Art(ArtCommand::Set {
+            file,
+            image,
+            mime,
+            write,
+        }) => {
+            let mime = match mime {
+                Some(mime) => mime,
+                None => image
+                    .extension()
+                    .and_then(|e| e.to_str())
+                    .and_then(art::mime_for_extension)
+                    .ok_or("cannot guess the image type, pass --mime")?
+                    .to_string(),
+            };
+            let data = fs::read(&image).map_err(|e| format!("{}: {}", image.display(), e))?;
+            let mut mp3 = load(&file)?;
+            art::set_cover(&mut mp3.tag, &mime, data);
+            save(mp3, &file, &write)?;
+        }
+        Command::Art(ArtCommand::Extract { file, output }) => {
+            let mp3 = load(&file)?;
+            let picture = art::cover(&mp3.tag).ok_or("the tag has no pictures")?;
+            let output = output.unwrap_or_else(|| {
+                file.with_file_name(format!(
+                    "cover.{}",
+                    art::extension_for_mime(&picture.mime_type)
+                ))
+            });
+            write_file(&output, &picture.data)?;
+            println!("{}", output.display());
+        }
+        Command::Strip { file, all, output } => {
+            let mut mp3 = load(&file)?;
+            if all {
+                mp3.set_keep(Trailer::Id3v1, false);
+                mp3.set_keep(Trailer::Ape, false);
+                mp3.set_keep(Trailer::AppendedId3v2, false);
+            }
+            write_file(output.as_deref().unwrap_or(&file), &mp3.stripped())?;
+        }
+    }
+    Ok(())
+}
+
+fn load(path: &Path) -> CliResult<Mp3File> {
+    let bytes = fs::read(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    Ok(Mp3File::from_bytes(bytes).map_err(|e| format!("{}: {}", path.display(), e))?)
+}
+
+fn save(mut mp3: Mp3File, input: &Path, args: &WriteArgs) -> CliResult<()> {
+    mp3.set_keep(Trailer::Id3v1, !args.strip_id3v1);
+    mp3.set_keep(Trailer::Ape, !args.strip_ape);
+    mp3.set_keep(Trailer::AppendedId3v2, !args.strip_appended_id3v2);
+    let version = args.version.unwrap_or(mp3.source_version());
+    for change in version::report(&mp3.tag, version) {
+        eprintln!("rid3: {}", change);
+    }
+    write_file(
+        args.output.as_deref().unwrap_or(input),
+        &mp3.to_bytes(version)?,
+    )
+}
+
+fn write_file(path: &Path, bytes: &[u8]) -> CliResult<()> {
+    fs::write(path, bytes).map_err(|e| format!("{}: {}", path.display(), e).into())
+}
+
+/// Runs a chapter edit and brings the tables of contents up to date, as the
+/// web editor does. Chapters hidden before, see [`toc::hidden`], stay unlisted.
+fn edit_chapters<T>(tag: &mut Tag, edit: impl FnOnce(&mut Tag) -> T) -> T {
+    let hidden = toc::hidden(tag);
+    let result = edit(tag);
+    toc::sync_except(tag, &hidden);
+    result
+}
+
+/// Sets the frame `id`, which may carry a description as in `TXXX:DESC`.
+/// Comments without a language get `lang`.
+fn set_frame(mp3: &mut Mp3File, id: &str, value: &str, lang: &str) -> CliResult<()> {
+    match id.split_once(':') {
+        Some(("TXXX", description)) => {
+            mp3.tag.add_frame(ExtendedText {
+                description: description.to_string(),
+                value: value.to_string(),
+            });
+        }
+        Some(("WXXX", description)) => {
+            warn_about_url(id, value);
+            mp3.tag.add_frame(ExtendedLink {
+                description: description.to_string(),
+                link: value.to_string(),
+            });
+        }
+        Some(("COMM", rest)) => {
+            let (lang, description) = comment_key(rest).unwrap_or((lang, rest));
+            mp3.tag.add_frame(Comment {
+                lang: lang.to_string(),
+                description: description.to_string(),
+                text: value.to_string(),
+            });
+        }
+        None if id == "TXXX" || id == "WXXX" => {
+            return Err(format!("`{}` needs a description, e.g. `{}:DESC=…`", id, id).into())
+        }
+        // They hold lists of pairs rather than plain text.
+        None if matches!(id, "IPLS" | "TIPL" | "TMCL") => {
+            return Err(format!("`{}` lists people and roles, which `set` can't write", id).into())
+        }
+        None if is_frame_id(id, 'T') => mp3.set_text(id, value),
+        None if links::is_url_frame(id) => {
+            warn_about_url(id, value);
+            // `add_frame` keeps WCOM and WOAR frames with other links.
+            mp3.tag.remove(id);
+            mp3.tag
+                .add_frame(Frame::with_content(id, Content::Link(value.to_string())));
+        }
+        _ => return Err(format!("cannot set `{}` from the command line", id).into()),
+    }
+    Ok(())
+}
+
+fn remove_frame(mp3: &mut Mp3File, id: &str) {
+    match id.split_once(':') {
+        Some(("TXXX", description)) => mp3.tag.remove_extended_text(Some(description), None),
+        Some(("WXXX", description)) => frame::retain_frames(&mut mp3.tag, |f| {
+            f.content()
+                .extended_link()
+                .is_none_or(|l| l.description != description)
+        }),
+        Some(("COMM", rest)) => match comment_key(rest) {
+            Some((lang, description)) => frame::retain_frames(&mut mp3.tag, |f| {
+                f.content()
+                    .comment()
+                    .is_none_or(|c| c.lang != lang || c.description != description)
+            }),
+            None => mp3.tag.remove_comment(Some(rest), None),
+        },
+        _ => {
+            mp3.tag.remove(id);
+        }
+    }
+}
+
+/// Splits `LANG:DESC`, the part of a comment's key after `COMM:` as
+/// [`frame::key`] prints it. None if it doesn't start with a language code.
+fn comment_key(rest: &str) -> Option<(&str, &str)> {
+    rest.split_once(':')
+        .filter(|(lang, _)| lyrics::is_language_code(lang))
+}
+
+fn warn_about_url(id: &str, url: &str) {
+    if let Some(problem) = links::problem(url) {
+        eprintln!("rid3: {}: {}", id, problem);
+    }
+}
+
+fn is_frame_id(id: &str, prefix: char) -> bool {
+    id.len() == 4
+        && id.starts_with(prefix)
+        && id
+            .chars()
+            .all(|c| c.is_ascii_uppercase() || c.is_ascii_digit())
+}
+
+fn print_tag(mp3: &Mp3File) {
+    println!("version\t{}", source_name(mp3).unwrap_or("none"));
+    for f in mp3.tag.frames() {
+        if f.id() != "CHAP" && f.id() != "APIC" {
+            match frame::description(f) {
+                Some(description) => {
+                    println!("{}:{}\t{}", f.id(), description, frame::display_value(f))
+                }
+                None => println!("{}\t{}", f.id(), frame::display_value(f)),
+            }
+        }
+    }
+    for p in mp3.tag.pictures() {
+        println!(
+            "APIC\t{} ({}, {} bytes)",
+            p.picture_type,
+            p.mime_type,
+            p.data.len()
+        );
+    }
+    for chapter in mp3.tag.chapters() {
+        println!(
+            "CHAP\t{} {}-{} {}",
+            chapter.element_id,
+            chapters::format_time(chapter.start_time),
+            chapters::format_time(chapter.end_time),
+            chapters::chapter_title(chapter).unwrap_or("")
+        );
+    }
+}
+
+/// The tag format found in the file, if any.
+fn source_name(mp3: &Mp3File) -> Option<&'static str> {
+    match mp3.source() {
+        TagSource::Id3v2(version) => Some(match version {
+            Version::Id3v22 => "ID3v2.2",
+            Version::Id3v23 => "ID3v2.3",
+            Version::Id3v24 => "ID3v2.4",
+        }),
+        TagSource::Id3v1 => Some("ID3v1"),
+        TagSource::None => None,
+    }
+}
+
+fn tag_json(mp3: &Mp3File) -> Value {
+    let frames: Vec<Value> = mp3
+        .tag
+        .frames()
+        .filter(|f| f.id() != "CHAP" && f.id() != "APIC")
+        .map(|f| match frame::description(f) {
+            Some(description) => json!({
+                "id": f.id(),
+                "description": description,
+                "value": frame::display_value(f),
+            }),
+            None => json!({ "id": f.id(), "value": frame::display_value(f) }),
+        })
+        .collect();
+    let pictures: Vec<Value> = mp3
+        .tag
+        .pictures()
+        .map(|p| {
+            json!({
+                "picture_type": p.picture_type.to_string(),
+                "mime_type": p.mime_type,
+                "description": p.description,
+                "size": p.data.len(),
+            })
+        })
+        .collect();
+    json!({
+        "version": source_name(mp3),
+        "frames": frames,
+        "pictures": pictures,
+        "chapters": chapters_json(mp3),
+    })
+}
+
+fn chapters_json(mp3: &Mp3File) -> Value {
+    mp3.tag
+        .chapters()
+        .map(|c| {
+            json!({
+                "element_id": c.element_id,
+                "start_time": c.start_time,
+                "end_time": c.end_time,
+                "title": chapters::chapter_title(c),
+            })
+        })
+        .collect()
+}
+
+fn parse_time(s: &str) -> Result<u32, String> {
+    chapters::parse_time(s).ok_or_else(|| format!("invalid time `{}`", s))
+}
+
+fn parse_version(s: &str) -> Result<Version, String> {
+    version::parse(s).ok_or_else(|| format!("unknown ID3 version `{}`", s))
+}
+
+fn parse_lang(s: &str) -> Result<String, String> {
+    if lyrics::is_language_code(s) {
+        Ok(s.to_string())
+    } else {
+        Err(format!("`{}` is not a three-letter language code", s))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn untagged_mp3() -> Mp3File {
+        let mut bytes = vec![0xff, 0xfb, 0x90, 0x00];
+        bytes.extend([0x55; 512]);
+        Mp3File::from_bytes(bytes).unwrap()
+    }
+
+    #[test]
+    fn arguments_are_parsed() {
+        let cli = Cli::try_parse_from(["rid3", "set", "a.mp3", "COMM:notes=Hi", "--lang", "deu"])
+            .unwrap();
+        let Command::Set {
+            assignments, lang, ..
+        } = cli.command
+        else {
+            panic!("not a set command");
+        };
+        assert_eq!(assignments, ["COMM:notes=Hi"]);
+        assert_eq!(lang, "deu");
+        assert!(Cli::try_parse_from(["rid3", "set", "a.mp3", "TIT2=x", "--lang", "en"]).is_err());
+        assert!(Cli::try_parse_from(["rid3", "set", "a.mp3"]).is_err());
+
+        let cli = Cli::try_parse_from([
+            "rid3", "chapters", "add", "a.mp3", "--start", "00:01", "--end", "2500",
+        ])
+        .unwrap();
+        let Command::Chapters(ChaptersCommand::Add { start, end, .. }) = cli.command else {
+            panic!("not a chapters add command");
+        };
+        assert_eq!((start, end), (1000, 2500));
+    }
+
+    #[test]
+    fn set_and_remove_round_trip() {
+        let mut mp3 = untagged_mp3();
+        for (id, value) in [
+            ("TIT2", "Episode 12"),
+            ("TXXX:CATALOGNUMBER", "EP12"),
+            ("WXXX:shop", "https://example.com/shop"),
+            ("WOAR", "https://example.com"),
+            ("COMM:notes", "In English"),
+            ("COMM:deu:notes", "Auf Deutsch"),
+        ] {
+            set_frame(&mut mp3, id, value, "eng").unwrap();
+        }
+        assert!(set_frame(&mut mp3, "TXXX", "foo", "eng").is_err());
+        assert!(set_frame(&mut mp3, "WXXX", "https://example.com", "eng").is_err());
+        assert!(set_frame(&mut mp3, "APIC", "x", "eng").is_err());
+
+        let mut mp3 = Mp3File::from_bytes(mp3.to_bytes(Version::Id3v24).unwrap()).unwrap();
+        assert_eq!(mp3.tag.title(), Some("Episode 12"));
+        let text = mp3.tag.extended_texts().next().unwrap();
+        assert_eq!(
+            (text.description.as_str(), text.value.as_str()),
+            ("CATALOGNUMBER", "EP12")
+        );
+        let link = mp3.tag.extended_links().next().unwrap();
+        assert_eq!(
+            (link.description.as_str(), link.link.as_str()),
+            ("shop", "https://example.com/shop")
+        );
+        let keys: Vec<String> = mp3.tag.frames().map(frame::key).collect();
+        assert!(keys.contains(&"WOAR:https://example.com".to_string()));
+        assert!(keys.contains(&"COMM:eng:notes".to_string()));
+        assert!(keys.contains(&"COMM:deu:notes".to_string()));
+
+        for id in ["TXXX:CATALOGNUMBER", "WXXX:shop", "COMM:deu:notes", "TIT2"] {
+            remove_frame(&mut mp3, id);
+        }
+        let keys: Vec<String> = mp3.tag.frames().map(frame::key).collect();
+        assert_eq!(keys, ["WOAR:https://example.com", "COMM:eng:notes"]);
+    }
+
+    #[test]
+    fn set_replaces_links_that_may_repeat() {
+        let mut mp3 = untagged_mp3();
+        set_frame(&mut mp3, "WOAR", "https://example.com/a", "eng").unwrap();
+        set_frame(&mut mp3, "WOAR", "https://example.com/b", "eng").unwrap();
+        let links: Vec<&str> = mp3
+            .tag
+            .frames()
+            .filter_map(|f| f.content().link())
+            .collect();
+        assert_eq!(links, ["https://example.com/b"]);
+    }
+
+    #[test]
+    fn people_lists_are_refused() {
+        let mut mp3 = untagged_mp3();
+        for id in ["TIPL", "TMCL", "IPLS"] {
+            assert!(set_frame(&mut mp3, id, "producer", "eng").is_err());
+        }
+        assert_eq!(mp3.tag.frames().count(), 0);
+    }
+
+    #[test]
+    fn chapter_commands_keep_the_table_of_contents() {
+        let mut tag = Tag::new();
+        edit_chapters(&mut tag, |tag| {
+            chapters::add_chapter(tag, 1000, 2000, "Main")
+        });
+        edit_chapters(&mut tag, |tag| chapters::add_chapter(tag, 0, 1000, "Intro"));
+        assert_eq!(toc::top_level(&tag).unwrap().elements, ["chp1", "chp0"]);
+
+        assert!(edit_chapters(&mut tag, |tag| chapters::remove_chapter(
+            tag, "chp1"
+        )));
+        assert_eq!(toc::top_level(&tag).unwrap().elements, ["chp0"]);
+    }
+}
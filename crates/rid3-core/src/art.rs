@@ -0,0 +1,390 @@
+use std::fmt;
+
+use id3::frame::{Picture, PictureType};
+use id3::{Tag, TagLike};
+
+/// Every picture type defined by ID3v2, in the order of their codes.
+pub const PICTURE_TYPES: [PictureType; 21] = [
+    PictureType::Other,
+    PictureType::Icon,
+    PictureType::OtherIcon,
+    PictureType::CoverFront,
+    PictureType::CoverBack,
+    PictureType::Leaflet,
+    PictureType::Media,
+    PictureType::LeadArtist,
+    PictureType::Artist,
+    PictureType::Conductor,
+    PictureType::Band,
+    PictureType::Composer,
+    PictureType::Lyricist,
+    PictureType::RecordingLocation,
+    PictureType::DuringRecording,
+    PictureType::DuringPerformance,
+    PictureType::ScreenCapture,
+    PictureType::BrightFish,
+    PictureType::Illustration,
+    PictureType::BandLogo,
+    PictureType::PublisherLogo,
+];
+
+/// The front cover, or the first picture if the tag has no front cover.
+pub fn cover(tag: &Tag) -> Option<&Picture> {
+    tag.pictures()
+        .find(|p| p.picture_type == PictureType::CoverFront)
+        .or_else(|| tag.pictures().next())
+}
+
+/// Replaces the front cover with `data`.
+pub fn set_cover(tag: &mut Tag, mime_type: &str, data: Vec<u8>) {
+    tag.remove_picture_by_type(PictureType::CoverFront);
+    tag.add_frame(Picture {
+        mime_type: mime_type.to_string(),
+        picture_type: PictureType::CoverFront,
+        description: String::new(),
+        data,
+    });
+}
+
+/// Why a picture can't sit next to the others in a tag.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Conflict {
+    /// Another picture has the same type and description.
+    SameTypeAndDescription(PictureType),
+    /// ID3 allows one picture of each icon type.
+    SecondIcon(PictureType),
+}
+
+impl fmt::Display for Conflict {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Conflict::SameTypeAndDescription(t) => write!(
+                f,
+                "There is already a \"{}\" picture with this description",
+                t
+            ),
+            Conflict::SecondIcon(t) => write!(f, "A tag can only have one \"{}\" picture", t),
+        }
+    }
+}
+
+/// Checks a picture of `picture_type` and `description` against `others`,
+/// which should not include the picture itself when it is being edited.
+pub fn conflict<'a>(
+    others: impl IntoIterator<Item = &'a Picture>,
+    picture_type: PictureType,
+    description: &str,
+) -> Option<Conflict> {
+    let is_icon = matches!(picture_type, PictureType::Icon | PictureType::OtherIcon);
+    others
+        .into_iter()
+        .filter(|p| p.picture_type == picture_type)
+        .find_map(|p| {
+            if is_icon {
+                Some(Conflict::SecondIcon(picture_type))
+            } else if p.description == description {
+                Some(Conflict::SameTypeAndDescription(picture_type))
+            } else {
+                None
+            }
+        })
+}
+
+/// The MIME type ID3 uses for a picture whose data is a URL to the image.
+pub const LINKED_MIME: &str = "-->";
+
+/// The image formats pictures are recognised in.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ImageFormat {
+    Jpeg,
+    Png,
+    Gif,
+    WebP,
+    Bmp,
+}
+
+impl ImageFormat {
+    /// Every format, for looking them up.
+    pub const ALL: [ImageFormat; 5] = [
+        ImageFormat::Jpeg,
+        ImageFormat::Png,
+        ImageFormat::Gif,
+        ImageFormat::WebP,
+        ImageFormat::Bmp,
+    ];
+
+    /// Recognises the format from the magic bytes at the start of `data`.
+    pub fn detect(data: &[u8]) -> Option<ImageFormat> {
+        match data {
+            [0xff, 0xd8, 0xff, ..] => Some(ImageFormat::Jpeg),
+            [0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1a, b'\n', ..] => Some(ImageFormat::Png),
+            [b'G', b'I', b'F', b'8', b'7' | b'9', b'a', ..] => Some(ImageFormat::Gif),
+            [b'R', b'I', b'F', b'F', _, _, _, _, b'W', b'E', b'B', b'P', ..] => {
+                Some(ImageFormat::WebP)
+            }
+            [b'B', b'M', ..] => Some(ImageFormat::Bmp),
+            _ => None,
+        }
+    }
+
+    /// The format a MIME type, or an ID3v2.2 three-letter image format such
+    /// as `JPG`, stands for.
+    pub fn from_mime(mime_type: &str) -> Option<ImageFormat> {
+        let mime_type = mime_type.trim().to_ascii_lowercase();
+        let format = match mime_type.as_str() {
+            "image/jpg" | "image/pjpeg" | "jpg" => ImageFormat::Jpeg,
+            "image/x-png" => ImageFormat::Png,
+            "image/x-ms-bmp" | "image/x-bmp" => ImageFormat::Bmp,
+            _ => {
+                return Self::ALL.into_iter().find(|f| {
+                    f.mime_type() == mime_type || f.v22_format().eq_ignore_ascii_case(&mime_type)
+                })
+            }
+        };
+        Some(format)
+    }
+
+    pub fn mime_type(&self) -> &'static str {
+        match self {
+            ImageFormat::Jpeg => "image/jpeg",
+            ImageFormat::Png => "image/png",
+            ImageFormat::Gif => "image/gif",
+            ImageFormat::WebP => "image/webp",
+            ImageFormat::Bmp => "image/bmp",
+        }
+    }
+
+    /// The three-letter image format of an ID3v2.2 `PIC` frame.
+    pub fn v22_format(&self) -> &'static str {
+        match self {
+            ImageFormat::Jpeg => "JPG",
+            ImageFormat::Png => "PNG",
+            ImageFormat::Gif => "GIF",
+            ImageFormat::WebP => "WBP",
+            ImageFormat::Bmp => "BMP",
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ImageFormat::Jpeg => "jpg",
+            ImageFormat::Png => "png",
+            ImageFormat::Gif => "gif",
+            ImageFormat::WebP => "webp",
+            ImageFormat::Bmp => "bmp",
+        }
+    }
+}
+
+impl fmt::Display for ImageFormat {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            ImageFormat::Jpeg => "JPEG",
+            ImageFormat::Png => "PNG",
+            ImageFormat::Gif => "GIF",
+            ImageFormat::WebP => "WebP",
+            ImageFormat::Bmp => "BMP",
+        })
+    }
+}
+
+/// Whether the picture's data is a URL to the image rather than the image.
+pub fn is_linked(picture: &Picture) -> bool {
+    picture.mime_type == LINKED_MIME
+}
+
+/// The URL a linked picture points to.
+pub fn linked_url(picture: &Picture) -> Option<String> {
+    is_linked(picture).then(|| {
+        String::from_utf8_lossy(&picture.data)
+            .trim_end_matches('\0')
+            .to_string()
+    })
+}
+
+/// The format of a picture's image, from its data if recognised and from
+/// its stored MIME type otherwise.
+pub fn format(picture: &Picture) -> Option<ImageFormat> {
+    if is_linked(picture) {
+        return None;
+    }
+    ImageFormat::detect(&picture.data).or_else(|| ImageFormat::from_mime(&picture.mime_type))
+}
+
+/// The MIME type to show a picture's image with.
+pub fn display_mime(picture: &Picture) -> &str {
+    format(picture).map_or(&picture.mime_type, |f| f.mime_type())
+}
+
+/// A stored MIME type that does not match the image data.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MimeMismatch {
+    pub stored: String,
+    pub detected: ImageFormat,
+}
+
+impl fmt::Display for MimeMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.stored.is_empty() {
+            write!(f, "The picture is {} but has no MIME type", self.detected)
+        } else {
+            write!(
+                f,
+                "The picture is {} but its MIME type is \"{}\"",
+                self.detected, self.stored
+            )
+        }
+    }
+}
+
+/// Compares a picture's stored MIME type with the format its data is in.
+/// Linked pictures and data in an unrecognised format are never reported.
+pub fn check_mime(picture: &Picture) -> Option<MimeMismatch> {
+    if is_linked(picture) {
+        return None;
+    }
+    let detected = ImageFormat::detect(&picture.data)?;
+    (ImageFormat::from_mime(&picture.mime_type) != Some(detected)).then(|| MimeMismatch {
+        stored: picture.mime_type.clone(),
+        detected,
+    })
+}
+
+/// Sets the picture's MIME type to the one its data calls for. Returns
+/// whether anything changed.
+pub fn fix_mime(picture: &mut Picture) -> bool {
+    let Some(mismatch) = check_mime(picture) else {
+        return false;
+    };
+    picture.mime_type = mismatch.detected.mime_type().to_string();
+    true
+}
+
+/// The extension to save a picture's image with: from its MIME type, or from
+/// its data when the MIME type names no known format.
+pub fn extension(picture: &Picture) -> &'static str {
+    ImageFormat::from_mime(&picture.mime_type)
+        .or_else(|| ImageFormat::detect(&picture.data))
+        .map_or("bin", |f| f.extension())
+}
+
+/// A picture's type followed by its description, e.g. `Back cover - Inlay`.
+pub fn label(picture: &Picture) -> String {
+    if picture.description.is_empty() {
+        picture.picture_type.to_string()
+    } else {
+        format!("{} - {}", picture.picture_type, picture.description)
+    }
+}
+
+/// The usual file extension for an image MIME type.
+pub fn extension_for_mime(mime_type: &str) -> &'static str {
+    ImageFormat::from_mime(mime_type).map_or("bin", |f| f.extension())
+}
+
+/// The MIME type for an image file extension.
+pub fn mime_for_extension(extension: &str) -> Option<&'static str> {
+    let extension = extension.to_ascii_lowercase();
+    ImageFormat::ALL
+        .into_iter()
+        .find(|f| f.extension() == extension || (extension == "jpeg" && *f == ImageFormat::Jpeg))
+        .map(|f| f.mime_type())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn set_cover_replaces_front_cover() {
+        let mut tag = Tag::new();
+        set_cover(&mut tag, "image/png", vec![1]);
+        set_cover(&mut tag, "image/jpeg", vec![2]);
+
+        assert_eq!(tag.pictures().count(), 1);
+        let cover = cover(&tag).unwrap();
+        assert_eq!(cover.mime_type, "image/jpeg");
+        assert_eq!(cover.data, [2]);
+    }
+
+    #[test]
+    fn pictures_must_differ_in_type_or_description() {
+        let back = Picture {
+            mime_type: "image/png".to_string(),
+            picture_type: PictureType::CoverBack,
+            description: "Back".to_string(),
+            data: vec![1],
+        };
+        let icon = Picture {
+            picture_type: PictureType::Icon,
+            description: String::new(),
+            ..back.clone()
+        };
+        let pictures = [back, icon];
+
+        assert_eq!(
+            conflict(&pictures, PictureType::CoverBack, "Back"),
+            Some(Conflict::SameTypeAndDescription(PictureType::CoverBack))
+        );
+        assert_eq!(conflict(&pictures, PictureType::CoverBack, "Inlay"), None);
+        assert_eq!(conflict(&pictures, PictureType::CoverFront, "Back"), None);
+        assert_eq!(
+            conflict(&pictures, PictureType::Icon, "Other"),
+            Some(Conflict::SecondIcon(PictureType::Icon))
+        );
+    }
+
+    #[test]
+    fn formats_come_from_magic_bytes() {
+        let png = [0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1a, b'\n', 0];
+        assert_eq!(ImageFormat::detect(&png), Some(ImageFormat::Png));
+        assert_eq!(
+            ImageFormat::detect(&[0xff, 0xd8, 0xff, 0xe0]),
+            Some(ImageFormat::Jpeg)
+        );
+        assert_eq!(ImageFormat::detect(b"GIF89a"), Some(ImageFormat::Gif));
+        assert_eq!(
+            ImageFormat::detect(b"RIFF\0\0\0\0WEBPVP8 "),
+            Some(ImageFormat::WebP)
+        );
+        assert_eq!(ImageFormat::detect(b"BM\0\0"), Some(ImageFormat::Bmp));
+        assert_eq!(ImageFormat::detect(b"RIFF\0\0\0\0WAVE"), None);
+
+        assert_eq!(ImageFormat::from_mime("image/JPG"), Some(ImageFormat::Jpeg));
+        assert_eq!(ImageFormat::from_mime("PNG"), Some(ImageFormat::Png));
+        assert_eq!(extension_for_mime("image/webp"), "webp");
+        assert_eq!(mime_for_extension("JPEG"), Some("image/jpeg"));
+    }
+
+    #[test]
+    fn wrong_mime_types_are_fixed() {
+        let mut picture = Picture {
+            mime_type: "image/png".to_string(),
+            picture_type: PictureType::CoverFront,
+            description: String::new(),
+            data: vec![0xff, 0xd8, 0xff, 0xe0],
+        };
+        assert_eq!(
+            check_mime(&picture).unwrap().to_string(),
+            "The picture is JPEG but its MIME type is \"image/png\""
+        );
+        assert_eq!(display_mime(&picture), "image/jpeg");
+        assert!(fix_mime(&mut picture));
+        assert_eq!(picture.mime_type, "image/jpeg");
+        assert_eq!(check_mime(&picture), None);
+
+        picture.mime_type = "image/jpg".to_string();
+        assert_eq!(check_mime(&picture), None);
+
+        let linked = Picture {
+            mime_type: LINKED_MIME.to_string(),
+            data: b"https://example.com/cover.jpg".to_vec(),
+            ..picture
+        };
+        assert_eq!(check_mime(&linked), None);
+        assert_eq!(
+            linked_url(&linked).as_deref(),
+            Some("https://example.com/cover.jpg")
+        );
+    }
+}
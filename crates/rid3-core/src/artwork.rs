@@ -0,0 +1,374 @@
+//! Picture data as images: dimensions, cropping, resizing and re-encoding as
+//! JPEG, and exporting every picture as a zip. Everything is pure Rust so it
+//! runs in the browser too.
+
+use std::fmt;
+use std::io::{Cursor, Write};
+
+use id3::frame::{Chapter, Picture};
+use id3::Tag;
+use image::codecs::jpeg::JpegEncoder;
+use image::imageops::FilterType;
+use image::{DynamicImage, ImageReader, Rgb, RgbImage};
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipWriter};
+
+use crate::{art, chapters, filename, Error, Result};
+
+/// Longest edges podcast directories commonly ask for.
+pub const MAX_EDGES: [u32; 2] = [3000, 1400];
+
+/// A JPEG quality that keeps artwork sharp at a fraction of a PNG's size.
+pub const DEFAULT_QUALITY: u8 = 85;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Dimensions {
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Dimensions {
+    pub fn is_square(&self) -> bool {
+        self.width == self.height
+    }
+
+    /// The dimensions after [`rework`] with `options`.
+    pub fn reworked(self, options: &Rework) -> Dimensions {
+        let Dimensions {
+            mut width,
+            mut height,
+        } = self;
+        if options.crop_square {
+            width = width.min(height);
+            height = width;
+        }
+        match options.max_edge {
+            Some(max) if width.max(height) > max => {
+                let scale = f64::from(max) / f64::from(width.max(height));
+                let scaled = |edge: u32| ((f64::from(edge) * scale).round() as u32).max(1);
+                Dimensions {
+                    width: scaled(width),
+                    height: scaled(height),
+                }
+            }
+            _ => Dimensions { width, height },
+        }
+    }
+}
+
+impl fmt::Display for Dimensions {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}×{}", self.width, self.height)
+    }
+}
+
+/// The dimensions of an image, read from its header only.
+pub fn dimensions(data: &[u8]) -> Result<Dimensions> {
+    let (width, height) = ImageReader::new(Cursor::new(data))
+        .with_guessed_format()
+        .map_err(|err| Error::Image(err.into()))?
+        .into_dimensions()
+        .map_err(Error::Image)?;
+    Ok(Dimensions { width, height })
+}
+
+/// How to rework a picture. The result is always a JPEG.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Rework {
+    /// Crops the middle of the image to a square.
+    pub crop_square: bool,
+    /// Scales the image down so its longest edge is at most this many
+    /// pixels. Smaller images are never scaled up.
+    pub max_edge: Option<u32>,
+    /// JPEG quality from 1 to 100.
+    pub quality: u8,
+}
+
+impl Default for Rework {
+    fn default() -> Self {
+        Rework {
+            crop_square: false,
+            max_edge: None,
+            quality: DEFAULT_QUALITY,
+        }
+    }
+}
+
+/// A reworked picture.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Reworked {
+    /// The image as a JPEG.
+    pub data: Vec<u8>,
+    pub dimensions: Dimensions,
+}
+
+/// Crops, scales and re-encodes the image in `data` as a JPEG. Transparent
+/// areas are put on white, since JPEG has no transparency.
+pub fn rework(data: &[u8], options: &Rework) -> Result<Reworked> {
+    let mut image = image::load_from_memory(data).map_err(Error::Image)?;
+    if options.crop_square {
+        let side = image.width().min(image.height());
+        let x = (image.width() - side) / 2;
+        let y = (image.height() - side) / 2;
+        image = image.crop_imm(x, y, side, side);
+    }
+    if let Some(max) = options.max_edge {
+        if image.width().max(image.height()) > max {
+            image = image.resize(max, max, FilterType::Lanczos3);
+        }
+    }
+    let rgb = flatten(&image);
+    let mut out = Vec::new();
+    JpegEncoder::new_with_quality(&mut out, options.quality.clamp(1, 100))
+        .encode_image(&rgb)
+        .map_err(Error::Image)?;
+    Ok(Reworked {
+        data: out,
+        dimensions: Dimensions {
+            width: rgb.width(),
+            height: rgb.height(),
+        },
+    })
+}
+
+/// The image without transparency, on a white background.
+fn flatten(image: &DynamicImage) -> RgbImage {
+    if !image.color().has_alpha() {
+        return image.to_rgb8();
+    }
+    let rgba = image.to_rgba8();
+    RgbImage::from_fn(rgba.width(), rgba.height(), |x, y| {
+        let [r, g, b, a] = rgba.get_pixel(x, y).0;
+        let alpha = u16::from(a);
+        let blend = |c: u8| ((u16::from(c) * alpha + 255 * (255 - alpha) + 127) / 255) as u8;
+        Rgb([blend(r), blend(g), blend(b)])
+    })
+}
+
+/// Every picture in `tag` that holds an image, chapter pictures included,
+/// with a unique file name such as `Front cover.jpg` or
+/// `chp1 Intro - Front cover.png`. Linked pictures are left out.
+pub fn exports(tag: &Tag) -> Vec<(String, &Picture)> {
+    let main = tag.pictures().map(|p| (String::new(), p));
+    let in_chapters = tag.chapters().flat_map(|chapter| {
+        let prefix = chapter_prefix(chapter);
+        chapter
+            .frames
+            .iter()
+            .filter_map(|f| f.content().picture())
+            .map(move |p| (prefix.clone(), p))
+    });
+
+    let mut exports: Vec<(String, &Picture)> = Vec::new();
+    for (prefix, picture) in main.chain(in_chapters) {
+        if art::is_linked(picture) {
+            continue;
+        }
+        let stem = format!("{}{}", prefix, art::label(picture));
+        let extension = art::extension(picture);
+        let mut name = filename::sanitize_as(&stem, extension);
+        let mut n = 2;
+        while exports.iter().any(|(taken, _)| *taken == name) {
+            // Long names are shortened first, so truncation can't cut off the counter.
+            let counter = format!(" ({})", n);
+            let room = filename::MAX_LEN - extension.len() - 1 - counter.len();
+            name = filename::sanitize_as(
+                &format!("{}{}", filename::truncate(&stem, room), counter),
+                extension,
+            );
+            n += 1;
+        }
+        exports.push((name, picture));
+    }
+    exports
+}
+
+/// What names of a chapter's pictures start with, e.g. `chp1 Intro - `.
+pub fn chapter_prefix(chapter: &Chapter) -> String {
+    match chapters::chapter_title(chapter) {
+        Some(title) => format!("{} {} - ", chapter.element_id, title),
+        None => format!("{} - ", chapter.element_id),
+    }
+}
+
+/// A zip of every picture in [`exports`]. Images are compressed already, so
+/// they are stored as they are.
+pub fn zip(tag: &Tag) -> Result<Vec<u8>> {
+    let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+    let options = SimpleFileOptions::default().compression_method(CompressionMethod::Stored);
+    for (name, picture) in exports(tag) {
+        zip.start_file(name, options).map_err(Error::Archive)?;
+        zip.write_all(&picture.data)
+            .map_err(|err| Error::Archive(err.into()))?;
+    }
+    Ok(zip.finish().map_err(Error::Archive)?.into_inner())
+}
+
+/// A byte count for people, e.g. `5.1 MB`.
+pub fn format_size(bytes: usize) -> String {
+    const KB: f64 = 1000.0;
+    let n = bytes as f64;
+    if n >= KB * KB {
+        format!("{:.1} MB", n / (KB * KB))
+    } else if n >= KB {
+        format!("{:.0} KB", n / KB)
+    } else {
+        format!("{} bytes", bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use id3::TagLike;
+    use image::{ImageFormat, Rgba, RgbaImage};
+
+    fn png(width: u32, height: u32) -> Vec<u8> {
+        let image = RgbaImage::from_pixel(width, height, Rgba([255, 0, 0, 0]));
+        let mut out = Cursor::new(Vec::new());
+        image.write_to(&mut out, ImageFormat::Png).unwrap();
+        out.into_inner()
+    }
+
+    #[test]
+    fn pictures_are_cropped_scaled_and_encoded_as_jpeg() {
+        let data = png(40, 20);
+        assert_eq!(
+            dimensions(&data).unwrap(),
+            Dimensions {
+                width: 40,
+                height: 20
+            }
+        );
+
+        let options = Rework {
+            crop_square: true,
+            max_edge: Some(10),
+            quality: 90,
+        };
+        let reworked = rework(&data, &options).unwrap();
+        assert_eq!(
+            reworked.dimensions,
+            Dimensions {
+                width: 10,
+                height: 10
+            }
+        );
+        assert_eq!(
+            dimensions(&data).unwrap().reworked(&options),
+            reworked.dimensions
+        );
+        assert_eq!(
+            crate::art::ImageFormat::detect(&reworked.data),
+            Some(crate::art::ImageFormat::Jpeg)
+        );
+        // Transparent red is put on white.
+        let decoded = image::load_from_memory(&reworked.data).unwrap().to_rgb8();
+        assert!(decoded.get_pixel(5, 5).0.iter().all(|c| *c > 240));
+    }
+
+    #[test]
+    fn small_pictures_are_not_scaled_up() {
+        let size = Dimensions {
+            width: 800,
+            height: 600,
+        };
+        let options = Rework {
+            max_edge: Some(1400),
+            ..Rework::default()
+        };
+        assert_eq!(size.reworked(&options), size);
+        assert_eq!(
+            Dimensions {
+                width: 4000,
+                height: 3000
+            }
+            .reworked(&Rework {
+                max_edge: Some(3000),
+                ..options
+            }),
+            Dimensions {
+                width: 3000,
+                height: 2250
+            }
+        );
+    }
+
+    #[test]
+    fn every_picture_is_exported_under_its_own_name() {
+        let cover = Picture {
+            mime_type: "image/jpeg".to_string(),
+            picture_type: id3::frame::PictureType::CoverFront,
+            description: String::new(),
+            data: vec![0xff, 0xd8, 0xff, 0xe0],
+        };
+        let mut tag = Tag::new();
+        tag.add_frame(cover.clone());
+        crate::frame::add(
+            &mut tag,
+            Picture {
+                mime_type: art::LINKED_MIME.to_string(),
+                description: "Online".to_string(),
+                data: b"https://example.com/a.jpg".to_vec(),
+                ..cover.clone()
+            }
+            .into(),
+        );
+        chapters::add_chapter(&mut tag, 0, 1000, "Intro");
+        let mut chapter = tag.chapters().next().unwrap().clone();
+        chapter.frames.push(cover.clone().into());
+        chapter.frames.push(cover.into());
+        tag.add_frame(chapter);
+
+        let names: Vec<String> = exports(&tag).into_iter().map(|(name, _)| name).collect();
+        assert_eq!(
+            names,
+            [
+                "Front cover.jpg",
+                "chp0 Intro - Front cover.jpg",
+                "chp0 Intro - Front cover (2).jpg",
+            ]
+        );
+
+        let archive = zip::ZipArchive::new(Cursor::new(zip(&tag).unwrap())).unwrap();
+        assert_eq!(archive.len(), 3);
+        assert!(archive.file_names().any(|n| n == "Front cover.jpg"));
+    }
+
+    #[test]
+    fn long_names_keep_their_counter() {
+        let cover = Picture {
+            mime_type: "image/jpeg".to_string(),
+            picture_type: id3::frame::PictureType::CoverFront,
+            description: String::new(),
+            data: vec![0xff, 0xd8, 0xff, 0xe0],
+        };
+        let mut chapter = Chapter {
+            element_id: "chp0".to_string(),
+            start_time: 0,
+            end_time: 1000,
+            start_offset: 0,
+            end_offset: 0,
+            frames: Vec::new(),
+        };
+        chapters::set_chapter_title(&mut chapter, &"é".repeat(200));
+        chapter.frames.push(cover.clone().into());
+        chapter.frames.push(cover.clone().into());
+        chapter.frames.push(cover.into());
+        let mut tag = Tag::new();
+        tag.add_frame(chapter);
+
+        let names: Vec<String> = exports(&tag).into_iter().map(|(name, _)| name).collect();
+        assert_eq!(names.len(), 3);
+        assert!(names.iter().all(|n| n.len() <= filename::MAX_LEN));
+        assert!(names[1].ends_with(" (2).jpg"));
+        assert!(names[2].ends_with(" (3).jpg"));
+    }
+
+    #[test]
+    fn sizes_are_readable() {
+        assert_eq!(format_size(512), "512 bytes");
+        assert_eq!(format_size(830_400), "830 KB");
+        assert_eq!(format_size(5_123_456), "5.1 MB");
+    }
+}
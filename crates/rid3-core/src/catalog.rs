@@ -0,0 +1,252 @@
+//! The frames defined by the ID3v2.3 and ID3v2.4 specifications and the
+//! chapter addendum, with what each one is for.
+
+use id3::frame::{Comment, ExtendedLink, ExtendedText, Lyrics, Popularimeter, SynchronisedLyrics};
+use id3::frame::{SynchronisedLyricsType, TimestampFormat};
+use id3::{Content, Frame, Version};
+
+/// A frame ID and the versions that define it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FrameInfo {
+    pub id: &'static str,
+    pub description: &'static str,
+    v23: bool,
+    v24: bool,
+}
+
+const fn both(id: &'static str, description: &'static str) -> FrameInfo {
+    FrameInfo {
+        id,
+        description,
+        v23: true,
+        v24: true,
+    }
+}
+
+const fn v23(id: &'static str, description: &'static str) -> FrameInfo {
+    FrameInfo {
+        id,
+        description,
+        v23: true,
+        v24: false,
+    }
+}
+
+const fn v24(id: &'static str, description: &'static str) -> FrameInfo {
+    FrameInfo {
+        id,
+        description,
+        v23: false,
+        v24: true,
+    }
+}
+
+/// Every known frame, sorted by ID.
+pub const FRAMES: &[FrameInfo] = &[
+    both("AENC", "Audio encryption"),
+    both("APIC", "Attached picture"),
+    v24("ASPI", "Audio seek point index"),
+    both("CHAP", "Chapter"),
+    both("COMM", "Comments"),
+    both("COMR", "Commercial frame"),
+    both("CTOC", "Table of contents"),
+    both("ENCR", "Encryption method registration"),
+    v24("EQU2", "Equalisation (2)"),
+    v23("EQUA", "Equalisation"),
+    both("ETCO", "Event timing codes"),
+    both("GEOB", "General encapsulated object"),
+    both("GRID", "Group identification registration"),
+    v23("IPLS", "Involved people list"),
+    both("LINK", "Linked information"),
+    both("MCDI", "Music CD identifier"),
+    both("MLLT", "MPEG location lookup table"),
+    both("OWNE", "Ownership frame"),
+    both("PCNT", "Play counter"),
+    both("POPM", "Popularimeter"),
+    both("POSS", "Position synchronisation frame"),
+    both("PRIV", "Private frame"),
+    both("RBUF", "Recommended buffer size"),
+    v24("RVA2", "Relative volume adjustment (2)"),
+    v23("RVAD", "Relative volume adjustment"),
+    both("RVRB", "Reverb"),
+    v24("SEEK", "Seek frame"),
+    v24("SIGN", "Signature frame"),
+    both("SYLT", "Synchronised lyrics/text"),
+    both("SYTC", "Synchronised tempo codes"),
+    both("TALB", "Album/Movie/Show title"),
+    both("TBPM", "BPM (beats per minute)"),
+    both("TCOM", "Composer"),
+    both("TCON", "Content type"),
+    both("TCOP", "Copyright message"),
+    v23("TDAT", "Date"),
+    v24("TDEN", "Encoding time"),
+    both("TDLY", "Playlist delay"),
+    v24("TDOR", "Original release time"),
+    v24("TDRC", "Recording time"),
+    v24("TDRL", "Release time"),
+    v24("TDTG", "Tagging time"),
+    both("TENC", "Encoded by"),
+    both("TEXT", "Lyricist/Text writer"),
+    both("TFLT", "File type"),
+    v23("TIME", "Time"),
+    v24("TIPL", "Involved people list"),
+    both("TIT1", "Content group description"),
+    both("TIT2", "Title/Songname/Content description"),
+    both("TIT3", "Subtitle/Description refinement"),
+    both("TKEY", "Initial key"),
+    both("TLAN", "Language(s)"),
+    both("TLEN", "Length"),
+    v24("TMCL", "Musician credits list"),
+    both("TMED", "Media type"),
+    v24("TMOO", "Mood"),
+    both("TOAL", "Original album/movie/show title"),
+    both("TOFN", "Original filename"),
+    both("TOLY", "Original lyricist(s)/text writer(s)"),
+    both("TOPE", "Original artist(s)/performer(s)"),
+    v23("TORY", "Original release year"),
+    both("TOWN", "File owner/licensee"),
+    both("TPE1", "Lead performer(s)/Soloist(s)"),
+    both("TPE2", "Band/orchestra/accompaniment"),
+    both("TPE3", "Conductor/performer refinement"),
+    both("TPE4", "Interpreted, remixed, or otherwise modified by"),
+    both("TPOS", "Part of a set"),
+    v24("TPRO", "Produced notice"),
+    both("TPUB", "Publisher"),
+    both("TRCK", "Track number/Position in set"),
+    v23("TRDA", "Recording dates"),
+    both("TRSN", "Internet radio station name"),
+    both("TRSO", "Internet radio station owner"),
+    v23("TSIZ", "Size"),
+    v24("TSOA", "Album sort order"),
+    v24("TSOP", "Performer sort order"),
+    v24("TSOT", "Title sort order"),
+    both("TSRC", "ISRC (international standard recording code)"),
+    both("TSSE", "Software/Hardware and settings used for encoding"),
+    v24("TSST", "Set subtitle"),
+    both("TXXX", "User defined text information frame"),
+    v23("TYER", "Year"),
+    both("UFID", "Unique file identifier"),
+    both("USER", "Terms of use"),
+    both("USLT", "Unsynchronised lyric/text transcription"),
+    both("WCOM", "Commercial information"),
+    both("WCOP", "Copyright/Legal information"),
+    both("WOAF", "Official audio file webpage"),
+    both("WOAR", "Official artist/performer webpage"),
+    both("WOAS", "Official audio source webpage"),
+    both("WORS", "Official Internet radio station homepage"),
+    both("WPAY", "Payment"),
+    both("WPUB", "Publishers official webpage"),
+    both("WXXX", "User defined URL link frame"),
+];
+
+impl FrameInfo {
+    /// Whether tags of `version` can hold this frame. ID3v2.2 holds the
+    /// ID3v2.3 frames that have a three-character ID.
+    pub fn is_valid_in(&self, version: Version) -> bool {
+        match version {
+            Version::Id3v22 => {
+                self.v23
+                    && Frame::text(self.id, "")
+                        .id_for_version(Version::Id3v22)
+                        .is_some()
+            }
+            Version::Id3v23 => self.v23,
+            Version::Id3v24 => self.v24,
+        }
+    }
+}
+
+/// The frames that tags of `version` can hold.
+pub fn frames_for(version: Version) -> impl Iterator<Item = &'static FrameInfo> {
+    FRAMES.iter().filter(move |f| f.is_valid_in(version))
+}
+
+/// What the frame with `id` is for, if it is a known frame.
+pub fn describe(id: &str) -> Option<&'static str> {
+    FRAMES.iter().find(|f| f.id == id).map(|f| f.description)
+}
+
+/// An empty frame with `id`, ready to be filled in. Frames that only make
+/// sense with data in them, such as pictures, chapters or binary frames,
+/// cannot be created empty and give `None`.
+pub fn new_frame(id: &str) -> Option<Frame> {
+    let content = match id {
+        "TXXX" => Content::ExtendedText(ExtendedText {
+            description: String::new(),
+            value: String::new(),
+        }),
+        "WXXX" => Content::ExtendedLink(ExtendedLink {
+            description: String::new(),
+            link: String::new(),
+        }),
+        "COMM" => Content::Comment(Comment {
+            lang: "eng".to_string(),
+            description: String::new(),
+            text: String::new(),
+        }),
+        "USLT" => Content::Lyrics(Lyrics {
+            lang: "eng".to_string(),
+            description: String::new(),
+            text: String::new(),
+        }),
+        "SYLT" => Content::SynchronisedLyrics(SynchronisedLyrics {
+            lang: "eng".to_string(),
+            timestamp_format: TimestampFormat::Ms,
+            content_type: SynchronisedLyricsType::Lyrics,
+            description: String::new(),
+            content: Vec::new(),
+        }),
+        "POPM" => Content::Popularimeter(Popularimeter {
+            user: String::new(),
+            rating: 0,
+            counter: 0,
+        }),
+        // IPLS, TIPL and TMCL hold lists of pairs rather than plain text.
+        "IPLS" | "TIPL" | "TMCL" => return None,
+        id if id.starts_with('T') => Content::Text(String::new()),
+        id if id.starts_with('W') => Content::Link(String::new()),
+        _ => return None,
+    };
+    FRAMES
+        .iter()
+        .any(|f| f.id == id)
+        .then(|| Frame::with_content(id, content))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn versions_have_their_own_frames() {
+        let ids = |version| frames_for(version).map(|f| f.id).collect::<Vec<_>>();
+        let (v22, v23, v24) = (
+            ids(Version::Id3v22),
+            ids(Version::Id3v23),
+            ids(Version::Id3v24),
+        );
+        assert!(v23.contains(&"TYER") && !v24.contains(&"TYER"));
+        assert!(v24.contains(&"TDRC") && !v23.contains(&"TDRC"));
+        assert!(v22.contains(&"TIT2") && !v22.contains(&"CHAP"));
+        assert!(v22.len() < v23.len());
+    }
+
+    #[test]
+    fn frames_are_sorted() {
+        assert!(FRAMES.windows(2).all(|w| w[0].id < w[1].id));
+    }
+
+    #[test]
+    fn only_fillable_frames_are_created_empty() {
+        assert_eq!(new_frame("TALB").unwrap().content().text(), Some(""));
+        assert!(new_frame("WOAR").unwrap().content().link().is_some());
+        assert!(new_frame("TXXX")
+            .unwrap()
+            .content()
+            .extended_text()
+            .is_some());
+        assert_eq!(new_frame("APIC"), None);
+        assert_eq!(new_frame("TIPL"), None);
+        assert_eq!(new_frame("TZZZ"), None);
+    }
+}
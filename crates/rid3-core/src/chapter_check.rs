@@ -0,0 +1,580 @@
+//! Consistency checks on chapters and tables of contents, and the fixes for
+//! what they find.
+
+use std::fmt;
+
+use id3::frame::{Chapter, TableOfContents};
+use id3::{Content, Frame, Tag, TagLike};
+
+use crate::chapters::{format_time, next_element_id};
+use crate::toc;
+
+/// Something wrong with the tag's chapters.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Problem {
+    /// The chapter comes before one that starts earlier.
+    OutOfOrder { element_id: String },
+    /// The chapter ends before it starts.
+    EndsBeforeStart { element_id: String },
+    /// The chapter starts before the one before it has ended.
+    Overlap {
+        element_id: String,
+        previous: String,
+    },
+    /// Nothing plays between the end of the previous chapter and this one.
+    Gap {
+        element_id: String,
+        previous: String,
+        ms: u32,
+    },
+    /// The chapter ends after the audio does.
+    PastDuration {
+        element_id: String,
+        end_time: u32,
+        duration: u32,
+    },
+    /// Another chapter or table has the same element ID.
+    DuplicateId { element_id: String },
+    /// A table lists an element ID no chapter or table has.
+    DanglingReference { toc: String, element_id: String },
+    /// The tag has tables of contents, but none of them is the top level.
+    NoTopLevel,
+}
+
+impl Problem {
+    /// The chapter the problem is with. Dangling references belong to the
+    /// table that holds them instead, and a missing top level to no chapter.
+    pub fn chapter_id(&self) -> Option<&str> {
+        match self {
+            Problem::OutOfOrder { element_id }
+            | Problem::EndsBeforeStart { element_id }
+            | Problem::Overlap { element_id, .. }
+            | Problem::Gap { element_id, .. }
+            | Problem::PastDuration { element_id, .. }
+            | Problem::DuplicateId { element_id } => Some(element_id),
+            Problem::DanglingReference { .. } | Problem::NoTopLevel => None,
+        }
+    }
+
+    /// The fix that takes care of the problem.
+    pub fn fix(&self) -> Fix {
+        match self {
+            Problem::OutOfOrder { .. } => Fix::Sort,
+            Problem::EndsBeforeStart { .. } | Problem::Overlap { .. } | Problem::Gap { .. } => {
+                Fix::EndAtNextStart
+            }
+            Problem::PastDuration { .. } => Fix::ClampToDuration,
+            Problem::DuplicateId { .. } => Fix::RegenerateIds,
+            Problem::DanglingReference { .. } => Fix::RemoveDangling,
+            Problem::NoTopLevel => Fix::PromoteTopLevel,
+        }
+    }
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Problem::OutOfOrder { element_id } => {
+                write!(f, "{} comes after a chapter that starts later", element_id)
+            }
+            Problem::EndsBeforeStart { element_id } => {
+                write!(f, "{} ends before it starts", element_id)
+            }
+            Problem::Overlap {
+                element_id,
+                previous,
+            } => write!(f, "{} starts before {} ends", element_id, previous),
+            Problem::Gap {
+                element_id,
+                previous,
+                ms,
+            } => write!(
+                f,
+                "{} starts {} after {} ends",
+                element_id,
+                format_time(*ms),
+                previous
+            ),
+            Problem::PastDuration {
+                element_id,
+                end_time,
+                duration,
+            } => write!(
+                f,
+                "{} ends at {}, after the audio ends at {}",
+                element_id,
+                format_time(*end_time),
+                format_time(*duration)
+            ),
+            Problem::DuplicateId { element_id } => {
+                write!(f, "More than one element has the ID {}", element_id)
+            }
+            Problem::DanglingReference { toc, element_id } => write!(
+                f,
+                "{} lists {}, but there is no such chapter or table",
+                toc, element_id
+            ),
+            Problem::NoTopLevel => f.write_str(
+                "None of the tables is the top level, so players may not list the chapters",
+            ),
+        }
+    }
+}
+
+/// A change that clears up one kind of [`Problem`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Fix {
+    /// Puts the chapters in start time order.
+    Sort,
+    /// Ends each chapter where the next one starts, and the last one where
+    /// the audio ends.
+    EndAtNextStart,
+    /// Keeps every chapter within the audio.
+    ClampToDuration,
+    /// Drops table entries that point at nothing.
+    RemoveDangling,
+    /// Numbers the chapters `chp0`, `chp1`, … in start time order.
+    RegenerateIds,
+    /// Makes the first table no other table lists the top level.
+    PromoteTopLevel,
+}
+
+impl fmt::Display for Fix {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Fix::Sort => "Sort by start time",
+            Fix::EndAtNextStart => "End each chapter where the next starts",
+            Fix::ClampToDuration => "Keep chapters within the audio",
+            Fix::RemoveDangling => "Remove missing entries from tables",
+            Fix::RegenerateIds => "Renumber chapter IDs",
+            Fix::PromoteTopLevel => "Make a table the top level",
+        })
+    }
+}
+
+/// Checks the tag's chapters against each other, its tables of contents
+/// and, when it is known, the length of the audio in milliseconds.
+pub fn check(tag: &Tag, duration: Option<u32>) -> Vec<Problem> {
+    let mut problems = Vec::new();
+    let chapters: Vec<&Chapter> = tag.chapters().collect();
+
+    let mut latest_start = 0;
+    for chapter in &chapters {
+        if chapter.start_time < latest_start {
+            problems.push(Problem::OutOfOrder {
+                element_id: chapter.element_id.clone(),
+            });
+        }
+        latest_start = latest_start.max(chapter.start_time);
+        if chapter.end_time < chapter.start_time {
+            problems.push(Problem::EndsBeforeStart {
+                element_id: chapter.element_id.clone(),
+            });
+        }
+        if let Some(duration) = duration.filter(|d| chapter.end_time > *d) {
+            problems.push(Problem::PastDuration {
+                element_id: chapter.element_id.clone(),
+                end_time: chapter.end_time,
+                duration,
+            });
+        }
+    }
+
+    // Hidden chapters, such as picture changes, may start partway through
+    // another and are not part of the sequence.
+    let hidden = toc::hidden(tag);
+    let mut by_start: Vec<&Chapter> = chapters
+        .iter()
+        .copied()
+        .filter(|c| !hidden.contains(&c.element_id))
+        .collect();
+    by_start.sort_by_key(|c| c.start_time);
+    for pair in by_start.windows(2) {
+        let (previous, chapter) = (pair[0], pair[1]);
+        if chapter.start_time < previous.end_time {
+            problems.push(Problem::Overlap {
+                element_id: chapter.element_id.clone(),
+                previous: previous.element_id.clone(),
+            });
+        } else if chapter.start_time > previous.end_time && previous.end_time >= previous.start_time
+        {
+            problems.push(Problem::Gap {
+                element_id: chapter.element_id.clone(),
+                previous: previous.element_id.clone(),
+                ms: chapter.start_time - previous.end_time,
+            });
+        }
+    }
+
+    let ids: Vec<&str> = element_ids(tag).collect();
+    for (i, id) in ids.iter().enumerate() {
+        if ids[..i].contains(id) {
+            let problem = Problem::DuplicateId {
+                element_id: id.to_string(),
+            };
+            if !problems.contains(&problem) {
+                problems.push(problem);
+            }
+        }
+    }
+    for table in tag.tables_of_contents() {
+        for element_id in &table.elements {
+            if !ids.contains(&element_id.as_str()) {
+                problems.push(Problem::DanglingReference {
+                    toc: table.element_id.clone(),
+                    element_id: element_id.clone(),
+                });
+            }
+        }
+    }
+    if tag.tables_of_contents().next().is_some() && toc::top_level(tag).is_none() {
+        problems.push(Problem::NoTopLevel);
+    }
+    problems
+}
+
+/// Applies `fix` to the tag. `duration` is the length of the audio in
+/// milliseconds, which [`Fix::ClampToDuration`] needs.
+pub fn apply(tag: &mut Tag, fix: Fix, duration: Option<u32>) {
+    match fix {
+        Fix::Sort => rebuild_chapters(tag, |chapters| {
+            chapters.sort_by_key(|c| c.start_time);
+        }),
+        Fix::EndAtNextStart => {
+            let hidden = toc::hidden(tag);
+            rebuild_chapters(tag, |chapters| {
+                let mut order: Vec<usize> = (0..chapters.len())
+                    .filter(|i| !hidden.contains(&chapters[*i].element_id))
+                    .collect();
+                order.sort_by_key(|i| chapters[*i].start_time);
+                for pair in order.windows(2) {
+                    chapters[pair[0]].end_time = chapters[pair[1]].start_time;
+                }
+                if let Some(last) = order.last().map(|i| &mut chapters[*i]) {
+                    last.end_time = match duration {
+                        Some(duration) => duration.max(last.start_time),
+                        None => last.end_time.max(last.start_time),
+                    };
+                }
+            })
+        }
+        Fix::ClampToDuration => {
+            let Some(duration) = duration else {
+                return;
+            };
+            rebuild_chapters(tag, |chapters| {
+                for chapter in chapters {
+                    chapter.start_time = chapter.start_time.min(duration);
+                    chapter.end_time = chapter.end_time.min(duration);
+                }
+            });
+        }
+        Fix::RemoveDangling => {
+            let ids: Vec<String> = element_ids(tag).map(str::to_string).collect();
+            let dangling: Vec<String> = tag
+                .tables_of_contents()
+                .flat_map(|t| t.elements.iter())
+                .filter(|e| !ids.contains(e))
+                .cloned()
+                .collect();
+            for element_id in dangling {
+                toc::remove_references(tag, &element_id);
+            }
+        }
+        Fix::RegenerateIds => regenerate_ids(tag),
+        Fix::PromoteTopLevel => {
+            if toc::top_level(tag).is_some() {
+                return;
+            }
+            let listed: Vec<String> = tag
+                .tables_of_contents()
+                .flat_map(|t| t.elements.iter().cloned())
+                .collect();
+            let root = tag
+                .tables_of_contents()
+                .find(|t| !listed.contains(&t.element_id))
+                .or_else(|| tag.tables_of_contents().next())
+                .cloned();
+            if let Some(root) = root {
+                toc::set_toc(
+                    tag,
+                    TableOfContents {
+                        top_level: true,
+                        ..root
+                    },
+                );
+            }
+        }
+    }
+}
+
+/// Gives the chapters new IDs in start time order. Tables list the new ID of
+/// the first chapter that had the old one.
+fn regenerate_ids(tag: &mut Tag) {
+    let mut renamed: Vec<(String, String)> = Vec::new();
+    let mut order: Vec<(usize, u32)> = tag
+        .chapters()
+        .enumerate()
+        .map(|(i, c)| (i, c.start_time))
+        .collect();
+    order.sort_by_key(|(_, start)| *start);
+
+    // Start from a tag without chapters, so every `chpN` is free again
+    // unless a table uses it.
+    let mut scratch = Tag::new();
+    for table in tag.tables_of_contents() {
+        scratch.add_frame(table.clone());
+    }
+    let mut new_ids = vec![String::new(); order.len()];
+    for (i, _) in &order {
+        let id = next_element_id(&scratch);
+        scratch.add_frame(Chapter {
+            element_id: id.clone(),
+            start_time: 0,
+            end_time: 0,
+            start_offset: 0,
+            end_offset: 0,
+            frames: Vec::new(),
+        });
+        new_ids[*i] = id;
+    }
+
+    rebuild_chapters(tag, |chapters| {
+        for (chapter, new_id) in chapters.iter_mut().zip(&new_ids) {
+            if !renamed.iter().any(|(old, _)| *old == chapter.element_id) {
+                renamed.push((chapter.element_id.clone(), new_id.clone()));
+            }
+            chapter.element_id = new_id.clone();
+        }
+    });
+    let tables: Vec<TableOfContents> = tag
+        .tables_of_contents()
+        .map(|t| TableOfContents {
+            elements: t
+                .elements
+                .iter()
+                .map(|e| match renamed.iter().find(|(old, _)| old == e) {
+                    Some((_, new)) => new.clone(),
+                    None => e.clone(),
+                })
+                .collect(),
+            ..t.clone()
+        })
+        .collect();
+    rebuild(tag, |frame| {
+        let table = frame.content().table_of_contents()?;
+        tables
+            .iter()
+            .find(|t| t.element_id == table.element_id)
+            .map(|t| Frame::with_content("CTOC", Content::TableOfContents(t.clone())))
+    });
+}
+
+/// Every chapter and table element ID, duplicates included.
+fn element_ids(tag: &Tag) -> impl Iterator<Item = &str> {
+    tag.chapters()
+        .map(|c| c.element_id.as_str())
+        .chain(tag.tables_of_contents().map(|t| t.element_id.as_str()))
+}
+
+/// Lets `edit` change, but not add or remove, the tag's chapters, then puts
+/// them back in the places the chapters had in the tag. Works by position,
+/// so chapters that share an element ID are told apart.
+fn rebuild_chapters(tag: &mut Tag, edit: impl FnOnce(&mut Vec<Chapter>)) {
+    let mut chapters: Vec<Chapter> = tag.chapters().cloned().collect();
+    edit(&mut chapters);
+    let mut chapters = chapters.into_iter();
+    rebuild(tag, |frame| {
+        frame.content().chapter()?;
+        chapters
+            .next()
+            .map(|c| Frame::with_content("CHAP", Content::Chapter(c)))
+    });
+}
+
+/// Rebuilds the tag with the frames `replace` returns in place of the old
+/// ones it is given; None keeps a frame as it is.
+fn rebuild(tag: &mut Tag, mut replace: impl FnMut(&Frame) -> Option<Frame>) {
+    let frames: Vec<Frame> = tag
+        .frames()
+        .map(|f| replace(f).unwrap_or_else(|| f.clone()))
+        .collect();
+    let mut rebuilt = Tag::with_version(tag.version());
+    rebuilt.extend(frames);
+    *tag = rebuilt;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chapters::add_chapter;
+
+    fn chapter(element_id: &str, start_time: u32, end_time: u32) -> Frame {
+        Frame::with_content(
+            "CHAP",
+            Content::Chapter(Chapter {
+                element_id: element_id.to_string(),
+                start_time,
+                end_time,
+                start_offset: 0,
+                end_offset: 0,
+                frames: Vec::new(),
+            }),
+        )
+    }
+
+    fn times(tag: &Tag) -> Vec<(&str, u32, u32)> {
+        tag.chapters()
+            .map(|c| (c.element_id.as_str(), c.start_time, c.end_time))
+            .collect()
+    }
+
+    #[test]
+    fn consistent_chapters_have_no_problems() {
+        let mut tag = Tag::new();
+        add_chapter(&mut tag, 0, 1000, "Intro");
+        add_chapter(&mut tag, 1000, 2000, "Main");
+        toc::sync(&mut tag);
+        assert_eq!(check(&tag, Some(2000)), []);
+    }
+
+    #[test]
+    fn tables_without_a_top_level_are_reported() {
+        // Some taggers write a CTOC without the top-level flag.
+        let mut written = Tag::new();
+        written.add_frame(chapter("chp0", 0, 1000));
+        written.add_frame(chapter("chp1", 500, 2000));
+        written.add_frame(TableOfContents {
+            element_id: "toc".to_string(),
+            top_level: false,
+            ordered: true,
+            elements: vec!["chp0".to_string()],
+            frames: Vec::new(),
+        });
+        let mut bytes = Vec::new();
+        written.write_to(&mut bytes, id3::Version::Id3v24).unwrap();
+        let mut tag = Tag::read_from2(std::io::Cursor::new(bytes)).unwrap();
+
+        let problems = check(&tag, Some(2000));
+        assert!(problems.contains(&Problem::NoTopLevel));
+        assert!(problems.contains(&Problem::Overlap {
+            element_id: "chp1".to_string(),
+            previous: "chp0".to_string(),
+        }));
+
+        // As the editor applies fixes, see `toc::sync_except`.
+        let hidden = toc::hidden(&tag);
+        apply(&mut tag, Fix::PromoteTopLevel, Some(2000));
+        toc::sync_except(&mut tag, &hidden);
+        let top = toc::top_level(&tag).unwrap();
+        assert_eq!(top.element_id, "toc");
+        assert_eq!(top.elements, ["chp0", "chp1"]);
+        assert!(!check(&tag, Some(2000)).contains(&Problem::NoTopLevel));
+    }
+
+    #[test]
+    fn hidden_chapters_may_overlap() {
+        let mut tag = Tag::new();
+        add_chapter(&mut tag, 0, 1000, "Intro");
+        add_chapter(&mut tag, 500, 700, "Picture change");
+        toc::sync(&mut tag);
+        toc::remove_references(&mut tag, "chp1");
+        assert_eq!(check(&tag, Some(1000)), []);
+
+        apply(&mut tag, Fix::EndAtNextStart, Some(1000));
+        assert_eq!(times(&tag), [("chp0", 0, 1000), ("chp1", 500, 700)]);
+    }
+
+    #[test]
+    fn timing_problems_are_found_and_fixed() {
+        let mut tag = Tag::new();
+        tag.extend([
+            chapter("chp0", 0, 1200),
+            chapter("chp2", 3000, 9000),
+            chapter("chp1", 1000, 2000),
+        ]);
+        let problems = check(&tag, Some(8000));
+        assert_eq!(
+            problems,
+            [
+                Problem::PastDuration {
+                    element_id: "chp2".to_string(),
+                    end_time: 9000,
+                    duration: 8000
+                },
+                Problem::OutOfOrder {
+                    element_id: "chp1".to_string()
+                },
+                Problem::Overlap {
+                    element_id: "chp1".to_string(),
+                    previous: "chp0".to_string()
+                },
+                Problem::Gap {
+                    element_id: "chp2".to_string(),
+                    previous: "chp1".to_string(),
+                    ms: 1000
+                },
+            ]
+        );
+
+        apply(&mut tag, Fix::Sort, Some(8000));
+        apply(&mut tag, Fix::EndAtNextStart, Some(8000));
+        assert_eq!(
+            times(&tag),
+            [
+                ("chp0", 0, 1000),
+                ("chp1", 1000, 3000),
+                ("chp2", 3000, 8000)
+            ]
+        );
+        assert_eq!(check(&tag, Some(8000)), []);
+    }
+
+    #[test]
+    fn clamping_keeps_chapters_within_the_audio() {
+        let mut tag = Tag::new();
+        tag.extend([chapter("chp0", 0, 5000), chapter("chp1", 9000, 12000)]);
+        apply(&mut tag, Fix::ClampToDuration, None);
+        assert_eq!(times(&tag), [("chp0", 0, 5000), ("chp1", 9000, 12000)]);
+        apply(&mut tag, Fix::ClampToDuration, Some(8000));
+        assert_eq!(times(&tag), [("chp0", 0, 5000), ("chp1", 8000, 8000)]);
+    }
+
+    #[test]
+    fn ids_and_references_are_repaired() {
+        let mut tag = Tag::new();
+        tag.extend([
+            chapter("intro", 0, 1000),
+            chapter("intro", 1000, 2000),
+            Frame::with_content(
+                "CTOC",
+                Content::TableOfContents(TableOfContents {
+                    element_id: "toc".to_string(),
+                    top_level: true,
+                    ordered: true,
+                    elements: vec!["intro".to_string(), "outro".to_string()],
+                    frames: Vec::new(),
+                }),
+            ),
+        ]);
+        assert_eq!(
+            check(&tag, None),
+            [
+                Problem::DuplicateId {
+                    element_id: "intro".to_string()
+                },
+                Problem::DanglingReference {
+                    toc: "toc".to_string(),
+                    element_id: "outro".to_string()
+                },
+            ]
+        );
+
+        apply(&mut tag, Fix::RegenerateIds, None);
+        apply(&mut tag, Fix::RemoveDangling, None);
+        assert_eq!(times(&tag), [("chp0", 0, 1000), ("chp1", 1000, 2000)]);
+        assert_eq!(toc::top_level(&tag).unwrap().elements, ["chp0"]);
+        toc::sync(&mut tag);
+        assert_eq!(check(&tag, None), []);
+    }
+}
@@ -0,0 +1,406 @@
+//! Chapter files from podcast hosts: Podcasting 2.0 JSON chapters and
+//! Podlove Simple Chapters, read into and written from `CHAP` frames and a
+//! top-level `CTOC`.
+
+use std::fmt::Write as _;
+
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::engine::Engine as _;
+use id3::frame::{Chapter, Picture, PictureType, TableOfContents};
+use id3::{Tag, TagLike};
+use quick_xml::escape::escape;
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::Reader;
+use serde::{Deserialize, Serialize};
+
+use crate::art::{self, LINKED_MIME};
+use crate::chapters::{self, format_time, parse_time, NO_OFFSET};
+use crate::frame::retain_frames;
+use crate::toc;
+use crate::{Error, Result};
+
+/// The Podcasting 2.0 chapters version written.
+pub const JSON_VERSION: &str = "1.2.0";
+
+/// The MIME type of Podcasting 2.0 chapters.
+pub const JSON_MIME: &str = "application/json+chapters";
+
+/// The XML namespace of Podlove Simple Chapters.
+pub const PODLOVE_NAMESPACE: &str = "http://podlove.org/simple-chapters";
+
+/// A Podcasting 2.0 chapters file. Fields with no ID3 counterpart, such as
+/// `author` or a chapter's `location`, are skipped when reading.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct PodcastChapters {
+    #[serde(default = "json_version")]
+    pub version: String,
+    pub chapters: Vec<PodcastChapter>,
+}
+
+/// One chapter, timed in seconds.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PodcastChapter {
+    pub start_time: f64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub end_time: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    /// The chapter's picture: a web address or a `data:` URL.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub img: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    /// Whether players list the chapter. Unlisted ones usually just change
+    /// the picture partway through a chapter.
+    #[serde(default = "listed", skip_serializing_if = "is_listed")]
+    pub toc: bool,
+}
+
+fn json_version() -> String {
+    JSON_VERSION.to_string()
+}
+
+fn listed() -> bool {
+    true
+}
+
+fn is_listed(toc: &bool) -> bool {
+    *toc
+}
+
+/// The tag's chapters by start time. Chapters no table of contents lists
+/// get `toc: false`, and embedded pictures become `data:` URLs.
+pub fn export(tag: &Tag) -> PodcastChapters {
+    let hidden = toc::hidden(tag);
+    let mut sorted: Vec<&Chapter> = tag.chapters().collect();
+    sorted.sort_by_key(|c| c.start_time);
+    PodcastChapters {
+        version: json_version(),
+        chapters: sorted
+            .into_iter()
+            .map(|c| PodcastChapter {
+                start_time: to_seconds(c.start_time),
+                end_time: Some(to_seconds(c.end_time)),
+                title: chapters::chapter_title(c).map(String::from),
+                img: chapters::chapter_picture(c).map(img),
+                url: chapters::chapter_url(c).map(String::from),
+                toc: !hidden.contains(&c.element_id),
+            })
+            .collect(),
+    }
+}
+
+/// Replaces the tag's chapters and tables of contents with `chapters`, in
+/// start order, and a top-level table listing those with `toc` set.
+/// Chapters without an end run until the next one of the same kind starts,
+/// the last ones to `duration` in milliseconds, if known.
+pub fn import(tag: &mut Tag, chapters: &PodcastChapters, duration: Option<u32>) {
+    retain_frames(tag, |f| f.id() != "CHAP" && f.id() != "CTOC");
+    let mut entries: Vec<&PodcastChapter> = chapters.chapters.iter().collect();
+    entries.sort_by(|a, b| a.start_time.total_cmp(&b.start_time));
+
+    let mut listed = Vec::new();
+    for (i, entry) in entries.iter().enumerate() {
+        let start_time = to_ms(entry.start_time);
+        let end_time = entry
+            .end_time
+            .map(to_ms)
+            .or_else(|| {
+                entries[i + 1..]
+                    .iter()
+                    .find(|next| next.toc == entry.toc)
+                    .map(|next| to_ms(next.start_time))
+            })
+            .or(duration)
+            .unwrap_or(start_time);
+        let mut chapter = Chapter {
+            element_id: chapters::next_element_id(tag),
+            start_time,
+            end_time,
+            start_offset: NO_OFFSET,
+            end_offset: NO_OFFSET,
+            frames: Vec::new(),
+        };
+        if let Some(title) = &entry.title {
+            chapters::set_chapter_title(&mut chapter, title);
+        }
+        if let Some(url) = &entry.url {
+            chapters::set_chapter_url(&mut chapter, url);
+        }
+        if let Some(img) = &entry.img {
+            chapters::set_chapter_picture(&mut chapter, Some(picture(img)));
+        }
+        if entry.toc {
+            listed.push(chapter.element_id.clone());
+        }
+        tag.add_frame(chapter);
+    }
+
+    if !entries.is_empty() {
+        tag.add_frame(TableOfContents {
+            element_id: toc::next_toc_id(tag),
+            top_level: true,
+            ordered: true,
+            elements: listed,
+            frames: Vec::new(),
+        });
+    }
+}
+
+/// Reads a chapters file in either format, telling them apart by whether
+/// it is XML.
+pub fn parse(text: &str) -> Result<PodcastChapters> {
+    if text.trim_start().starts_with('<') {
+        from_podlove(text)
+    } else {
+        from_json(text)
+    }
+}
+
+/// Reads Podcasting 2.0 JSON chapters.
+pub fn from_json(text: &str) -> Result<PodcastChapters> {
+    serde_json::from_str(text).map_err(invalid)
+}
+
+/// Writes Podcasting 2.0 JSON chapters.
+pub fn to_json(chapters: &PodcastChapters) -> String {
+    serde_json::to_string_pretty(chapters).expect("chapters always serialize")
+}
+
+/// Reads Podlove Simple Chapters. The `psc` prefix is optional; start times
+/// are `HH:MM:SS.mmm`, with the hours and milliseconds optional, or seconds.
+pub fn from_podlove(text: &str) -> Result<PodcastChapters> {
+    let mut reader = Reader::from_str(text);
+    let mut found = false;
+    let mut chapters = Vec::new();
+    loop {
+        match reader.read_event().map_err(invalid)? {
+            Event::Start(element) | Event::Empty(element) => match element.local_name().as_ref() {
+                b"chapters" => found = true,
+                b"chapter" => chapters.push(podlove_chapter(&element)?),
+                _ => {}
+            },
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+    if !found {
+        return Err(Error::Chapters("no Podlove chapters element".to_string()));
+    }
+    Ok(PodcastChapters {
+        version: json_version(),
+        chapters,
+    })
+}
+
+/// Writes Podlove Simple Chapters. The format has no end times and lists
+/// every chapter, so chapters without `toc` are left out.
+pub fn to_podlove(chapters: &PodcastChapters) -> String {
+    let mut xml = format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<psc:chapters version=\"1.2\" xmlns:psc=\"{}\">\n",
+        PODLOVE_NAMESPACE
+    );
+    for chapter in chapters.chapters.iter().filter(|c| c.toc) {
+        let _ = write!(
+            xml,
+            "  <psc:chapter start=\"{}\" title=\"{}\"",
+            format_time(to_ms(chapter.start_time)),
+            escape(chapter.title.as_deref().unwrap_or_default())
+        );
+        for (name, value) in [("href", &chapter.url), ("image", &chapter.img)] {
+            if let Some(value) = value {
+                let _ = write!(xml, " {}=\"{}\"", name, escape(value));
+            }
+        }
+        xml.push_str("/>\n");
+    }
+    xml.push_str("</psc:chapters>\n");
+    xml
+}
+
+fn podlove_chapter(element: &BytesStart) -> Result<PodcastChapter> {
+    let mut chapter = PodcastChapter {
+        start_time: 0.0,
+        end_time: None,
+        title: None,
+        img: None,
+        url: None,
+        toc: true,
+    };
+    let mut start = None;
+    for attribute in element.attributes() {
+        let attribute = attribute.map_err(invalid)?;
+        let value = attribute.unescape_value().map_err(invalid)?.into_owned();
+        match attribute.key.local_name().as_ref() {
+            b"start" => start = Some(value),
+            b"title" => chapter.title = Some(value),
+            b"href" => chapter.url = Some(value),
+            b"image" => chapter.img = Some(value),
+            _ => {}
+        }
+    }
+    let start = start.ok_or_else(|| Error::Chapters("a chapter has no start".to_string()))?;
+    let ms = podlove_start(&start)
+        .ok_or_else(|| Error::Chapters(format!("bad start time {:?}", start)))?;
+    chapter.start_time = to_seconds(ms);
+    Ok(chapter)
+}
+
+/// Unlike [`parse_time`], a plain number is seconds here.
+fn podlove_start(s: &str) -> Option<u32> {
+    let s = s.trim();
+    if s.contains([':', '.']) {
+        parse_time(s)
+    } else {
+        s.parse::<u32>().ok()?.checked_mul(1000)
+    }
+}
+
+/// An `img` for a picture: a linked picture's URL, or the image as a
+/// `data:` URL.
+fn img(picture: &Picture) -> String {
+    art::linked_url(picture).unwrap_or_else(|| {
+        format!(
+            "data:{};base64,{}",
+            art::display_mime(picture),
+            BASE64.encode(&picture.data)
+        )
+    })
+}
+
+/// The picture for an `img`: embedded for a base64 `data:` URL, linked
+/// otherwise.
+fn picture(img: &str) -> Picture {
+    let embedded = img
+        .strip_prefix("data:")
+        .and_then(|rest| rest.split_once(";base64,"))
+        .and_then(|(mime_type, data)| Some((mime_type.to_string(), BASE64.decode(data).ok()?)));
+    let (mime_type, data) =
+        embedded.unwrap_or_else(|| (LINKED_MIME.to_string(), img.as_bytes().to_vec()));
+    Picture {
+        mime_type,
+        picture_type: PictureType::Other,
+        description: String::new(),
+        data,
+    }
+}
+
+fn to_ms(seconds: f64) -> u32 {
+    (seconds * 1000.0).round() as u32
+}
+
+fn to_seconds(ms: u32) -> f64 {
+    f64::from(ms) / 1000.0
+}
+
+fn invalid(err: impl std::fmt::Display) -> Error {
+    Error::Chapters(err.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chapters::{add_chapter, chapter_picture, chapter_title, chapter_url};
+
+    const JSON: &str = r#"{
+        "version": "1.2.0",
+        "author": "Someone",
+        "chapters": [
+            {"startTime": 0, "title": "Intro", "url": "https://example.com/intro"},
+            {"startTime": 30.5, "title": "News", "img": "https://example.com/news.jpg"},
+            {"startTime": 45, "img": "data:image/png;base64,iVBORw==", "toc": false},
+            {"startTime": 90, "endTime": 120, "title": "Outro", "location": {"name": "Here"}}
+        ]
+    }"#;
+
+    #[test]
+    fn json_chapters_become_frames_and_back() {
+        let mut tag = Tag::new();
+        add_chapter(&mut tag, 0, 1000, "Old");
+        toc::sync(&mut tag);
+        let file = from_json(JSON).unwrap();
+        import(&mut tag, &file, Some(150_000));
+
+        let imported: Vec<&Chapter> = tag.chapters().collect();
+        let times: Vec<(u32, u32)> = imported
+            .iter()
+            .map(|c| (c.start_time, c.end_time))
+            .collect();
+        assert_eq!(
+            times,
+            [
+                (0, 30_500),
+                (30_500, 90_000),
+                (45_000, 150_000),
+                (90_000, 120_000)
+            ]
+        );
+        assert_eq!(chapter_title(imported[0]), Some("Intro"));
+        assert_eq!(chapter_url(imported[0]), Some("https://example.com/intro"));
+        let linked = chapter_picture(imported[1]).unwrap();
+        assert_eq!(linked.mime_type, LINKED_MIME);
+        let embedded = chapter_picture(imported[2]).unwrap();
+        assert_eq!(embedded.mime_type, "image/png");
+        assert_eq!(embedded.data, [0x89, b'P', b'N', b'G']);
+        assert_eq!(
+            toc::top_level(&tag).unwrap().elements,
+            ["chp0", "chp1", "chp3"]
+        );
+
+        let exported = export(&tag);
+        assert_eq!(exported.chapters.len(), 4);
+        assert_eq!(exported.chapters[1].start_time, 30.5);
+        assert_eq!(exported.chapters[1].end_time, Some(90.0));
+        assert_eq!(
+            exported.chapters[1].img.as_deref(),
+            Some("https://example.com/news.jpg")
+        );
+        assert_eq!(
+            exported.chapters[2].img.as_deref(),
+            Some("data:image/png;base64,iVBORw==")
+        );
+        assert!(!exported.chapters[2].toc);
+        let json = to_json(&exported);
+        assert!(json.contains("\"startTime\": 30.5"));
+        assert!(json.contains("\"toc\": false"));
+        assert_eq!(from_json(&json).unwrap(), exported);
+    }
+
+    #[test]
+    fn podlove_chapters_are_read_and_written() {
+        let xml = r#"<?xml version="1.0" encoding="UTF-8"?>
+            <psc:chapters version="1.2" xmlns:psc="http://podlove.org/simple-chapters">
+                <psc:chapter start="0" title="Intro"/>
+                <psc:chapter start="01:02.5" title="Q &amp; A" href="https://example.com"/>
+                <psc:chapter start="1:00:00" title="Late" image="https://example.com/late.jpg"/>
+            </psc:chapters>"#;
+        let file = parse(xml).unwrap();
+        let starts: Vec<f64> = file.chapters.iter().map(|c| c.start_time).collect();
+        assert_eq!(starts, [0.0, 62.5, 3600.0]);
+        assert_eq!(file.chapters[1].title.as_deref(), Some("Q & A"));
+        assert_eq!(file.chapters[1].url.as_deref(), Some("https://example.com"));
+
+        let mut tag = Tag::new();
+        import(&mut tag, &file, None);
+        let ends: Vec<u32> = tag.chapters().map(|c| c.end_time).collect();
+        assert_eq!(ends, [62_500, 3_600_000, 3_600_000]);
+
+        let mut exported = export(&tag);
+        exported.chapters[0].toc = false;
+        let written = to_podlove(&exported);
+        assert!(!written.contains("Intro"));
+        assert!(written.contains(
+            r#"<psc:chapter start="00:01:02.500" title="Q &amp; A" href="https://example.com"/>"#
+        ));
+        assert_eq!(from_podlove(&written).unwrap().chapters.len(), 2);
+    }
+
+    #[test]
+    fn broken_files_are_errors() {
+        assert!(parse("{\"chapters\": [{\"title\": \"No start\"}]}").is_err());
+        assert!(parse("<rss><channel/></rss>").is_err());
+        assert!(parse("<chapters><chapter start=\"soon\"/></chapters>").is_err());
+        assert!(parse("<chapters></chapters>").unwrap().chapters.is_empty());
+    }
+}
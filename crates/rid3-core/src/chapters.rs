@@ -0,0 +1,435 @@
+use id3::frame::{Chapter, ExtendedLink, Picture};
+use id3::{Content, Frame, Tag, TagLike};
+
+use crate::frame::retain_frames;
+
+/// Offsets stored in `CHAP` frames that are positioned by time only.
+pub const NO_OFFSET: u32 = 0xffff_ffff;
+
+/// The `TIT2` title stored inside a chapter, if any.
+pub fn chapter_title(chapter: &Chapter) -> Option<&str> {
+    chapter
+        .frames
+        .iter()
+        .find(|f| f.id() == "TIT2")
+        .and_then(|f| f.content().text())
+}
+
+/// The URL a chapter links to, from its first `WXXX` frame.
+pub fn chapter_url(chapter: &Chapter) -> Option<&str> {
+    chapter
+        .frames
+        .iter()
+        .find_map(|f| f.content().extended_link())
+        .map(|l| l.link.as_str())
+}
+
+/// The picture shown during a chapter, from its first `APIC` frame.
+pub fn chapter_picture(chapter: &Chapter) -> Option<&Picture> {
+    chapter.frames.iter().find_map(|f| f.content().picture())
+}
+
+/// Sets the chapter's `TIT2` title. An empty title removes it.
+pub fn set_chapter_title(chapter: &mut Chapter, title: &str) {
+    let frame = (!title.is_empty()).then(|| Frame::text("TIT2", title));
+    set_sub_frame(chapter, "TIT2", frame);
+}
+
+/// Sets the URL of the chapter's `WXXX` frame. An empty URL removes it.
+pub fn set_chapter_url(chapter: &mut Chapter, url: &str) {
+    let frame = (!url.is_empty()).then(|| {
+        Frame::from(ExtendedLink {
+            description: String::new(),
+            link: url.to_string(),
+        })
+    });
+    set_sub_frame(chapter, "WXXX", frame);
+}
+
+/// Sets the chapter's `APIC` picture, or removes it.
+pub fn set_chapter_picture(chapter: &mut Chapter, picture: Option<Picture>) {
+    set_sub_frame(chapter, "APIC", picture.map(Frame::from));
+}
+
+/// Puts `frame` in place of the chapter's first frame with this ID, keeping
+/// its position, or appends it. Other frames with the ID are removed.
+fn set_sub_frame(chapter: &mut Chapter, id: &str, frame: Option<Frame>) {
+    let position = chapter.frames.iter().position(|f| f.id() == id);
+    chapter.frames.retain(|f| f.id() != id);
+    if let Some(frame) = frame {
+        let index = position.unwrap_or(chapter.frames.len());
+        chapter.frames.insert(index, frame);
+    }
+}
+
+/// Replaces the chapter that has the same element ID as `chapter`, keeping
+/// its place in the tag. Returns false if there was none.
+pub fn set_chapter(tag: &mut Tag, chapter: Chapter) -> bool {
+    let key = format!("CHAP:{}", chapter.element_id);
+    crate::frame::set_content(tag, &key, Content::Chapter(chapter))
+}
+
+/// Replaces the chapter at `index` in tag order, keeping its place in the
+/// tag. Unlike [`set_chapter`], this tells apart chapters that share an
+/// element ID. Returns false if there is no such chapter.
+pub fn set_chapter_at(tag: &mut Tag, index: usize, chapter: Chapter) -> bool {
+    let Some(position) = chapter_position(tag, index) else {
+        return false;
+    };
+    let mut frames: Vec<Frame> = tag.frames().cloned().collect();
+    let old = &frames[position];
+    let mut new =
+        Frame::with_content(old.id(), Content::Chapter(chapter)).set_encoding(old.encoding());
+    new.set_tag_alter_preservation(old.tag_alter_preservation());
+    new.set_file_alter_preservation(old.file_alter_preservation());
+    frames[position] = new;
+    let mut replaced = Tag::with_version(tag.version());
+    replaced.extend(frames);
+    *tag = replaced;
+    true
+}
+
+/// Where the chapter at `index` in tag order is among all the tag's frames.
+fn chapter_position(tag: &Tag, index: usize) -> Option<usize> {
+    tag.frames()
+        .enumerate()
+        .filter(|(_, f)| f.content().chapter().is_some())
+        .nth(index)
+        .map(|(position, _)| position)
+}
+
+/// Returns the first `chpN` element ID not used by any frame in the tag.
+pub fn next_element_id(tag: &Tag) -> String {
+    let used: Vec<&str> = tag
+        .chapters()
+        .map(|c| c.element_id.as_str())
+        .chain(tag.tables_of_contents().map(|t| t.element_id.as_str()))
+        .collect();
+    (0..)
+        .map(|n| format!("chp{}", n))
+        .find(|id| !used.contains(&id.as_str()))
+        .unwrap()
+}
+
+/// Adds a chapter with the given title and returns its element ID.
+pub fn add_chapter(tag: &mut Tag, start_time: u32, end_time: u32, title: &str) -> String {
+    let element_id = next_element_id(tag);
+    let mut frames = Vec::new();
+    if !title.is_empty() {
+        frames.push(Frame::with_content(
+            "TIT2",
+            Content::Text(title.to_string()),
+        ));
+    }
+    tag.add_frame(Chapter {
+        element_id: element_id.clone(),
+        start_time,
+        end_time,
+        start_offset: NO_OFFSET,
+        end_offset: NO_OFFSET,
+        frames,
+    });
+    element_id
+}
+
+/// Adds an untitled chapter starting at `at` ms, as when marking one while
+/// listening: the chapter that started before it now ends at `at`, and the
+/// new one runs to the next chapter's start, or to `duration`. Returns the
+/// new element ID, or None if a chapter already starts at `at`.
+pub fn mark_chapter(tag: &mut Tag, at: u32, duration: u32) -> Option<String> {
+    if tag.chapters().any(|c| c.start_time == at) {
+        return None;
+    }
+    // Hidden chapters, such as picture changes, are not part of the sequence.
+    let hidden = crate::toc::hidden(tag);
+    let next_start = tag
+        .chapters()
+        .filter(|c| !hidden.contains(&c.element_id))
+        .map(|c| c.start_time)
+        .filter(|start| *start > at)
+        .min();
+    let previous = tag
+        .chapters()
+        .enumerate()
+        .filter(|(_, c)| c.start_time < at && !hidden.contains(&c.element_id))
+        .max_by_key(|(_, c)| c.start_time)
+        .map(|(i, c)| (i, c.clone()));
+    if let Some((index, previous)) = previous {
+        set_chapter_at(
+            tag,
+            index,
+            Chapter {
+                end_time: at,
+                ..previous
+            },
+        );
+    }
+    let end_time = next_start.unwrap_or(duration).max(at);
+    Some(add_chapter(tag, at, end_time, ""))
+}
+
+/// Moves the start of a chapter by `delta_ms`, keeping it between 0 and the
+/// chapter's end. A chapter that ended where it started is moved along, so
+/// the two still meet, and the start stops at that chapter's own start.
+/// The chapter is the one at `index` in tag order. Returns false if there is
+/// no such chapter.
+pub fn nudge_chapter(tag: &mut Tag, index: usize, delta_ms: i64) -> bool {
+    let Some(chapter) = tag.chapters().nth(index) else {
+        return false;
+    };
+    let old = chapter.start_time;
+    let floor = tag
+        .chapters()
+        .enumerate()
+        .filter(|(i, c)| *i != index && c.end_time == old && c.start_time <= old)
+        .map(|(_, c)| c.start_time)
+        .max()
+        .unwrap_or(0)
+        .min(chapter.end_time);
+    let new =
+        (i64::from(old) + delta_ms).clamp(i64::from(floor), i64::from(chapter.end_time)) as u32;
+    let moved: Vec<(usize, Chapter)> = tag
+        .chapters()
+        .enumerate()
+        .filter_map(|(i, c)| {
+            let moved = if i == index {
+                Chapter {
+                    start_time: new,
+                    ..c.clone()
+                }
+            } else if c.end_time == old && c.start_time <= old {
+                Chapter {
+                    end_time: new,
+                    ..c.clone()
+                }
+            } else {
+                return None;
+            };
+            Some((i, moved))
+        })
+        .collect();
+    for (i, chapter) in moved {
+        set_chapter_at(tag, i, chapter);
+    }
+    true
+}
+
+/// Removes the chapter with the given element ID, and any table of contents'
+/// reference to it. Returns false if there was none.
+pub fn remove_chapter(tag: &mut Tag, element_id: &str) -> bool {
+    let before = tag.chapters().count();
+    retain_frames(tag, |f| {
+        f.content()
+            .chapter()
+            .is_none_or(|c| c.element_id != element_id)
+    });
+    if tag.chapters().count() == before {
+        return false;
+    }
+    crate::toc::remove_references(tag, element_id);
+    true
+}
+
+/// Removes the chapter at `index` in tag order. Tables of contents keep
+/// listing its element ID while another chapter has it. Returns false if
+/// there is no such chapter.
+pub fn remove_chapter_at(tag: &mut Tag, index: usize) -> bool {
+    let Some(element_id) = tag.chapters().nth(index).map(|c| c.element_id.clone()) else {
+        return false;
+    };
+    let mut n = 0;
+    retain_frames(tag, |f| {
+        if f.content().chapter().is_none() {
+            return true;
+        }
+        n += 1;
+        n - 1 != index
+    });
+    if !tag.chapters().any(|c| c.element_id == element_id) {
+        crate::toc::remove_references(tag, &element_id);
+    }
+    true
+}
+
+/// Formats milliseconds as `HH:MM:SS.mmm`.
+pub fn format_time(ms: u32) -> String {
+    format!(
+        "{:02}:{:02}:{:02}.{:03}",
+        ms / 3_600_000,
+        ms / 60_000 % 60,
+        ms / 1000 % 60,
+        ms % 1000
+    )
+}
+
+/// Parses a time given as plain milliseconds or as `[[HH:]MM:]SS[.mmm]`.
+pub fn parse_time(s: &str) -> Option<u32> {
+    let s = s.trim();
+    if !s.contains([':', '.']) {
+        return s.parse().ok();
+    }
+    let (clock, millis) = match s.split_once('.') {
+        Some((clock, frac)) if !frac.is_empty() && frac.len() <= 3 => {
+            let scale = 10u32.pow(3 - frac.len() as u32);
+            (clock, frac.parse::<u32>().ok()? * scale)
+        }
+        Some(_) => return None,
+        None => (s, 0),
+    };
+    let mut seconds: u32 = 0;
+    for part in clock.split(':') {
+        seconds = seconds.checked_mul(60)?.checked_add(part.parse().ok()?)?;
+    }
+    seconds.checked_mul(1000)?.checked_add(millis)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn added_chapters_get_unique_ids() {
+        let mut tag = Tag::new();
+        assert_eq!(add_chapter(&mut tag, 0, 1000, "Intro"), "chp0");
+        assert_eq!(add_chapter(&mut tag, 1000, 2000, "Main"), "chp1");
+        assert!(remove_chapter(&mut tag, "chp0"));
+        assert_eq!(add_chapter(&mut tag, 0, 1000, "Intro"), "chp0");
+
+        let titles: Vec<_> = tag.chapters().filter_map(chapter_title).collect();
+        assert_eq!(titles, ["Main", "Intro"]);
+    }
+
+    #[test]
+    fn removing_unknown_chapter_changes_nothing() {
+        let mut tag = Tag::new();
+        add_chapter(&mut tag, 0, 1000, "Intro");
+        assert!(!remove_chapter(&mut tag, "chp9"));
+        assert_eq!(tag.chapters().count(), 1);
+    }
+
+    #[test]
+    fn chapter_frames_are_set_in_place() {
+        let mut tag = Tag::new();
+        let element_id = add_chapter(&mut tag, 0, 1000, "Intro");
+        let mut chapter = tag.chapters().next().unwrap().clone();
+        set_chapter_url(&mut chapter, "https://example.com");
+        set_chapter_title(&mut chapter, "Welcome");
+        chapter.end_time = 2500;
+        assert!(set_chapter(&mut tag, chapter));
+
+        let chapter = tag.chapters().next().unwrap();
+        let ids: Vec<&str> = chapter.frames.iter().map(|f| f.id()).collect();
+        assert_eq!(ids, ["TIT2", "WXXX"]);
+        assert_eq!(chapter_title(chapter), Some("Welcome"));
+        assert_eq!(chapter_url(chapter), Some("https://example.com"));
+        assert_eq!(chapter.end_time, 2500);
+
+        let mut chapter = chapter.clone();
+        set_chapter_title(&mut chapter, "");
+        set_chapter_url(&mut chapter, "");
+        assert!(chapter.frames.is_empty());
+        chapter.element_id = "chp9".to_string();
+        assert!(!set_chapter(&mut tag, chapter));
+        assert_eq!(tag.chapters().next().unwrap().element_id, element_id);
+    }
+
+    #[test]
+    fn marking_closes_the_chapter_before() {
+        let mut tag = Tag::new();
+        assert_eq!(mark_chapter(&mut tag, 0, 60_000).as_deref(), Some("chp0"));
+        assert_eq!(
+            mark_chapter(&mut tag, 20_000, 60_000).as_deref(),
+            Some("chp1")
+        );
+        assert_eq!(
+            mark_chapter(&mut tag, 10_000, 60_000).as_deref(),
+            Some("chp2")
+        );
+        assert_eq!(mark_chapter(&mut tag, 10_000, 60_000), None);
+
+        let times: Vec<(&str, u32, u32)> = tag
+            .chapters()
+            .map(|c| (c.element_id.as_str(), c.start_time, c.end_time))
+            .collect();
+        assert_eq!(
+            times,
+            [
+                ("chp0", 0, 10_000),
+                ("chp1", 20_000, 60_000),
+                ("chp2", 10_000, 20_000)
+            ]
+        );
+    }
+
+    #[test]
+    fn chapters_sharing_an_id_are_edited_one_at_a_time() {
+        let mut tag = Tag::new();
+        add_chapter(&mut tag, 0, 1000, "Intro");
+        add_chapter(&mut tag, 1000, 2000, "Main");
+        let mut twin = tag.chapters().nth(1).unwrap().clone();
+        twin.element_id = "chp0".to_string();
+        set_chapter_at(&mut tag, 1, twin);
+        crate::toc::sync(&mut tag);
+
+        let mut second = tag.chapters().nth(1).unwrap().clone();
+        set_chapter_title(&mut second, "Outro");
+        assert!(set_chapter_at(&mut tag, 1, second));
+        let titles: Vec<_> = tag.chapters().filter_map(chapter_title).collect();
+        assert_eq!(titles, ["Intro", "Outro"]);
+
+        assert!(remove_chapter_at(&mut tag, 1));
+        let titles: Vec<_> = tag.chapters().filter_map(chapter_title).collect();
+        assert_eq!(titles, ["Intro"]);
+        let top = crate::toc::top_level(&tag).unwrap();
+        assert!(top.elements.contains(&"chp0".to_string()));
+        assert!(!remove_chapter_at(&mut tag, 1));
+    }
+
+    #[test]
+    fn nudging_moves_the_shared_boundary() {
+        let mut tag = Tag::new();
+        add_chapter(&mut tag, 0, 1000, "Intro");
+        add_chapter(&mut tag, 1000, 2000, "Main");
+        assert!(nudge_chapter(&mut tag, 1, -100));
+        assert!(nudge_chapter(&mut tag, 0, -100));
+        assert!(!nudge_chapter(&mut tag, 9, 100));
+
+        let times: Vec<(u32, u32)> = tag.chapters().map(|c| (c.start_time, c.end_time)).collect();
+        assert_eq!(times, [(0, 900), (900, 2000)]);
+    }
+
+    #[test]
+    fn nudging_stops_at_the_previous_start() {
+        let mut tag = Tag::new();
+        add_chapter(&mut tag, 0, 1000, "Intro");
+        add_chapter(&mut tag, 1000, 2000, "Main");
+        assert!(nudge_chapter(&mut tag, 1, -1500));
+
+        let times: Vec<(u32, u32)> = tag.chapters().map(|c| (c.start_time, c.end_time)).collect();
+        assert_eq!(times, [(0, 0), (0, 2000)]);
+    }
+
+    #[test]
+    fn marking_leaves_hidden_chapters_alone() {
+        let mut tag = Tag::new();
+        add_chapter(&mut tag, 0, 60_000, "A");
+        add_chapter(&mut tag, 30_000, 35_000, "Picture change");
+        crate::toc::sync(&mut tag);
+        crate::toc::remove_references(&mut tag, "chp1");
+        assert_eq!(
+            mark_chapter(&mut tag, 40_000, 90_000).as_deref(),
+            Some("chp2")
+        );
+
+        let times: Vec<(u32, u32)> = tag.chapters().map(|c| (c.start_time, c.end_time)).collect();
+        assert_eq!(times, [(0, 40_000), (30_000, 35_000), (40_000, 90_000)]);
+    }
+
+    #[test]
+    fn times_round_trip() {
+        assert_eq!(parse_time("1500"), Some(1500));
+        assert_eq!(parse_time("01:02.5"), Some(62_500));
+        assert_eq!(parse_time(&format_time(3_723_004)), Some(3_723_004));
+        assert_eq!(parse_time("1:xx"), None);
+    }
+}
@@ -0,0 +1,171 @@
+//! What changed between the tag as it was loaded and the tag being edited.
+
+use std::fmt;
+
+use id3::{Content, Frame, Tag};
+
+use crate::chapters::{chapter_title, format_time};
+use crate::frame::{display_value, key};
+
+/// A frame that was added, removed or modified. Frames are matched up by
+/// [`key`], so editing a `TXXX` value is a modification while changing its
+/// description removes one frame and adds another.
+#[derive(Clone, Debug, PartialEq)]
+pub enum FrameChange {
+    Added(Frame),
+    Removed(Frame),
+    Modified { before: Frame, after: Frame },
+}
+
+impl FrameChange {
+    /// The key of the frame that changed.
+    pub fn key(&self) -> String {
+        match self {
+            FrameChange::Added(frame) | FrameChange::Removed(frame) => key(frame),
+            FrameChange::Modified { after, .. } => key(after),
+        }
+    }
+}
+
+impl fmt::Display for FrameChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FrameChange::Added(frame) => write!(f, "Added {}: {}", key(frame), summary(frame)),
+            FrameChange::Removed(frame) => {
+                write!(f, "Removed {}: {}", key(frame), summary(frame))
+            }
+            FrameChange::Modified { before, after } => {
+                let (before_summary, after_summary) = (summary(before), summary(after));
+                if before_summary == after_summary {
+                    write!(f, "Changed {}", key(after))
+                } else {
+                    write!(
+                        f,
+                        "Changed {}: {} → {}",
+                        key(after),
+                        before_summary,
+                        after_summary
+                    )
+                }
+            }
+        }
+    }
+}
+
+/// The frames that differ between `before` and `after`, in the order they
+/// appear in `after`, followed by the frames that were removed.
+pub fn diff(before: &Tag, after: &Tag) -> Vec<FrameChange> {
+    let before = keyed(before);
+    let after = keyed(after);
+    let mut changes = Vec::new();
+    for (k, frame) in &after {
+        match before.iter().find(|(bk, _)| bk == k) {
+            Some((_, old)) if old != frame => changes.push(FrameChange::Modified {
+                before: (*old).clone(),
+                after: (*frame).clone(),
+            }),
+            Some(_) => {}
+            None => changes.push(FrameChange::Added((*frame).clone())),
+        }
+    }
+    for (k, frame) in &before {
+        if !after.iter().any(|(ak, _)| ak == k) {
+            changes.push(FrameChange::Removed((*frame).clone()));
+        }
+    }
+    changes
+}
+
+/// Pairs every frame with its key, numbering repeats of the same key so
+/// duplicate frames are matched up in order.
+fn keyed(tag: &Tag) -> Vec<((String, usize), &Frame)> {
+    let mut keyed: Vec<((String, usize), &Frame)> = Vec::new();
+    for frame in tag.frames() {
+        let k = key(frame);
+        let n = keyed.iter().filter(|((other, _), _)| *other == k).count();
+        keyed.push(((k, n), frame));
+    }
+    keyed
+}
+
+fn summary(frame: &Frame) -> String {
+    match frame.content() {
+        Content::Picture(picture) => format!(
+            "{} ({}, {} bytes)",
+            picture.picture_type,
+            picture.mime_type,
+            picture.data.len()
+        ),
+        Content::Chapter(chapter) => format!(
+            "{}–{} {}",
+            format_time(chapter.start_time),
+            format_time(chapter.end_time),
+            chapter_title(chapter).unwrap_or_default()
+        ),
+        _ => display_value(frame),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chapters::add_chapter;
+    use id3::frame::ExtendedText;
+    use id3::TagLike;
+
+    fn catalog(value: &str) -> ExtendedText {
+        ExtendedText {
+            description: "CATALOGNUMBER".to_string(),
+            value: value.to_string(),
+        }
+    }
+
+    #[test]
+    fn unchanged_tag_has_no_changes() {
+        let mut tag = Tag::new();
+        tag.set_title("Title");
+        add_chapter(&mut tag, 0, 1000, "Intro");
+        assert_eq!(diff(&tag, &tag.clone()), []);
+    }
+
+    #[test]
+    fn frames_are_matched_by_key() {
+        let mut before = Tag::new();
+        before.set_title("Old");
+        before.set_album("Album");
+        before.add_frame(catalog("A-1"));
+
+        let mut after = before.clone();
+        after.set_title("New");
+        after.remove("TALB");
+        after.set_artist("Artist");
+        after.add_frame(catalog("A-2"));
+
+        let changes = diff(&before, &after);
+        let described: Vec<String> = changes.iter().map(|c| c.to_string()).collect();
+        assert_eq!(
+            described,
+            [
+                "Changed TIT2: Old → New",
+                "Added TPE1: Artist",
+                "Changed TXXX:CATALOGNUMBER: A-1 → A-2",
+                "Removed TALB: Album",
+            ]
+        );
+    }
+
+    #[test]
+    fn chapter_edits_are_modifications() {
+        let mut before = Tag::new();
+        add_chapter(&mut before, 0, 1000, "Intro");
+        let mut after = before.clone();
+        let mut chapter = after.chapters().next().unwrap().clone();
+        chapter.end_time = 2000;
+        after.add_frame(chapter);
+
+        let changes = diff(&before, &after);
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].key(), "CHAP:chp0");
+        assert!(matches!(changes[0], FrameChange::Modified { .. }));
+    }
+}
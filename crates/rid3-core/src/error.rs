@@ -0,0 +1,57 @@
+use std::fmt;
+
+use id3::ErrorKind;
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+#[derive(Debug)]
+pub enum Error {
+    /// The file could not be read or contains no ID3 tag.
+    Read(id3::Error),
+    /// The ID3 tag is present but malformed.
+    Parse(id3::Error),
+    /// The ID3 tag could not be written.
+    Write(id3::Error),
+    /// A picture could not be decoded or encoded.
+    Image(image::ImageError),
+    /// A zip of pictures could not be written.
+    Archive(zip::result::ZipError),
+    /// A chapters file could not be read.
+    Chapters(String),
+}
+
+impl Error {
+    /// Classifies an error returned while reading a tag.
+    pub(crate) fn reading(err: id3::Error) -> Self {
+        match err.kind {
+            ErrorKind::Parsing | ErrorKind::StringDecoding(_) | ErrorKind::UnsupportedFeature => {
+                Error::Parse(err)
+            }
+            _ => Error::Read(err),
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Read(err) => write!(f, "failed to read ID3 tag: {}", err),
+            Error::Parse(err) => write!(f, "malformed ID3 tag: {}", err),
+            Error::Write(err) => write!(f, "failed to write ID3 tag: {}", err),
+            Error::Image(err) => write!(f, "failed to process picture: {}", err),
+            Error::Archive(err) => write!(f, "failed to write zip: {}", err),
+            Error::Chapters(message) => write!(f, "invalid chapters file: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Read(err) | Error::Parse(err) | Error::Write(err) => Some(err),
+            Error::Image(err) => Some(err),
+            Error::Archive(err) => Some(err),
+            Error::Chapters(_) => None,
+        }
+    }
+}
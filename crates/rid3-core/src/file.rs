@@ -0,0 +1,283 @@
+use std::io::Cursor;
+use std::sync::Arc;
+
+use id3::{v1, ErrorKind, Tag, TagLike, Version};
+
+use crate::layout::{Layout, Trailer};
+use crate::mpeg::FrameHeader;
+use crate::version;
+use crate::{Error, Result};
+
+/// The version given to tags created for files that had no ID3v2 tag.
+pub const DEFAULT_VERSION: Version = Version::Id3v23;
+
+/// Where the tag being edited came from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TagSource {
+    /// The file has an ID3v2 tag of this version.
+    Id3v2(Version),
+    /// The file only has an ID3v1 tag, which was imported into a new tag.
+    Id3v1,
+    /// The file has no tag; editing starts from an empty one.
+    None,
+}
+
+/// An MP3 file loaded into memory together with its parsed ID3 tag.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Mp3File {
+    pub tag: Tag,
+    /// Shared, so snapshots of the file do not copy the audio.
+    bytes: Arc<[u8]>,
+    source: TagSource,
+    layout: Layout,
+    /// Trailing tags to leave out when saving.
+    removed: Vec<Trailer>,
+}
+
+impl Mp3File {
+    /// Reads the ID3v2 tag from `bytes`. Files without one start with an
+    /// empty tag, seeded from an ID3v1 tag when the file ends with one.
+    pub fn from_bytes(bytes: Vec<u8>) -> Result<Self> {
+        let (tag, source) = match Tag::read_from2(Cursor::new(bytes.as_slice())) {
+            Ok(tag) => {
+                let version = tag.version();
+                (tag, TagSource::Id3v2(version))
+            }
+            Err(err) if matches!(err.kind, ErrorKind::NoTag) => {
+                let mut tag = Tag::with_version(DEFAULT_VERSION);
+                match v1::Tag::read_from(Cursor::new(bytes.as_slice())) {
+                    Ok(v1_tag) => {
+                        for frame in Tag::from(v1_tag).frames() {
+                            tag.add_frame(frame.clone());
+                        }
+                        (tag, TagSource::Id3v1)
+                    }
+                    Err(_) => (tag, TagSource::None),
+                }
+            }
+            Err(err) => return Err(Error::reading(err)),
+        };
+        let layout = Layout::of(&bytes);
+        Ok(Mp3File {
+            tag,
+            bytes: bytes.into(),
+            source,
+            layout,
+            removed: Vec::new(),
+        })
+    }
+
+    pub fn source(&self) -> TagSource {
+        self.source
+    }
+
+    /// The version the tag was read as, or [`DEFAULT_VERSION`] for new tags.
+    pub fn source_version(&self) -> Version {
+        match self.source {
+            TagSource::Id3v2(version) => version,
+            TagSource::Id3v1 | TagSource::None => DEFAULT_VERSION,
+        }
+    }
+
+    /// The file as it was loaded.
+    pub fn bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    pub fn set_text(&mut self, id: &str, value: impl Into<String>) {
+        self.tag.set_text(id, value);
+    }
+
+    pub fn layout(&self) -> &Layout {
+        &self.layout
+    }
+
+    /// The header of the first MPEG audio frame, if one can be found.
+    pub fn frame_header(&self) -> Option<FrameHeader> {
+        FrameHeader::find(&self.bytes[self.layout.audio.clone()])
+    }
+
+    /// Whether a trailing tag found in the file is written back on save.
+    /// Trailing tags are kept unless removed with [`Mp3File::set_keep`].
+    pub fn keeps(&self, trailer: Trailer) -> bool {
+        self.layout.trailer(trailer).is_some() && !self.removed.contains(&trailer)
+    }
+
+    pub fn set_keep(&mut self, trailer: Trailer, keep: bool) {
+        self.removed.retain(|t| *t != trailer);
+        if !keep {
+            self.removed.push(trailer);
+        }
+    }
+
+    /// Produces the file contents with the current tag converted to and
+    /// written in `version`. See [`version::report`] for what changes.
+    ///
+    /// The old ID3v2 tag is replaced as a whole, so the audio stream is
+    /// copied untouched whatever the size of the new tag.
+    pub fn to_bytes(&self, version: Version) -> Result<Vec<u8>> {
+        let tag = version::convert(&self.tag, version).tag;
+        let mut out = Vec::with_capacity(self.bytes.len());
+        tag.write_to(&mut out, version).map_err(Error::Write)?;
+        self.write_untagged(&mut out);
+        Ok(out)
+    }
+
+    /// Produces the file contents with the ID3v2 tag removed.
+    pub fn stripped(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(self.bytes.len());
+        self.write_untagged(&mut out);
+        out
+    }
+
+    /// Appends the audio stream and the trailing tags that are kept, in the
+    /// order they were found.
+    fn write_untagged(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(&self.bytes[self.layout.audio.clone()]);
+        for trailer in [Trailer::AppendedId3v2, Trailer::Ape, Trailer::Id3v1] {
+            if self.keeps(trailer) {
+                if let Some(range) = self.layout.trailer(trailer) {
+                    out.extend_from_slice(&self.bytes[range]);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mp3_with_title(title: &str) -> Vec<u8> {
+        let mut tag = Tag::new();
+        tag.set_title(title);
+        let mut bytes = Vec::new();
+        tag.write_to(&mut bytes, Version::Id3v23).unwrap();
+        bytes.extend_from_slice(&[0xff, 0xfb, 0x90, 0x00]);
+        bytes.extend([0x55; 512]);
+        bytes
+    }
+
+    #[test]
+    fn reads_tag_from_bytes() {
+        let file = Mp3File::from_bytes(mp3_with_title("Episode 1")).unwrap();
+        assert_eq!(file.tag.title(), Some("Episode 1"));
+    }
+
+    #[test]
+    fn edited_frames_are_written_back() {
+        let mut file = Mp3File::from_bytes(mp3_with_title("Episode 1")).unwrap();
+        file.set_text("TIT2", "Episode 2");
+        file.set_text("TALB", "Show");
+
+        let reread = Mp3File::from_bytes(file.to_bytes(Version::Id3v23).unwrap()).unwrap();
+        assert_eq!(reread.tag.title(), Some("Episode 2"));
+        assert_eq!(reread.tag.album(), Some("Show"));
+    }
+
+    #[test]
+    fn stripped_file_has_no_tag() {
+        let file = Mp3File::from_bytes(mp3_with_title("Episode 1")).unwrap();
+        let stripped = file.stripped();
+        assert_eq!(stripped[..2], [0xff, 0xfb]);
+        assert_eq!(stripped.len(), 4 + 512);
+    }
+
+    #[test]
+    fn audio_survives_tags_of_any_size() {
+        let original = mp3_with_title("Episode 1");
+        let audio = original[Layout::of(&original).audio].to_vec();
+
+        let mut file = Mp3File::from_bytes(original).unwrap();
+        file.set_text("TIT2", "A much longer title than the one before");
+        let grown = file.to_bytes(Version::Id3v23).unwrap();
+        assert!(grown.ends_with(&audio));
+
+        file.set_text("TIT2", "");
+        let shrunk = file.to_bytes(Version::Id3v23).unwrap();
+        assert!(shrunk.ends_with(&audio));
+        assert_eq!(Layout::of(&shrunk).audio.len(), audio.len());
+    }
+
+    #[test]
+    fn trailing_id3v1_is_kept_unless_removed() {
+        let mut bytes = mp3_with_title("Episode 1");
+        let mut v1 = [0u8; 128];
+        v1[..3].copy_from_slice(b"TAG");
+        bytes.extend_from_slice(&v1);
+
+        let mut file = Mp3File::from_bytes(bytes).unwrap();
+        assert!(file.keeps(Trailer::Id3v1));
+        assert!(!file.keeps(Trailer::Ape));
+        assert!(file.to_bytes(Version::Id3v23).unwrap().ends_with(&v1));
+
+        file.set_keep(Trailer::Id3v1, false);
+        let saved = file.to_bytes(Version::Id3v23).unwrap();
+        assert!(saved.ends_with(&[0x55; 512]));
+        assert_eq!(Layout::of(&saved).id3v1, None);
+    }
+
+    #[test]
+    fn appended_id3v2_is_kept_unless_removed() {
+        let mut appended = Vec::new();
+        let mut tag = Tag::new();
+        tag.set_title("Old");
+        tag.write_to(&mut appended, Version::Id3v24).unwrap();
+        // An appended tag has a footer, `3DI` and a copy of the header's size.
+        appended[5] |= 0x10;
+        let footer: Vec<u8> = b"3DI".iter().chain(&appended[3..10]).copied().collect();
+        appended.extend(footer);
+        let mut bytes = mp3_with_title("Episode 1");
+        bytes.extend_from_slice(&appended);
+
+        let mut file = Mp3File::from_bytes(bytes).unwrap();
+        assert_eq!(file.layout().trailers(), [Trailer::AppendedId3v2]);
+        assert!(file.keeps(Trailer::AppendedId3v2));
+        assert!(file.to_bytes(Version::Id3v23).unwrap().ends_with(&appended));
+
+        file.set_keep(Trailer::AppendedId3v2, false);
+        let saved = file.to_bytes(Version::Id3v23).unwrap();
+        assert!(saved.ends_with(&[0x55; 512]));
+    }
+
+    #[test]
+    fn missing_tag_starts_empty() {
+        let audio = vec![0xff, 0xfb, 0x90, 0x00];
+        let mut file = Mp3File::from_bytes(audio.clone()).unwrap();
+        assert_eq!(file.source(), TagSource::None);
+        assert_eq!(file.tag.frames().count(), 0);
+
+        file.set_text("TIT2", "New");
+        let bytes = file.to_bytes(Version::Id3v24).unwrap();
+        assert!(bytes.ends_with(&audio));
+        let reread = Mp3File::from_bytes(bytes).unwrap();
+        assert_eq!(reread.source(), TagSource::Id3v2(Version::Id3v24));
+        assert_eq!(reread.tag.title(), Some("New"));
+    }
+
+    #[test]
+    fn id3v1_fields_are_imported() {
+        let mut bytes = vec![0xff, 0xfb, 0x90, 0x00];
+        let mut v1 = [0u8; 128];
+        v1[..3].copy_from_slice(b"TAG");
+        v1[3..8].copy_from_slice(b"Title");
+        v1[33..39].copy_from_slice(b"Artist");
+        v1[127] = 0xff;
+        bytes.extend_from_slice(&v1);
+
+        let file = Mp3File::from_bytes(bytes).unwrap();
+        assert_eq!(file.source(), TagSource::Id3v1);
+        assert_eq!(file.tag.title(), Some("Title"));
+        assert_eq!(file.tag.artist(), Some("Artist"));
+        assert_eq!(file.source_version(), DEFAULT_VERSION);
+    }
+
+    #[test]
+    fn bad_text_encoding_is_a_parse_error() {
+        let mut bytes = mp3_with_title("Episode 1");
+        // The first byte of the TIT2 frame body selects the text encoding.
+        bytes[20] = 0x07;
+        let err = Mp3File::from_bytes(bytes).unwrap_err();
+        assert!(matches!(err, Error::Parse(_)), "{:?}", err);
+    }
+}
@@ -0,0 +1,155 @@
+//! Download file names built from `%FRAME%` templates.
+
+use id3::{Tag, TagLike};
+
+use crate::frame::display_value;
+
+/// The longest file name most file systems accept, in bytes.
+pub const MAX_LEN: usize = 255;
+
+const EXTENSION: &str = "mp3";
+
+/// Fills `%TPE1%`-style placeholders in `template` with the tag's frame
+/// values and turns the result into a safe file name ending in `.mp3`.
+/// Frames missing from the tag become empty.
+pub fn render(template: &str, tag: &Tag) -> String {
+    let mut out = String::new();
+    let mut rest = template;
+    while let Some(start) = rest.find('%') {
+        out.push_str(&rest[..start]);
+        let after = &rest[start + 1..];
+        match after.find('%') {
+            Some(end) if is_frame_id(&after[..end]) => {
+                out.push_str(&frame_value(tag, &after[..end]));
+                rest = &after[end + 1..];
+            }
+            _ => {
+                out.push('%');
+                rest = after;
+            }
+        }
+    }
+    out.push_str(rest);
+    sanitize(&out)
+}
+
+/// Replaces characters that are illegal in file names, collapses
+/// whitespace, adds the `.mp3` extension and limits the length.
+pub fn sanitize(name: &str) -> String {
+    sanitize_as(name, EXTENSION)
+}
+
+/// A name for a file saved next to the MP3 called `mp3_name`, such as its
+/// lyrics or artwork: the MP3's name without `.mp3`, then `suffix`, ending
+/// in `extension` instead.
+pub fn sibling(mp3_name: &str, suffix: &str, extension: &str) -> String {
+    let stem = strip_extension(mp3_name, EXTENSION);
+    sanitize_as(&format!("{}{}", stem, suffix), extension)
+}
+
+/// [`sanitize`] for a file ending in `extension`, given without the dot.
+pub fn sanitize_as(name: &str, extension: &str) -> String {
+    let cleaned: String = name
+        .chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            c if c.is_control() => ' ',
+            c => c,
+        })
+        .collect();
+    let cleaned = cleaned.split_whitespace().collect::<Vec<_>>().join(" ");
+
+    let mut stem = strip_extension(&cleaned, extension)
+        .trim_matches(|c: char| c == '.' || c == ' ')
+        .to_string();
+    if stem.is_empty() {
+        stem.push_str("untitled");
+    }
+    let stem = truncate(&stem, MAX_LEN - extension.len() - 1).trim_end();
+    format!("{}.{}", stem, extension)
+}
+
+/// The longest start of `s` that fits in `max` bytes without splitting a
+/// character.
+pub fn truncate(s: &str, max: usize) -> &str {
+    let mut end = s.len().min(max);
+    while !s.is_char_boundary(end) {
+        end -= 1;
+    }
+    &s[..end]
+}
+
+/// `name` without a trailing `.extension`, compared case-insensitively.
+fn strip_extension<'a>(name: &'a str, extension: &str) -> &'a str {
+    match name.len().checked_sub(extension.len() + 1) {
+        Some(at)
+            if name.is_char_boundary(at)
+                && name[at..].starts_with('.')
+                && name[at + 1..].eq_ignore_ascii_case(extension) =>
+        {
+            &name[..at]
+        }
+        _ => name,
+    }
+}
+
+fn is_frame_id(s: &str) -> bool {
+    s.len() == 4
+        && s.chars()
+            .all(|c| c.is_ascii_uppercase() || c.is_ascii_digit())
+}
+
+fn frame_value(tag: &Tag, id: &str) -> String {
+    let Some(frame) = tag.get(id) else {
+        return String::new();
+    };
+    let value = display_value(frame);
+    // "3/12" in TRCK and TPOS means track 3 of 12.
+    let value = match id {
+        "TRCK" | "TPOS" => value.split('/').next().unwrap_or_default().to_string(),
+        _ => value,
+    };
+    value.replace('\0', ", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fills_placeholders() {
+        let mut tag = Tag::new();
+        tag.set_artist("AC/DC");
+        tag.set_text("TRCK", "3/12");
+        tag.set_title("T.N.T.");
+        assert_eq!(
+            render("%TPE1% - %TRCK% - %TIT2%.mp3", &tag),
+            "AC_DC - 3 - T.N.T.mp3"
+        );
+    }
+
+    #[test]
+    fn missing_frames_and_stray_percents() {
+        let tag = Tag::new();
+        assert_eq!(render("100% %TALB%", &tag), "100%.mp3");
+        assert_eq!(render("%TIT2%", &tag), "untitled.mp3");
+    }
+
+    #[test]
+    fn long_names_are_cut_on_char_boundaries() {
+        let name = sanitize(&"é".repeat(300));
+        assert!(name.len() <= MAX_LEN);
+        assert!(name.ends_with(".mp3"));
+        assert!(name.starts_with("éé"));
+    }
+
+    #[test]
+    fn siblings_share_the_mp3_name() {
+        assert_eq!(
+            sibling("Show - 1.mp3", " (eng)", "txt"),
+            "Show - 1 (eng).txt"
+        );
+        assert_eq!(sibling("cover.MP3", "", "jpg"), "cover.jpg");
+        assert_eq!(sanitize_as("notes.txt", "txt"), "notes.txt");
+    }
+}
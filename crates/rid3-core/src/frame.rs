@@ -0,0 +1,265 @@
+use id3::{Content, Frame, Tag};
+
+/// The value of a frame as a single line of text, for tables and listings.
+pub fn display_value(frame: &Frame) -> String {
+    match frame.content() {
+        Content::Text(text) | Content::Link(text) => text.clone(),
+        Content::Comment(comment) => comment.text.clone(),
+        Content::Lyrics(lyrics) => lyrics.text.clone(),
+        Content::ExtendedText(extended) => extended.value.clone(),
+        Content::ExtendedLink(extended) => extended.link.clone(),
+        Content::TableOfContents(toc) => toc.elements.join(", "),
+        content => content.to_string(),
+    }
+}
+
+/// The description that tells apart frames sharing an ID, such as `TXXX` or `COMM`.
+pub fn description(frame: &Frame) -> Option<&str> {
+    match frame.content() {
+        Content::Comment(comment) => Some(&comment.description),
+        Content::Lyrics(lyrics) => Some(&lyrics.description),
+        Content::ExtendedText(extended) => Some(&extended.description),
+        Content::ExtendedLink(extended) => Some(&extended.description),
+        Content::Picture(picture) => Some(&picture.description),
+        _ => None,
+    }
+}
+
+/// Names a frame by its ID and whatever tells it apart from other frames
+/// with that ID, such as `TXXX:CATALOGNUMBER` or `CHAP:chp0`. Two frames with
+/// the same key describe the same thing.
+pub fn key(frame: &Frame) -> String {
+    let id = frame.id();
+    match frame.content() {
+        Content::Comment(comment) => format!("{}:{}:{}", id, comment.lang, comment.description),
+        Content::Lyrics(lyrics) => format!("{}:{}:{}", id, lyrics.lang, lyrics.description),
+        Content::SynchronisedLyrics(lyrics) => {
+            format!("{}:{}:{}", id, lyrics.lang, lyrics.description)
+        }
+        Content::ExtendedText(extended) => format!("{}:{}", id, extended.description),
+        Content::ExtendedLink(extended) => format!("{}:{}", id, extended.description),
+        Content::Picture(picture) => {
+            format!("{}:{}:{}", id, picture.picture_type, picture.description)
+        }
+        Content::EncapsulatedObject(object) => format!("{}:{}", id, object.description),
+        Content::Popularimeter(popm) => format!("{}:{}", id, popm.user),
+        Content::Private(private) => format!("{}:{}", id, private.owner_identifier),
+        Content::UniqueFileIdentifier(ufid) => format!("{}:{}", id, ufid.owner_identifier),
+        Content::Chapter(chapter) => format!("{}:{}", id, chapter.element_id),
+        Content::TableOfContents(toc) => format!("{}:{}", id, toc.element_id),
+        // The only link frames a tag may have more than one of.
+        Content::Link(link) if id == "WCOM" || id == "WOAR" => format!("{}:{}", id, link),
+        _ => id.to_string(),
+    }
+}
+
+/// Adds `frame` unless the tag already has a frame with the same [`key`].
+/// Returns whether it was added.
+pub fn add(tag: &mut Tag, frame: Frame) -> bool {
+    let new_key = key(&frame);
+    if tag.frames().any(|f| key(f) == new_key) {
+        return false;
+    }
+    // Not `Tag::add_frame`, which treats pictures of the same type as
+    // duplicates whatever their descriptions.
+    tag.extend([frame]);
+    true
+}
+
+/// Removes the first frame with the given [`key`]. Returns whether one was found.
+pub fn remove(tag: &mut Tag, frame_key: &str) -> bool {
+    let mut found = false;
+    retain_frames(tag, |f| {
+        if !found && key(f) == frame_key {
+            found = true;
+            return false;
+        }
+        true
+    });
+    found
+}
+
+/// Replaces the content of the first frame with the given [`key`], keeping
+/// its ID, position and flags. Nothing is changed, and false returned, when
+/// `content` is of a different type than the frame's current content, or
+/// when the new content would give the frame the key of another frame.
+pub fn set_content(tag: &mut Tag, frame_key: &str, content: Content) -> bool {
+    let frames: Vec<&Frame> = tag.frames().collect();
+    let Some(index) = frames.iter().position(|f| key(f) == frame_key) else {
+        return false;
+    };
+    let old = frames[index];
+    if std::mem::discriminant(old.content()) != std::mem::discriminant(&content) {
+        return false;
+    }
+    let mut new = Frame::with_content(old.id(), content).set_encoding(old.encoding());
+    new.set_tag_alter_preservation(old.tag_alter_preservation());
+    new.set_file_alter_preservation(old.file_alter_preservation());
+    let new_key = key(&new);
+    if new_key != frame_key && frames.iter().any(|f| key(f) == new_key) {
+        return false;
+    }
+
+    let mut frames: Vec<Frame> = frames.into_iter().cloned().collect();
+    frames[index] = new;
+    let mut replaced = Tag::with_version(tag.version());
+    replaced.extend(frames);
+    *tag = replaced;
+    true
+}
+
+/// Binary frame data as space-separated hex bytes.
+pub fn to_hex(data: &[u8]) -> String {
+    data.iter()
+        .map(|b| format!("{:02x}", b))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Parses hex bytes, ignoring whitespace between them.
+pub fn parse_hex(s: &str) -> Option<Vec<u8>> {
+    let digits: Vec<char> = s.chars().filter(|c| !c.is_whitespace()).collect();
+    if !digits.len().is_multiple_of(2) {
+        return None;
+    }
+    digits
+        .chunks(2)
+        .map(|pair| u8::from_str_radix(&pair.iter().collect::<String>(), 16).ok())
+        .collect()
+}
+
+/// Keeps only the frames for which `keep` returns true, preserving their order.
+pub fn retain_frames(tag: &mut Tag, mut keep: impl FnMut(&Frame) -> bool) {
+    let frames: Vec<Frame> = tag.frames().filter(|f| keep(f)).cloned().collect();
+    let mut retained = Tag::with_version(tag.version());
+    retained.extend(frames);
+    *tag = retained;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use id3::frame::{Comment, Picture, PictureType};
+    use id3::TagLike;
+
+    #[test]
+    fn comment_value_is_its_text() {
+        let frame = Frame::from(Comment {
+            lang: "eng".to_string(),
+            description: "notes".to_string(),
+            text: "Recorded live".to_string(),
+        });
+        assert_eq!(display_value(&frame), "Recorded live");
+    }
+
+    #[test]
+    fn retain_keeps_order() {
+        let mut tag = Tag::new();
+        tag.set_title("Title");
+        tag.set_album("Album");
+        tag.set_artist("Artist");
+        retain_frames(&mut tag, |f| f.id() != "TALB");
+
+        let ids: Vec<&str> = tag.frames().map(|f| f.id()).collect();
+        assert_eq!(ids, ["TIT2", "TPE1"]);
+    }
+
+    #[test]
+    fn content_is_replaced_in_place() {
+        let mut tag = Tag::new();
+        tag.set_title("Title");
+        tag.add_frame(Comment {
+            lang: "eng".to_string(),
+            description: "notes".to_string(),
+            text: "Old".to_string(),
+        });
+        tag.set_album("Album");
+
+        let edited = Comment {
+            lang: "eng".to_string(),
+            description: "liner".to_string(),
+            text: "New".to_string(),
+        };
+        assert!(set_content(
+            &mut tag,
+            "COMM:eng:notes",
+            Content::Comment(edited)
+        ));
+        let keys: Vec<String> = tag.frames().map(key).collect();
+        assert_eq!(keys, ["TIT2", "COMM:eng:liner", "TALB"]);
+
+        // A comment can't be turned into text.
+        assert!(!set_content(
+            &mut tag,
+            "COMM:eng:liner",
+            Content::Text("x".into())
+        ));
+        assert_eq!(tag.comments().next().unwrap().text, "New");
+    }
+
+    #[test]
+    fn repeatable_links_are_told_apart() {
+        let mut tag = Tag::new();
+        assert!(add(&mut tag, Frame::link("WOAR", "https://a.example")));
+        assert!(add(&mut tag, Frame::link("WOAR", "https://b.example")));
+        assert!(!add(&mut tag, Frame::link("WOAR", "https://b.example")));
+        assert!(add(&mut tag, Frame::link("WPUB", "https://a.example")));
+        assert!(!add(&mut tag, Frame::link("WPUB", "https://b.example")));
+
+        assert!(set_content(
+            &mut tag,
+            "WOAR:https://b.example",
+            Content::Link("https://c.example".into())
+        ));
+        assert!(remove(&mut tag, "WOAR:https://a.example"));
+        let keys: Vec<String> = tag.frames().map(key).collect();
+        assert_eq!(keys, ["WOAR:https://c.example", "WPUB"]);
+    }
+
+    #[test]
+    fn hex_round_trips() {
+        assert_eq!(to_hex(&[0x00, 0xab, 0x10]), "00 ab 10");
+        assert_eq!(parse_hex("00 ab10"), Some(vec![0x00, 0xab, 0x10]));
+        assert_eq!(parse_hex("abc"), None);
+        assert_eq!(parse_hex("zz"), None);
+    }
+
+    #[test]
+    fn frames_are_added_and_removed_by_key() {
+        let mut tag = Tag::new();
+        tag.set_title("Title");
+        assert!(!add(&mut tag, Frame::text("TIT2", "")));
+        assert_eq!(tag.title(), Some("Title"));
+
+        let comment = Frame::from(Comment {
+            lang: "eng".to_string(),
+            description: "notes".to_string(),
+            text: String::new(),
+        });
+        assert!(add(&mut tag, comment));
+        assert!(remove(&mut tag, "COMM:eng:notes"));
+        assert!(!remove(&mut tag, "COMM:eng:notes"));
+        assert_eq!(tag.frames().count(), 1);
+    }
+
+    #[test]
+    fn pictures_of_one_type_are_kept_apart_by_description() {
+        let picture = |description: &str| Picture {
+            mime_type: "image/png".to_string(),
+            picture_type: PictureType::Other,
+            description: description.to_string(),
+            data: vec![1],
+        };
+        let mut tag = Tag::new();
+        assert!(add(&mut tag, Frame::from(picture("front"))));
+        assert!(add(&mut tag, Frame::from(picture("back"))));
+        assert!(set_content(
+            &mut tag,
+            "APIC:Other:back",
+            Content::Picture(picture("inlay"))
+        ));
+        retain_frames(&mut tag, |_| true);
+        let descriptions: Vec<&str> = tag.pictures().map(|p| p.description.as_str()).collect();
+        assert_eq!(descriptions, ["front", "inlay"]);
+    }
+}
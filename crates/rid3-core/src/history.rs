@@ -0,0 +1,141 @@
+//! Undo and redo over snapshots of whatever is being edited.
+//!
+//! Each edit records the state from before it under a label such as
+//! "Changed TIT2". Undoing hands that snapshot back and keeps the current
+//! state for redo; making a new edit forgets everything that was undone.
+
+use std::collections::VecDeque;
+
+/// How many edits are remembered unless another limit is given.
+pub const DEFAULT_LIMIT: usize = 50;
+
+/// A labelled snapshot of the state before an edit.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Entry<T> {
+    pub label: String,
+    pub snapshot: T,
+}
+
+/// A bounded undo/redo history. The oldest entries are dropped once
+/// `limit` edits have been recorded.
+#[derive(Clone, Debug, PartialEq)]
+pub struct History<T> {
+    undo: VecDeque<Entry<T>>,
+    redo: Vec<Entry<T>>,
+    limit: usize,
+}
+
+impl<T> Default for History<T> {
+    fn default() -> Self {
+        History::with_limit(DEFAULT_LIMIT)
+    }
+}
+
+impl<T> History<T> {
+    pub fn with_limit(limit: usize) -> Self {
+        History {
+            undo: VecDeque::new(),
+            redo: Vec::new(),
+            limit,
+        }
+    }
+
+    /// Records `before`, the state an edit described by `label` started from.
+    pub fn record(&mut self, label: impl Into<String>, before: T) {
+        self.undo.push_back(Entry {
+            label: label.into(),
+            snapshot: before,
+        });
+        while self.undo.len() > self.limit {
+            self.undo.pop_front();
+        }
+        self.redo.clear();
+    }
+
+    /// Steps back one edit. Returns the state to restore; `current` is kept
+    /// so the edit can be redone.
+    pub fn undo(&mut self, current: T) -> Option<T> {
+        let entry = self.undo.pop_back()?;
+        self.redo.push(Entry {
+            label: entry.label,
+            snapshot: current,
+        });
+        Some(entry.snapshot)
+    }
+
+    /// Reapplies the last undone edit. Returns the state to restore.
+    pub fn redo(&mut self, current: T) -> Option<T> {
+        let entry = self.redo.pop()?;
+        self.undo.push_back(Entry {
+            label: entry.label,
+            snapshot: current,
+        });
+        Some(entry.snapshot)
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.undo.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.redo.is_empty()
+    }
+
+    /// Labels of the edits that can be undone, oldest first.
+    pub fn undo_labels(&self) -> impl Iterator<Item = &str> {
+        self.undo.iter().map(|e| e.label.as_str())
+    }
+
+    /// Labels of the edits that can be redone, next redo first.
+    pub fn redo_labels(&self) -> impl Iterator<Item = &str> {
+        self.redo.iter().rev().map(|e| e.label.as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn undo_and_redo_walk_the_edits() {
+        let mut history = History::default();
+        let mut title = "A";
+        history.record("Changed TIT2", title);
+        title = "B";
+        history.record("Changed TIT2", title);
+        title = "C";
+
+        title = history.undo(title).unwrap();
+        assert_eq!(title, "B");
+        title = history.undo(title).unwrap();
+        assert_eq!(title, "A");
+        assert_eq!(history.undo(title), None);
+
+        title = history.redo(title).unwrap();
+        assert_eq!(title, "B");
+        assert_eq!(history.redo_labels().count(), 1);
+
+        // A new edit forgets what was undone.
+        history.record("Changed TALB", title);
+        assert!(!history.can_redo());
+        assert_eq!(
+            history.undo_labels().collect::<Vec<_>>(),
+            ["Changed TIT2", "Changed TALB"]
+        );
+    }
+
+    #[test]
+    fn oldest_entries_are_dropped() {
+        let mut history = History::with_limit(2);
+        for i in 0..5 {
+            history.record(format!("edit {}", i), i);
+        }
+        assert_eq!(
+            history.undo_labels().collect::<Vec<_>>(),
+            ["edit 3", "edit 4"]
+        );
+        assert_eq!(history.undo(5), Some(4));
+        assert_eq!(history.undo(4), Some(3));
+        assert_eq!(history.undo(3), None);
+    }
+}
@@ -0,0 +1,264 @@
+//! Locating the tags around the audio stream of an MP3 file.
+
+use std::fmt;
+use std::ops::Range;
+
+const ID3V1_SIZE: usize = 128;
+const ID3V1_EXTENDED_SIZE: usize = 227;
+const APE_FOOTER_SIZE: usize = 32;
+const ID3V2_HEADER_SIZE: usize = 10;
+
+/// A tag stored after the audio stream.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Trailer {
+    /// An ID3v1 tag, including an enhanced `TAG+` block.
+    Id3v1,
+    /// An APEv1 or APEv2 tag.
+    Ape,
+    /// An ID3v2.4 tag appended after the audio, found by its `3DI` footer.
+    AppendedId3v2,
+}
+
+impl fmt::Display for Trailer {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Trailer::Id3v1 => write!(f, "ID3v1 tag"),
+            Trailer::Ape => write!(f, "APE tag"),
+            Trailer::AppendedId3v2 => write!(f, "appended ID3v2 tag"),
+        }
+    }
+}
+
+/// Byte ranges of the parts of an MP3 file.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Layout {
+    /// Leading ID3v2 tags with their extended headers, footers and padding.
+    pub id3v2: Range<usize>,
+    /// The audio stream.
+    pub audio: Range<usize>,
+    /// An ID3v2.4 tag appended after the audio, found by its `3DI` footer.
+    pub appended_id3v2: Option<Range<usize>>,
+    pub ape: Option<Range<usize>>,
+    pub id3v1: Option<Range<usize>>,
+}
+
+impl Layout {
+    pub fn of(bytes: &[u8]) -> Self {
+        let audio_start = leading_id3v2_end(bytes);
+        let mut end = bytes.len();
+
+        let id3v1 = id3v1_start(bytes, audio_start).map(|start| start..end);
+        if let Some(range) = &id3v1 {
+            end = range.start;
+        }
+        let ape = ape_start(&bytes[..end], audio_start).map(|start| start..end);
+        if let Some(range) = &ape {
+            end = range.start;
+        }
+        let appended_id3v2 = appended_id3v2_start(&bytes[..end], audio_start).map(|s| s..end);
+        if let Some(range) = &appended_id3v2 {
+            end = range.start;
+        }
+
+        Layout {
+            id3v2: 0..audio_start,
+            audio: audio_start..end,
+            appended_id3v2,
+            ape,
+            id3v1,
+        }
+    }
+
+    /// The trailing tags present in the file.
+    pub fn trailers(&self) -> Vec<Trailer> {
+        let mut trailers = Vec::new();
+        if self.appended_id3v2.is_some() {
+            trailers.push(Trailer::AppendedId3v2);
+        }
+        if self.ape.is_some() {
+            trailers.push(Trailer::Ape);
+        }
+        if self.id3v1.is_some() {
+            trailers.push(Trailer::Id3v1);
+        }
+        trailers
+    }
+
+    pub fn trailer(&self, trailer: Trailer) -> Option<Range<usize>> {
+        match trailer {
+            Trailer::Id3v1 => self.id3v1.clone(),
+            Trailer::Ape => self.ape.clone(),
+            Trailer::AppendedId3v2 => self.appended_id3v2.clone(),
+        }
+    }
+}
+
+fn syncsafe(bytes: &[u8]) -> usize {
+    bytes.iter().fold(0, |n, b| (n << 7) | (*b as usize & 0x7f))
+}
+
+/// The end of the ID3v2 tags at the start of the file. Some taggers leave
+/// several tags in a row, so all of them are covered. Padding is part of a
+/// tag's declared size; zero bytes after it may be audio, such as silence,
+/// so they are only skipped when another tag follows them.
+fn leading_id3v2_end(bytes: &[u8]) -> usize {
+    let mut pos = 0;
+    while bytes.len() >= pos + ID3V2_HEADER_SIZE && bytes[pos..].starts_with(b"ID3") {
+        let header = &bytes[pos..pos + ID3V2_HEADER_SIZE];
+        let has_footer = header[3] == 4 && header[5] & 0x10 != 0;
+        let size = ID3V2_HEADER_SIZE
+            + syncsafe(&header[6..10])
+            + if has_footer { ID3V2_HEADER_SIZE } else { 0 };
+        // Seen in the wild: tags that claim to be larger than the file.
+        pos = (pos + size).min(bytes.len());
+        let zeros = bytes[pos..].iter().take_while(|b| **b == 0).count();
+        if bytes[pos + zeros..].starts_with(b"ID3") {
+            pos += zeros;
+        }
+    }
+    pos
+}
+
+fn id3v1_start(bytes: &[u8], min: usize) -> Option<usize> {
+    let start = bytes.len().checked_sub(ID3V1_SIZE)?;
+    if start < min || !bytes[start..].starts_with(b"TAG") {
+        return None;
+    }
+    match start.checked_sub(ID3V1_EXTENDED_SIZE) {
+        Some(extended) if extended >= min && bytes[extended..].starts_with(b"TAG+") => {
+            Some(extended)
+        }
+        _ => Some(start),
+    }
+}
+
+fn ape_start(bytes: &[u8], min: usize) -> Option<usize> {
+    let footer = bytes.len().checked_sub(APE_FOOTER_SIZE)?;
+    if footer < min || !bytes[footer..].starts_with(b"APETAGEX") {
+        return None;
+    }
+    let le = |at: usize| u32::from_le_bytes(bytes[at..at + 4].try_into().unwrap()) as usize;
+    let size = le(footer + 12);
+    let has_header = le(footer + 20) & 0x8000_0000 != 0;
+    let total = size + if has_header { APE_FOOTER_SIZE } else { 0 };
+    bytes.len().checked_sub(total).filter(|start| *start >= min)
+}
+
+fn appended_id3v2_start(bytes: &[u8], min: usize) -> Option<usize> {
+    let footer = bytes.len().checked_sub(ID3V2_HEADER_SIZE)?;
+    if footer < min || !bytes[footer..].starts_with(b"3DI") {
+        return None;
+    }
+    let size = syncsafe(&bytes[footer + 6..footer + 10]);
+    footer
+        .checked_sub(size + ID3V2_HEADER_SIZE)
+        .filter(|start| *start >= min && bytes[*start..].starts_with(b"ID3"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const AUDIO: [u8; 6] = [0xff, 0xfb, 0x90, 0x00, 0x12, 0x34];
+
+    fn id3v2(body: usize, padding: usize, footer: bool) -> Vec<u8> {
+        let size = body + padding;
+        let flags = if footer { 0x10 } else { 0 };
+        let mut tag = vec![b'I', b'D', b'3', 4, 0, flags];
+        tag.extend([21, 14, 7, 0].map(|shift| ((size >> shift) & 0x7f) as u8));
+        tag.extend(std::iter::repeat_n(b'x', body));
+        tag.extend(std::iter::repeat_n(0, padding));
+        if footer {
+            tag.extend_from_slice(b"3DI");
+            tag.extend_from_slice(&tag.clone()[3..10]);
+        }
+        tag
+    }
+
+    fn ape(items: usize, header: bool) -> Vec<u8> {
+        let size = items + APE_FOOTER_SIZE;
+        let block = |is_header: bool| {
+            let mut b = b"APETAGEX".to_vec();
+            b.extend(2000u32.to_le_bytes());
+            b.extend((size as u32).to_le_bytes());
+            b.extend(1u32.to_le_bytes());
+            let flags: u32 =
+                if header { 0x8000_0000 } else { 0 } | if is_header { 0x2000_0000 } else { 0 };
+            b.extend(flags.to_le_bytes());
+            b.extend([0; 8]);
+            b
+        };
+        let mut tag = if header { block(true) } else { Vec::new() };
+        tag.extend(std::iter::repeat_n(b'a', items));
+        tag.extend(block(false));
+        tag
+    }
+
+    fn id3v1() -> Vec<u8> {
+        let mut tag = b"TAG".to_vec();
+        tag.resize(ID3V1_SIZE, b' ');
+        tag
+    }
+
+    #[test]
+    fn finds_tag_with_padding_and_footer() {
+        let mut bytes = id3v2(300, 200, true);
+        let tag_len = bytes.len();
+        bytes.extend(AUDIO);
+        let layout = Layout::of(&bytes);
+        assert_eq!(layout.id3v2, 0..tag_len);
+        assert_eq!(&bytes[layout.audio], AUDIO);
+    }
+
+    #[test]
+    fn zeros_after_the_declared_size_are_audio() {
+        let mut bytes = id3v2(300, 0, false);
+        let tag_len = bytes.len();
+        bytes.extend([0; 64]);
+        bytes.extend(AUDIO);
+        assert_eq!(Layout::of(&bytes).audio, tag_len..bytes.len());
+    }
+
+    #[test]
+    fn zeros_between_leading_tags_are_skipped() {
+        let mut bytes = id3v2(300, 0, false);
+        bytes.extend([0; 64]);
+        bytes.extend(id3v2(20, 10, false));
+        let tags_len = bytes.len();
+        bytes.extend(AUDIO);
+        assert_eq!(Layout::of(&bytes).id3v2, 0..tags_len);
+    }
+
+    #[test]
+    fn finds_trailing_ape_and_id3v1() {
+        let mut bytes = id3v2(20, 0, false);
+        bytes.extend(AUDIO);
+        let ape_start = bytes.len();
+        bytes.extend(ape(40, true));
+        let id3v1_start = bytes.len();
+        bytes.extend(id3v1());
+
+        let layout = Layout::of(&bytes);
+        assert_eq!(&bytes[layout.audio.clone()], AUDIO);
+        assert_eq!(layout.ape, Some(ape_start..id3v1_start));
+        assert_eq!(layout.id3v1, Some(id3v1_start..bytes.len()));
+        assert_eq!(layout.trailers(), [Trailer::Ape, Trailer::Id3v1]);
+    }
+
+    #[test]
+    fn finds_appended_id3v2() {
+        let mut bytes = AUDIO.to_vec();
+        bytes.extend(id3v2(30, 0, true));
+        let layout = Layout::of(&bytes);
+        assert_eq!(layout.audio, 0..AUDIO.len());
+        assert_eq!(layout.appended_id3v2, Some(AUDIO.len()..bytes.len()));
+        assert_eq!(layout.trailers(), [Trailer::AppendedId3v2]);
+    }
+
+    #[test]
+    fn oversized_tag_is_clamped() {
+        let mut bytes = id3v2(10, 0, false);
+        bytes[9] = 0x7f;
+        assert_eq!(Layout::of(&bytes).audio, bytes.len()..bytes.len());
+    }
+}
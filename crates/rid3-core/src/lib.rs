@@ -0,0 +1,32 @@
+//! Tag reading, editing and writing for rid3.
+//!
+//! Nothing in this crate touches the browser, so it builds for the host
+//! target and is exercised by `cargo test`. The web frontend calls into it
+//! instead of doing the byte work in its callbacks.
+
+pub mod art;
+pub mod artwork;
+pub mod catalog;
+pub mod chapter_check;
+pub mod chapter_files;
+pub mod chapters;
+pub mod diff;
+mod error;
+mod file;
+pub mod filename;
+pub mod frame;
+pub mod history;
+pub mod layout;
+pub mod links;
+pub mod lyrics;
+pub mod mpeg;
+pub mod sylt;
+pub mod toc;
+pub mod user_text;
+pub mod values;
+pub mod version;
+
+pub use error::{Error, Result};
+pub use file::{Mp3File, TagSource, DEFAULT_VERSION};
+
+pub use id3;
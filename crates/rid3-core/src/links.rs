@@ -0,0 +1,107 @@
+//! URL link frames (`W***`) and checks on the URLs they hold.
+
+/// Every URL link frame with a short label, in ID order.
+pub const URL_FRAMES: [(&str, &str); 9] = [
+    ("WCOM", "Commercial information"),
+    ("WCOP", "Copyright information"),
+    ("WOAF", "Audio file webpage"),
+    ("WOAR", "Artist webpage"),
+    ("WOAS", "Audio source webpage"),
+    ("WORS", "Radio station homepage"),
+    ("WPAY", "Payment"),
+    ("WPUB", "Publisher webpage"),
+    ("WXXX", "Other link"),
+];
+
+/// Schemes that are safe to open from the editor.
+const OPENABLE_SCHEMES: [&str; 3] = ["http", "https", "ftp"];
+
+/// The label of a URL link frame from [`URL_FRAMES`].
+pub fn label(id: &str) -> Option<&'static str> {
+    URL_FRAMES
+        .iter()
+        .find(|(i, _)| *i == id)
+        .map(|(_, label)| *label)
+}
+
+/// Whether `id` is a URL link frame.
+pub fn is_url_frame(id: &str) -> bool {
+    label(id).is_some()
+}
+
+/// What is wrong with `url`, if anything. An empty URL is not a problem,
+/// so fields can be cleared.
+pub fn problem(url: &str) -> Option<&'static str> {
+    if url.is_empty() {
+        return None;
+    }
+    if url.chars().any(char::is_whitespace) {
+        return Some("URLs can't contain spaces");
+    }
+    let Some((scheme, rest)) = url.split_once(':') else {
+        return Some("Start the URL with a scheme such as https://");
+    };
+    let valid_scheme = scheme.starts_with(|c: char| c.is_ascii_alphabetic())
+        && scheme
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.'));
+    if !valid_scheme {
+        return Some("Start the URL with a scheme such as https://");
+    }
+    if is_openable_scheme(scheme) {
+        let host = rest
+            .strip_prefix("//")
+            .map(|r| r.split(['/', '?', '#']).next().unwrap_or_default());
+        if host.is_none_or(str::is_empty) {
+            return Some("The URL has no host name");
+        }
+    }
+    None
+}
+
+/// Whether `url` is a valid web address that can be opened in a new tab.
+/// Other schemes, such as `javascript:`, are never opened.
+pub fn is_openable(url: &str) -> bool {
+    problem(url).is_none()
+        && url
+            .split_once(':')
+            .is_some_and(|(scheme, _)| is_openable_scheme(scheme))
+}
+
+fn is_openable_scheme(scheme: &str) -> bool {
+    OPENABLE_SCHEMES
+        .iter()
+        .any(|s| s.eq_ignore_ascii_case(scheme))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn urls_are_checked() {
+        assert_eq!(problem(""), None);
+        assert_eq!(
+            problem("https://example.com/a b"),
+            Some("URLs can't contain spaces")
+        );
+        assert_eq!(
+            problem("example.com"),
+            Some("Start the URL with a scheme such as https://")
+        );
+        assert_eq!(problem("https:///path"), Some("The URL has no host name"));
+        assert_eq!(problem("mailto:someone@example.com"), None);
+        assert!(is_openable("HTTPS://example.com?q=1"));
+        assert!(!is_openable("mailto:someone@example.com"));
+        assert!(!is_openable("javascript:alert(1)"));
+    }
+
+    #[test]
+    fn url_frames_match_the_catalog() {
+        for (id, _) in URL_FRAMES {
+            assert!(crate::catalog::describe(id).is_some(), "{}", id);
+        }
+        assert!(is_url_frame("WOAR"));
+        assert!(!is_url_frame("TXXX"));
+    }
+}
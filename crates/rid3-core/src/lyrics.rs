@@ -0,0 +1,96 @@
+//! Unsynchronised lyrics (`USLT`), the languages they are tagged with, and
+//! plain-text lyrics files.
+
+use id3::frame::Lyrics;
+
+/// Common ISO-639-2 language codes and their English names, by code.
+pub const LANGUAGES: &[(&str, &str)] = &[
+    ("ara", "Arabic"),
+    ("chi", "Chinese"),
+    ("cze", "Czech"),
+    ("dan", "Danish"),
+    ("dut", "Dutch"),
+    ("eng", "English"),
+    ("fin", "Finnish"),
+    ("fre", "French"),
+    ("ger", "German"),
+    ("gre", "Greek"),
+    ("heb", "Hebrew"),
+    ("hin", "Hindi"),
+    ("hun", "Hungarian"),
+    ("ind", "Indonesian"),
+    ("ita", "Italian"),
+    ("jpn", "Japanese"),
+    ("kor", "Korean"),
+    ("mul", "Multiple languages"),
+    ("nor", "Norwegian"),
+    ("per", "Persian"),
+    ("pol", "Polish"),
+    ("por", "Portuguese"),
+    ("rum", "Romanian"),
+    ("rus", "Russian"),
+    ("spa", "Spanish"),
+    ("swe", "Swedish"),
+    ("tha", "Thai"),
+    ("tur", "Turkish"),
+    ("ukr", "Ukrainian"),
+    ("und", "Undetermined"),
+    ("vie", "Vietnamese"),
+    ("zxx", "No linguistic content"),
+];
+
+/// The English name of an ISO-639-2 code from [`LANGUAGES`].
+pub fn language_name(code: &str) -> Option<&'static str> {
+    LANGUAGES
+        .iter()
+        .find(|(c, _)| c.eq_ignore_ascii_case(code))
+        .map(|(_, name)| *name)
+}
+
+/// Whether `code` has the shape of an ISO-639-2 code: three ASCII letters.
+pub fn is_language_code(code: &str) -> bool {
+    code.len() == 3 && code.chars().all(|c| c.is_ascii_alphabetic())
+}
+
+/// Lyrics text from a `.txt` file: without a byte order mark, with `\n`
+/// line breaks as ID3 expects, and without trailing blank lines.
+pub fn from_txt(text: &str) -> String {
+    text.trim_start_matches('\u{feff}')
+        .replace("\r\n", "\n")
+        .replace('\r', "\n")
+        .trim_end()
+        .to_string()
+}
+
+/// The contents of a `.txt` file for `lyrics`.
+pub fn to_txt(lyrics: &Lyrics) -> String {
+    format!("{}\n", lyrics.text.trim_end())
+}
+
+/// Tells lyrics files apart by language and description, e.g. ` (eng, live)`.
+pub fn file_suffix(lang: &str, description: &str) -> String {
+    if description.is_empty() {
+        format!(" ({})", lang)
+    } else {
+        format!(" ({}, {})", lang, description)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn txt_files_are_normalised() {
+        assert_eq!(from_txt("\u{feff}One\r\nTwo\rThree\n\n"), "One\nTwo\nThree");
+    }
+
+    #[test]
+    fn languages_are_looked_up_by_code() {
+        assert_eq!(language_name("ENG"), Some("English"));
+        assert_eq!(language_name("xyz"), None);
+        assert!(is_language_code("xyz"));
+        assert!(!is_language_code("en"));
+        assert!(LANGUAGES.windows(2).all(|w| w[0].0 < w[1].0));
+    }
+}
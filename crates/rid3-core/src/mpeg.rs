@@ -0,0 +1,79 @@
+//! Just enough of the MPEG audio frame header to time things in frames.
+
+/// How far into the audio to look for the first frame header.
+const SEARCH_LEN: usize = 64 * 1024;
+
+/// The properties of an MPEG audio frame that timing depends on.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FrameHeader {
+    pub sample_rate: u32,
+    pub samples_per_frame: u32,
+}
+
+impl FrameHeader {
+    /// Parses the four header bytes at the start of `bytes`.
+    pub fn parse(bytes: &[u8]) -> Option<FrameHeader> {
+        let [b0, b1, b2, ..] = *bytes else {
+            return None;
+        };
+        if b0 != 0xff || b1 & 0xe0 != 0xe0 {
+            return None;
+        }
+        // 0 = MPEG 2.5, 2 = MPEG 2, 3 = MPEG 1.
+        let version = (b1 >> 3) & 0b11;
+        // 1 = Layer III, 2 = Layer II, 3 = Layer I.
+        let layer = (b1 >> 1) & 0b11;
+        let rates = match version {
+            3 => [44100, 48000, 32000],
+            2 => [22050, 24000, 16000],
+            0 => [11025, 12000, 8000],
+            _ => return None,
+        };
+        let sample_rate = *rates.get(usize::from((b2 >> 2) & 0b11))?;
+        let samples_per_frame = match (layer, version) {
+            (3, _) => 384,
+            (2, _) | (1, 3) => 1152,
+            (1, _) => 576,
+            _ => return None,
+        };
+        Some(FrameHeader {
+            sample_rate,
+            samples_per_frame,
+        })
+    }
+
+    /// Finds the first frame header near the start of `audio`.
+    pub fn find(audio: &[u8]) -> Option<FrameHeader> {
+        let end = audio.len().min(SEARCH_LEN);
+        (0..end).find_map(|at| FrameHeader::parse(&audio[at..]))
+    }
+
+    /// How long one frame plays, in milliseconds.
+    pub fn frame_ms(&self) -> f64 {
+        f64::from(self.samples_per_frame) * 1000.0 / f64::from(self.sample_rate)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn layer_three_headers() {
+        let mpeg1 = FrameHeader::parse(&[0xff, 0xfb, 0x90, 0x00]).unwrap();
+        assert_eq!(mpeg1.sample_rate, 44100);
+        assert_eq!(mpeg1.samples_per_frame, 1152);
+        assert!((mpeg1.frame_ms() - 26.122).abs() < 0.001);
+
+        let mpeg2 = FrameHeader::parse(&[0xff, 0xf3, 0x84, 0x00]).unwrap();
+        assert_eq!(mpeg2.sample_rate, 24000);
+        assert_eq!(mpeg2.samples_per_frame, 576);
+    }
+
+    #[test]
+    fn finds_the_header_after_junk() {
+        let audio = [0x00, 0x12, 0xff, 0xfb, 0x90, 0x00];
+        assert_eq!(FrameHeader::find(&audio).unwrap().sample_rate, 44100);
+        assert_eq!(FrameHeader::find(&[0x00; 16]), None);
+    }
+}
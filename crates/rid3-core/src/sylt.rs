@@ -0,0 +1,174 @@
+//! Synchronised lyrics (`SYLT`): timestamps, `.lrc` files and lines to stamp.
+
+use id3::frame::{SynchronisedLyricsType, TimestampFormat};
+
+use crate::chapters::format_time;
+
+/// Every `SYLT` content type, in the order of their codes.
+pub const CONTENT_TYPES: [SynchronisedLyricsType; 7] = [
+    SynchronisedLyricsType::Other,
+    SynchronisedLyricsType::Lyrics,
+    SynchronisedLyricsType::Transcription,
+    SynchronisedLyricsType::PartName,
+    SynchronisedLyricsType::Event,
+    SynchronisedLyricsType::Chord,
+    SynchronisedLyricsType::Trivia,
+];
+
+/// Both timestamp formats.
+pub const TIMESTAMP_FORMATS: [TimestampFormat; 2] = [TimestampFormat::Ms, TimestampFormat::Mpeg];
+
+/// The non-empty lines of pasted text, ready to be stamped one by one.
+pub fn lines(text: &str) -> Vec<String> {
+    text.lines()
+        .map(str::trim)
+        .filter(|l| !l.is_empty())
+        .map(String::from)
+        .collect()
+}
+
+/// Converts a timestamp between formats. `frame_ms` is how long an MPEG
+/// frame plays, see [`crate::mpeg::FrameHeader::frame_ms`].
+pub fn convert_timestamp(
+    timestamp: u32,
+    from: TimestampFormat,
+    to: TimestampFormat,
+    frame_ms: f64,
+) -> u32 {
+    match (from, to) {
+        (TimestampFormat::Ms, TimestampFormat::Mpeg) => {
+            (f64::from(timestamp) / frame_ms).round() as u32
+        }
+        (TimestampFormat::Mpeg, TimestampFormat::Ms) => {
+            (f64::from(timestamp) * frame_ms).round() as u32
+        }
+        _ => timestamp,
+    }
+}
+
+/// A timestamp for display: a time for milliseconds, a frame number otherwise.
+pub fn format_timestamp(timestamp: u32, format: TimestampFormat) -> String {
+    match format {
+        TimestampFormat::Ms => format_time(timestamp),
+        TimestampFormat::Mpeg => format!("frame {}", timestamp),
+    }
+}
+
+/// Reads the timed lines of an `.lrc` file, in milliseconds and sorted by
+/// time. Lines with several time tags are repeated at each time, and an
+/// `[offset:]` tag is applied. Other tags, such as `[ar:]`, are skipped.
+pub fn parse_lrc(text: &str) -> Vec<(u32, String)> {
+    let mut offset: i64 = 0;
+    let mut timed = Vec::new();
+    for line in text.trim_start_matches('\u{feff}').lines() {
+        let mut rest = line.trim();
+        let mut times = Vec::new();
+        while let Some(tag) = rest.strip_prefix('[') {
+            let Some(end) = tag.find(']') else {
+                break;
+            };
+            let (inner, after) = (&tag[..end], &tag[end + 1..]);
+            if let Some(ms) = parse_lrc_time(inner) {
+                times.push(ms);
+            } else if let Some(value) = inner.strip_prefix("offset:") {
+                offset = value.trim().parse().unwrap_or(0);
+            }
+            rest = after;
+        }
+        for ms in times {
+            let shifted = (i64::from(ms) - offset).clamp(0, i64::from(u32::MAX)) as u32;
+            timed.push((shifted, rest.trim().to_string()));
+        }
+    }
+    timed.sort_by_key(|(ms, _)| *ms);
+    timed
+}
+
+/// Writes timed lines in milliseconds as an `.lrc` file.
+pub fn to_lrc(lines: &[(u32, String)]) -> String {
+    lines
+        .iter()
+        .map(|(ms, text)| {
+            let centis = ms / 10;
+            format!(
+                "[{:02}:{:02}.{:02}]{}\n",
+                centis / 6000,
+                centis / 100 % 60,
+                centis % 100,
+                text
+            )
+        })
+        .collect()
+}
+
+/// `mm:ss`, `mm:ss.xx` or `mm:ss.xxx` in milliseconds.
+fn parse_lrc_time(s: &str) -> Option<u32> {
+    let (minutes, seconds) = s.split_once(':')?;
+    let minutes: u32 = minutes.parse().ok()?;
+    let (whole, fraction) = seconds.split_once('.').unwrap_or((seconds, ""));
+    let whole: u32 = whole.parse().ok()?;
+    if whole >= 60 || fraction.len() > 3 || !fraction.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    let fraction: u32 = format!("{:0<3}", fraction).parse().ok()?;
+    minutes
+        .checked_mul(60)?
+        .checked_add(whole)?
+        .checked_mul(1000)?
+        .checked_add(fraction)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lrc_round_trips() {
+        let lrc = "[ar:Someone]\n[00:01.50]First\n[00:03.00][01:00.25]Chorus\n";
+        let timed = parse_lrc(lrc);
+        assert_eq!(
+            timed,
+            [
+                (1500, "First".to_string()),
+                (3000, "Chorus".to_string()),
+                (60250, "Chorus".to_string()),
+            ]
+        );
+        assert_eq!(
+            to_lrc(&timed),
+            "[00:01.50]First\n[00:03.00]Chorus\n[01:00.25]Chorus\n"
+        );
+    }
+
+    #[test]
+    fn lrc_offset_shifts_lines_earlier() {
+        assert_eq!(
+            parse_lrc("[offset:500]\n[00:02.000]Line"),
+            [(1500, "Line".to_string())]
+        );
+    }
+
+    #[test]
+    fn lrc_times_past_u32_are_skipped() {
+        assert_eq!(
+            parse_lrc("[99999:00.00][00:01.00]Line"),
+            [(1000, "Line".to_string())]
+        );
+    }
+
+    #[test]
+    fn timestamps_convert_through_frames() {
+        let frame_ms = 26.0;
+        let frames = convert_timestamp(2600, TimestampFormat::Ms, TimestampFormat::Mpeg, frame_ms);
+        assert_eq!(frames, 100);
+        assert_eq!(
+            convert_timestamp(frames, TimestampFormat::Mpeg, TimestampFormat::Ms, frame_ms),
+            2600
+        );
+    }
+
+    #[test]
+    fn pasted_lines_skip_blanks() {
+        assert_eq!(lines(" One \n\nTwo\n"), ["One", "Two"]);
+    }
+}
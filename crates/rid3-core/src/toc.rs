@@ -0,0 +1,339 @@
+//! `CTOC` tables of contents, which list chapters and other tables by
+//! element ID. One table is the top level; the rest nest below it.
+
+use id3::frame::{Chapter, TableOfContents};
+use id3::{Content, Frame, Tag, TagLike};
+
+use crate::frame::{retain_frames, set_content};
+
+/// The table every other element hangs from, if the tag has one.
+pub fn top_level(tag: &Tag) -> Option<&TableOfContents> {
+    tag.tables_of_contents().find(|t| t.top_level)
+}
+
+/// The `TIT2` title stored inside a table, if any.
+pub fn toc_title(toc: &TableOfContents) -> Option<&str> {
+    toc.frames
+        .iter()
+        .find(|f| f.id() == "TIT2")
+        .and_then(|f| f.content().text())
+}
+
+/// Sets the table's `TIT2` title. An empty title removes it.
+pub fn set_toc_title(toc: &mut TableOfContents, title: &str) {
+    let position = toc.frames.iter().position(|f| f.id() == "TIT2");
+    toc.frames.retain(|f| f.id() != "TIT2");
+    if !title.is_empty() {
+        let index = position.unwrap_or(toc.frames.len());
+        toc.frames.insert(index, Frame::text("TIT2", title));
+    }
+}
+
+/// Replaces the table that has the same element ID as `toc`, keeping its
+/// place in the tag. Making it the top level takes the flag from any other
+/// table. Returns false if there was none.
+pub fn set_toc(tag: &mut Tag, toc: TableOfContents) -> bool {
+    let key = format!("CTOC:{}", toc.element_id);
+    if !set_content(tag, &key, Content::TableOfContents(toc.clone())) {
+        return false;
+    }
+    if toc.top_level {
+        let others: Vec<TableOfContents> = tag
+            .tables_of_contents()
+            .filter(|t| t.top_level && t.element_id != toc.element_id)
+            .map(|t| TableOfContents {
+                top_level: false,
+                ..t.clone()
+            })
+            .collect();
+        for other in others {
+            replace(tag, other);
+        }
+    }
+    true
+}
+
+/// Adds an empty, ordered table below `parent` and returns its element ID.
+/// Returns None if there is no table with the ID `parent`.
+pub fn add_toc(tag: &mut Tag, parent: &str, title: &str) -> Option<String> {
+    let mut parent = find(tag, parent)?.clone();
+    let element_id = next_toc_id(tag);
+    let mut toc = TableOfContents {
+        element_id: element_id.clone(),
+        top_level: false,
+        ordered: true,
+        elements: Vec::new(),
+        frames: Vec::new(),
+    };
+    set_toc_title(&mut toc, title);
+    tag.add_frame(toc);
+    parent.elements.push(element_id.clone());
+    replace(tag, parent);
+    Some(element_id)
+}
+
+/// Removes the table with the given element ID and every reference to it.
+/// What it listed is left to [`sync`]. Returns false if there was none.
+pub fn remove_toc(tag: &mut Tag, element_id: &str) -> bool {
+    let before = tag.tables_of_contents().count();
+    retain_frames(tag, |f| {
+        f.content()
+            .table_of_contents()
+            .is_none_or(|t| t.element_id != element_id)
+    });
+    if tag.tables_of_contents().count() == before {
+        return false;
+    }
+    remove_references(tag, element_id);
+    true
+}
+
+/// Drops `element_id` from the child list of every table.
+pub fn remove_references(tag: &mut Tag, element_id: &str) {
+    let listing: Vec<TableOfContents> = tag
+        .tables_of_contents()
+        .filter(|t| t.elements.iter().any(|e| e == element_id))
+        .map(|t| TableOfContents {
+            elements: t
+                .elements
+                .iter()
+                .filter(|e| *e != element_id)
+                .cloned()
+                .collect(),
+            ..t.clone()
+        })
+        .collect();
+    for toc in listing {
+        replace(tag, toc);
+    }
+}
+
+/// Moves a chapter or table from wherever it is listed to the end of the
+/// table `to`. A table can't be moved below itself. Returns whether it moved.
+pub fn move_element(tag: &mut Tag, element_id: &str, to: &str) -> bool {
+    if find(tag, to).is_none()
+        || element_id == to
+        || descendants(tag, element_id).iter().any(|d| d == to)
+    {
+        return false;
+    }
+    remove_references(tag, element_id);
+    let Some(mut target) = find(tag, to).cloned() else {
+        return false;
+    };
+    target.elements.push(element_id.to_string());
+    replace(tag, target);
+    let hidden = hidden(tag);
+    sync_except(tag, &hidden);
+    true
+}
+
+/// Brings the tables up to date with the chapters. A tag with chapters but
+/// no tables gets a top-level one, chapters no table lists are added to the
+/// top level, and ordered tables list their children by start time.
+pub fn sync(tag: &mut Tag) {
+    sync_except(tag, &[]);
+}
+
+/// [`sync`], except that the chapters in `hidden` are not added to the top
+/// level, as for chapters players should not list. A new top-level table
+/// still lists every chapter.
+pub fn sync_except(tag: &mut Tag, hidden: &[String]) {
+    let mut hidden = hidden;
+    if tag.tables_of_contents().next().is_none() {
+        if tag.chapters().next().is_none() {
+            return;
+        }
+        tag.add_frame(TableOfContents {
+            element_id: next_toc_id(tag),
+            top_level: true,
+            ordered: true,
+            elements: Vec::new(),
+            frames: Vec::new(),
+        });
+        hidden = &[];
+    }
+
+    let unlisted = unlisted(tag);
+    let mut unlisted: Vec<&Chapter> = tag
+        .chapters()
+        .filter(|c| unlisted.contains(&c.element_id) && !hidden.contains(&c.element_id))
+        .collect();
+    unlisted.sort_by_key(|c| c.start_time);
+    let unlisted: Vec<String> = unlisted.into_iter().map(|c| c.element_id.clone()).collect();
+
+    let mut tables: Vec<TableOfContents> = tag.tables_of_contents().cloned().collect();
+    for toc in &mut tables {
+        if toc.top_level {
+            toc.elements.extend(unlisted.iter().cloned());
+        }
+        if toc.ordered {
+            toc.elements.sort_by_key(|e| start_time(tag, e, 0));
+        }
+    }
+    for toc in tables {
+        replace(tag, toc);
+    }
+}
+
+/// The element IDs of the chapters no table lists.
+pub fn unlisted(tag: &Tag) -> Vec<String> {
+    let listed: Vec<&String> = tag.tables_of_contents().flat_map(|t| &t.elements).collect();
+    tag.chapters()
+        .map(|c| &c.element_id)
+        .filter(|id| !listed.contains(id))
+        .cloned()
+        .collect()
+}
+
+/// The chapters left out of the tables on purpose: those no table lists,
+/// in a tag with a top-level table. Players show them only as they play, if
+/// at all. Without a top level there is no telling them from chapters that
+/// were never listed, so none count as hidden.
+pub fn hidden(tag: &Tag) -> Vec<String> {
+    if top_level(tag).is_none() {
+        return Vec::new();
+    }
+    unlisted(tag)
+}
+
+/// The element IDs below the table `element_id`, however deeply nested.
+pub fn descendants(tag: &Tag, element_id: &str) -> Vec<String> {
+    let mut found: Vec<String> = Vec::new();
+    let mut pending = vec![element_id.to_string()];
+    while let Some(id) = pending.pop() {
+        if let Some(toc) = find(tag, &id) {
+            for child in &toc.elements {
+                if !found.contains(child) {
+                    found.push(child.clone());
+                    pending.push(child.clone());
+                }
+            }
+        }
+    }
+    found
+}
+
+/// Returns the first `tocN` element ID not used by any frame in the tag,
+/// starting with a plain `toc`.
+pub fn next_toc_id(tag: &Tag) -> String {
+    let used: Vec<&str> = tag
+        .chapters()
+        .map(|c| c.element_id.as_str())
+        .chain(tag.tables_of_contents().map(|t| t.element_id.as_str()))
+        .collect();
+    std::iter::once("toc".to_string())
+        .chain((1..).map(|n| format!("toc{}", n)))
+        .find(|id| !used.contains(&id.as_str()))
+        .unwrap()
+}
+
+fn find<'a>(tag: &'a Tag, element_id: &str) -> Option<&'a TableOfContents> {
+    tag.tables_of_contents()
+        .find(|t| t.element_id == element_id)
+}
+
+/// When an element starts: a chapter's start time, or a table's earliest
+/// child. Elements with no time sort last.
+fn start_time(tag: &Tag, element_id: &str, depth: usize) -> u32 {
+    if let Some(chapter) = tag.chapters().find(|c| c.element_id == element_id) {
+        return chapter.start_time;
+    }
+    // Tables that list each other would otherwise never finish.
+    if depth > tag.tables_of_contents().count() {
+        return u32::MAX;
+    }
+    find(tag, element_id)
+        .and_then(|t| {
+            t.elements
+                .iter()
+                .map(|e| start_time(tag, e, depth + 1))
+                .min()
+        })
+        .unwrap_or(u32::MAX)
+}
+
+fn replace(tag: &mut Tag, toc: TableOfContents) {
+    let key = format!("CTOC:{}", toc.element_id);
+    set_content(tag, &key, Content::TableOfContents(toc));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chapters::{add_chapter, remove_chapter};
+
+    fn elements(tag: &Tag, element_id: &str) -> Vec<String> {
+        find(tag, element_id).unwrap().elements.clone()
+    }
+
+    #[test]
+    fn top_level_table_follows_the_chapters() {
+        let mut tag = Tag::new();
+        sync(&mut tag);
+        assert_eq!(tag.tables_of_contents().count(), 0);
+
+        add_chapter(&mut tag, 5000, 9000, "Second");
+        add_chapter(&mut tag, 0, 5000, "First");
+        sync(&mut tag);
+        let top = top_level(&tag).unwrap();
+        assert_eq!(top.element_id, "toc");
+        assert!(top.ordered);
+        assert_eq!(top.elements, ["chp1", "chp0"]);
+
+        remove_chapter(&mut tag, "chp1");
+        sync(&mut tag);
+        assert_eq!(elements(&tag, "toc"), ["chp0"]);
+    }
+
+    #[test]
+    fn nested_tables_keep_their_chapters() {
+        let mut tag = Tag::new();
+        add_chapter(&mut tag, 0, 1000, "Intro");
+        add_chapter(&mut tag, 1000, 2000, "Part one");
+        add_chapter(&mut tag, 2000, 3000, "Part two");
+        sync(&mut tag);
+
+        let part = add_toc(&mut tag, "toc", "Parts").unwrap();
+        assert_eq!(part, "toc1");
+        assert!(move_element(&mut tag, "chp2", &part));
+        assert!(move_element(&mut tag, "chp1", &part));
+        assert!(!move_element(&mut tag, "toc", &part));
+        assert_eq!(elements(&tag, "toc"), ["chp0", "toc1"]);
+        assert_eq!(elements(&tag, "toc1"), ["chp1", "chp2"]);
+        assert_eq!(descendants(&tag, "toc"), ["chp0", "toc1", "chp1", "chp2"]);
+
+        assert!(remove_toc(&mut tag, "toc1"));
+        sync(&mut tag);
+        assert_eq!(elements(&tag, "toc"), ["chp0", "chp1", "chp2"]);
+    }
+
+    #[test]
+    fn hidden_chapters_stay_unlisted() {
+        let mut tag = Tag::new();
+        add_chapter(&mut tag, 0, 1000, "Intro");
+        add_chapter(&mut tag, 500, 600, "Picture change");
+        sync_except(&mut tag, &["chp1".to_string()]);
+        assert_eq!(elements(&tag, "toc"), ["chp0", "chp1"]);
+
+        remove_references(&mut tag, "chp1");
+        let hidden = unlisted(&tag);
+        add_chapter(&mut tag, 1000, 2000, "Outro");
+        sync_except(&mut tag, &hidden);
+        assert_eq!(elements(&tag, "toc"), ["chp0", "chp2"]);
+        assert_eq!(unlisted(&tag), ["chp1"]);
+    }
+
+    #[test]
+    fn only_one_table_is_top_level() {
+        let mut tag = Tag::new();
+        add_chapter(&mut tag, 0, 1000, "Intro");
+        sync(&mut tag);
+        add_toc(&mut tag, "toc", "").unwrap();
+        let mut nested = find(&tag, "toc1").unwrap().clone();
+        nested.top_level = true;
+        assert!(set_toc(&mut tag, nested));
+        assert_eq!(top_level(&tag).unwrap().element_id, "toc1");
+        assert_eq!(tag.tables_of_contents().filter(|t| t.top_level).count(), 1);
+    }
+}
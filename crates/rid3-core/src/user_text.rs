@@ -0,0 +1,60 @@
+//! User-defined text (`TXXX`) and the descriptions taggers commonly use.
+
+/// Descriptions written by MusicBrainz Picard, ReplayGain scanners and other
+/// taggers, with what their values hold.
+pub const COMMON_DESCRIPTIONS: &[(&str, &str)] = &[
+    ("Acoustid Id", "AcoustID fingerprint ID"),
+    ("ASIN", "Amazon product ID"),
+    ("BARCODE", "UPC or EAN of the release"),
+    ("CATALOGNUMBER", "The label's catalogue number"),
+    (
+        "MusicBrainz Album Artist Id",
+        "MusicBrainz ID of the album artist",
+    ),
+    ("MusicBrainz Album Id", "MusicBrainz release ID"),
+    (
+        "MusicBrainz Album Release Country",
+        "Country of release, e.g. GB",
+    ),
+    ("MusicBrainz Album Status", "official, promotion, bootleg…"),
+    ("MusicBrainz Album Type", "album, single, ep…"),
+    (
+        "MusicBrainz Artist Id",
+        "MusicBrainz ID of the track artist",
+    ),
+    (
+        "MusicBrainz Release Group Id",
+        "MusicBrainz release group ID",
+    ),
+    ("MusicBrainz Release Track Id", "MusicBrainz track ID"),
+    ("MusicBrainz Work Id", "MusicBrainz work ID"),
+    ("REPLAYGAIN_ALBUM_GAIN", "Album gain, e.g. -7.50 dB"),
+    ("REPLAYGAIN_ALBUM_PEAK", "Album peak, e.g. 0.988525"),
+    ("REPLAYGAIN_TRACK_GAIN", "Track gain, e.g. -6.48 dB"),
+    ("REPLAYGAIN_TRACK_PEAK", "Track peak, e.g. 0.977203"),
+    ("SCRIPT", "ISO 15924 script of the titles, e.g. Latn"),
+];
+
+/// What the value of a commonly used description holds, ignoring case.
+pub fn hint(description: &str) -> Option<&'static str> {
+    COMMON_DESCRIPTIONS
+        .iter()
+        .find(|(d, _)| d.eq_ignore_ascii_case(description))
+        .map(|(_, hint)| *hint)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn common_descriptions_are_unique() {
+        for (i, (a, _)) in COMMON_DESCRIPTIONS.iter().enumerate() {
+            assert!(COMMON_DESCRIPTIONS[i + 1..]
+                .iter()
+                .all(|(b, _)| !a.eq_ignore_ascii_case(b)));
+        }
+        assert_eq!(hint("barcode"), Some("UPC or EAN of the release"));
+        assert_eq!(hint("MOOD"), None);
+    }
+}
@@ -0,0 +1,79 @@
+//! Text frames holding several values, such as a list of artists or genres.
+//!
+//! ID3v2.4 separates the values with NUL, and the tag keeps them that way
+//! while editing. ID3v2.3 has no such separator; the spec joins performers
+//! with "/" and other taggers often use ";", so those lists are only split
+//! when asked to.
+
+/// Separates the values of a text frame in ID3v2.4.
+pub const SEPARATOR: char = '\0';
+
+/// The separator values are joined with when saving as ID3v2.3 or ID3v2.2.
+pub const V23_SEPARATOR: &str = "/";
+
+/// Text frames that commonly hold more than one value.
+pub const LIST_FRAMES: &[&str] = &[
+    "TCOM", "TCON", "TEXT", "TLAN", "TOLY", "TOPE", "TPE1", "TPE2", "TPE3", "TPE4",
+];
+
+/// The values of a text frame.
+pub fn split(text: &str) -> Vec<String> {
+    text.split(SEPARATOR).map(String::from).collect()
+}
+
+/// Joins values into the text of a frame.
+pub fn join(values: &[String]) -> String {
+    values.join(&SEPARATOR.to_string())
+}
+
+/// Splits text written by an ID3v2.3 tagger at "/" or ";", if it contains
+/// either. Only the first of the two found is used.
+pub fn split_legacy(text: &str) -> Option<Vec<String>> {
+    let separator = text.chars().find(|c| *c == '/' || *c == ';')?;
+    Some(
+        text.split(separator)
+            .map(|v| v.trim().to_string())
+            .filter(|v| !v.is_empty())
+            .collect(),
+    )
+}
+
+/// The values joined for ID3v2.3, and whether they can be told apart again:
+/// they can't if one of them contains the separator itself.
+pub fn join_for_v23(text: &str) -> (String, bool) {
+    let values = split(text);
+    let exact = values.iter().all(|v| !v.contains(V23_SEPARATOR));
+    (values.join(V23_SEPARATOR), exact)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn values_round_trip() {
+        let values = vec!["Alice".to_string(), "Bob".to_string()];
+        assert_eq!(join(&values), "Alice\0Bob");
+        assert_eq!(split("Alice\0Bob"), values);
+        assert_eq!(split("Alice"), ["Alice"]);
+    }
+
+    #[test]
+    fn legacy_lists_split_at_the_first_separator() {
+        assert_eq!(
+            split_legacy("Rock; Pop"),
+            Some(vec!["Rock".into(), "Pop".into()])
+        );
+        assert_eq!(
+            split_legacy("Alice/Bob"),
+            Some(vec!["Alice".into(), "Bob".into()])
+        );
+        assert_eq!(split_legacy("Alice"), None);
+    }
+
+    #[test]
+    fn v23_join_reports_ambiguity() {
+        assert_eq!(join_for_v23("Alice\0Bob"), ("Alice/Bob".to_string(), true));
+        assert_eq!(join_for_v23("AC/DC\0Bob"), ("AC/DC/Bob".to_string(), false));
+    }
+}
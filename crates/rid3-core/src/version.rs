@@ -0,0 +1,477 @@
+use std::fmt;
+
+use id3::frame::{InvolvedPeopleList, InvolvedPeopleListItem, Picture};
+use id3::{Content, Frame, Tag, TagLike, Timestamp, Version};
+
+use crate::art::{self, ImageFormat};
+use crate::values::{self, SEPARATOR, V23_SEPARATOR};
+
+/// Every ID3v2 version the editor can write, oldest first.
+pub const VERSIONS: [Version; 3] = [Version::Id3v22, Version::Id3v23, Version::Id3v24];
+
+/// Frames defined by ID3v2.4 that ID3v2.3 has no equivalent for.
+const ONLY_IN_V24: &[&str] = &[
+    "ASPI", "EQU2", "RVA2", "SEEK", "SIGN", "TDEN", "TDRL", "TDTG", "TMOO", "TPRO", "TSOA", "TSOP",
+    "TSOT", "TSST",
+];
+
+/// Frames defined by ID3v2.3 that ID3v2.4 dropped without a replacement.
+const ONLY_IN_V23: &[&str] = &["EQUA", "RVAD", "TRDA", "TSIZ"];
+
+/// The short form of a version, e.g. `2.3`.
+pub fn short_name(version: Version) -> &'static str {
+    match version {
+        Version::Id3v22 => "2.2",
+        Version::Id3v23 => "2.3",
+        Version::Id3v24 => "2.4",
+    }
+}
+
+/// Parses `2.3`, `v2.3` or `ID3v2.3`.
+pub fn parse(s: &str) -> Option<Version> {
+    let s = s.trim().trim_start_matches("ID3").trim_start_matches('v');
+    VERSIONS.into_iter().find(|v| short_name(*v) == s)
+}
+
+/// A change made to a tag so it can be written in another version.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Change {
+    /// Several frames were combined into one.
+    Merged { from: Vec<String>, to: String },
+    /// One frame was split into several.
+    Split { from: String, to: Vec<String> },
+    /// A frame was renamed to its equivalent in the target version.
+    Renamed { from: String, to: String },
+    /// The values of a multi-value text frame were joined into one. Unless
+    /// `exact`, a value contained the separator and the list can't be split
+    /// back the way it was.
+    JoinedValues {
+        id: String,
+        separator: &'static str,
+        exact: bool,
+    },
+    /// The frame does not exist in the target version.
+    Dropped { id: String },
+    /// ID3v2.2 only stores JPEG and PNG pictures, so a picture in another
+    /// format, or a linked one, was dropped.
+    DroppedPicture { mime_type: String },
+}
+
+impl fmt::Display for Change {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Change::Merged { from, to } => write!(f, "{} merged into {}", from.join(" + "), to),
+            Change::Split { from, to } => write!(f, "{} split into {}", from, to.join(" + ")),
+            Change::Renamed { from, to } => write!(f, "{} converted to {}", from, to),
+            Change::JoinedValues {
+                id,
+                separator,
+                exact,
+            } => {
+                write!(f, "{} values joined with \"{}\"", id, separator)?;
+                if !exact {
+                    write!(f, ", but a value contains \"{}\" too", separator)?;
+                }
+                Ok(())
+            }
+            Change::Dropped { id } => write!(f, "{} dropped", id),
+            Change::DroppedPicture { mime_type } => write!(
+                f,
+                "APIC ({}) dropped, ID3v2.2 only stores JPEG and PNG pictures",
+                mime_type
+            ),
+        }
+    }
+}
+
+/// A tag rewritten for a target version, with the changes that were needed.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Conversion {
+    pub tag: Tag,
+    pub changes: Vec<Change>,
+}
+
+/// Rewrites `tag` so that every frame exists in `target`, converting frames
+/// between the ID3v2.3 and ID3v2.4 forms and dropping the rest.
+pub fn convert(tag: &Tag, target: Version) -> Conversion {
+    let mut changes = Vec::new();
+    let mut frames: Vec<Frame> = tag.frames().cloned().collect();
+
+    if target == Version::Id3v24 {
+        frames = to_v24(frames, &mut changes);
+    } else {
+        frames = to_v23(frames, &mut changes);
+    }
+    if target == Version::Id3v22 {
+        frames = to_v22_pictures(frames, &mut changes);
+    }
+
+    let mut converted = Tag::with_version(target);
+    for frame in frames {
+        if frame.id_for_version(target).is_none() {
+            changes.push(Change::Dropped {
+                id: frame.id().to_string(),
+            });
+        } else if matches!(frame.content(), Content::Picture(_)) {
+            // `add_frame` would drop pictures sharing a type with an
+            // earlier one, even when their descriptions differ.
+            converted.extend([frame]);
+        } else {
+            converted.add_frame(frame);
+        }
+    }
+    Conversion {
+        tag: converted,
+        changes,
+    }
+}
+
+/// The changes [`convert`] would make, without the converted tag.
+pub fn report(tag: &Tag, target: Version) -> Vec<Change> {
+    convert(tag, target).changes
+}
+
+fn take(frames: &mut Vec<Frame>, id: &str) -> Option<Frame> {
+    let index = frames.iter().position(|f| f.id() == id)?;
+    Some(frames.remove(index))
+}
+
+fn text(frame: &Option<Frame>) -> Option<&str> {
+    frame
+        .as_ref()
+        .and_then(|f| f.content().text())
+        .map(str::trim)
+}
+
+fn people(frame: Option<Frame>) -> Vec<InvolvedPeopleListItem> {
+    match frame.as_ref().map(Frame::content) {
+        Some(Content::InvolvedPeopleList(list)) => list.items.clone(),
+        _ => Vec::new(),
+    }
+}
+
+fn people_frame(id: &str, items: Vec<InvolvedPeopleListItem>) -> Frame {
+    Frame::with_content(
+        id,
+        Content::InvolvedPeopleList(InvolvedPeopleList { items }),
+    )
+}
+
+fn to_v23(mut frames: Vec<Frame>, changes: &mut Vec<Change>) -> Vec<Frame> {
+    let mut added = Vec::new();
+
+    if let Some(tdrc) = take(&mut frames, "TDRC") {
+        let parsed = tdrc.content().text().map(str::trim);
+        if let Some(ts) = parsed.and_then(|t| t.parse::<Timestamp>().ok()) {
+            let mut to = vec!["TYER".to_string()];
+            added.push(Frame::text("TYER", format!("{:04}", ts.year)));
+            if let (Some(month), Some(day)) = (ts.month, ts.day) {
+                added.push(Frame::text("TDAT", format!("{:02}{:02}", day, month)));
+                to.push("TDAT".to_string());
+            }
+            if let (Some(hour), Some(minute)) = (ts.hour, ts.minute) {
+                added.push(Frame::text("TIME", format!("{:02}{:02}", hour, minute)));
+                to.push("TIME".to_string());
+            }
+            changes.push(Change::Split {
+                from: "TDRC".to_string(),
+                to,
+            });
+        } else {
+            changes.push(Change::Dropped {
+                id: "TDRC".to_string(),
+            });
+        }
+    }
+
+    if let Some(tdor) = take(&mut frames, "TDOR") {
+        match tdor.content().text().map(str::trim).map(str::parse) {
+            Some(Ok(Timestamp { year, .. })) => {
+                added.push(Frame::text("TORY", format!("{:04}", year)));
+                changes.push(Change::Renamed {
+                    from: "TDOR".to_string(),
+                    to: "TORY".to_string(),
+                });
+            }
+            _ => changes.push(Change::Dropped {
+                id: "TDOR".to_string(),
+            }),
+        }
+    }
+
+    let tipl = take(&mut frames, "TIPL");
+    let tmcl = take(&mut frames, "TMCL");
+    if tipl.is_some() || tmcl.is_some() {
+        let from = [&tipl, &tmcl]
+            .into_iter()
+            .flatten()
+            .map(|f| f.id().to_string())
+            .collect();
+        let mut items = people(tipl);
+        items.extend(people(tmcl));
+        added.push(people_frame("IPLS", items));
+        changes.push(Change::Merged {
+            from,
+            to: "IPLS".to_string(),
+        });
+    }
+
+    for frame in frames {
+        if ONLY_IN_V24.contains(&frame.id()) {
+            changes.push(Change::Dropped {
+                id: frame.id().to_string(),
+            });
+        } else if let Some(text) = frame.content().text().filter(|t| t.contains(SEPARATOR)) {
+            let (joined, exact) = values::join_for_v23(text);
+            changes.push(Change::JoinedValues {
+                id: frame.id().to_string(),
+                separator: V23_SEPARATOR,
+                exact,
+            });
+            added.push(Frame::text(frame.id(), joined));
+        } else {
+            added.push(frame);
+        }
+    }
+    added
+}
+
+/// Gives `PIC` frames one of the two formats ID3v2.2 can write, going by the
+/// image data, and drops pictures in any other format.
+fn to_v22_pictures(frames: Vec<Frame>, changes: &mut Vec<Change>) -> Vec<Frame> {
+    frames
+        .into_iter()
+        .filter_map(|frame| {
+            let Some(picture) = frame.content().picture() else {
+                return Some(frame);
+            };
+            match art::format(picture) {
+                Some(format @ (ImageFormat::Jpeg | ImageFormat::Png)) => {
+                    let picture = Picture {
+                        mime_type: format.mime_type().to_string(),
+                        ..picture.clone()
+                    };
+                    Some(
+                        Frame::with_content(frame.id(), Content::Picture(picture))
+                            .set_encoding(frame.encoding()),
+                    )
+                }
+                _ => {
+                    changes.push(Change::DroppedPicture {
+                        mime_type: picture.mime_type.clone(),
+                    });
+                    None
+                }
+            }
+        })
+        .collect()
+}
+
+fn to_v24(mut frames: Vec<Frame>, changes: &mut Vec<Change>) -> Vec<Frame> {
+    let mut added = Vec::new();
+
+    let tyer = take(&mut frames, "TYER");
+    let tdat = take(&mut frames, "TDAT");
+    let time = take(&mut frames, "TIME");
+    if let Some(year) = text(&tyer).and_then(|y| y.parse().ok()) {
+        let mut ts = Timestamp {
+            year,
+            ..Timestamp::default()
+        };
+        let digits = |s: &str, range: std::ops::Range<usize>| s.get(range)?.parse().ok();
+        if let Some(date) = text(&tdat).filter(|d| d.len() == 4) {
+            ts.day = digits(date, 0..2);
+            ts.month = digits(date, 2..4);
+        }
+        if let Some(clock) = text(&time).filter(|t| t.len() == 4) {
+            if ts.day.is_some() {
+                ts.hour = digits(clock, 0..2);
+                ts.minute = digits(clock, 2..4);
+            }
+        }
+        added.push(Frame::text("TDRC", ts.to_string()));
+        let from: Vec<String> = [&tyer, &tdat, &time]
+            .into_iter()
+            .flatten()
+            .map(|f| f.id().to_string())
+            .collect();
+        changes.push(if from.len() == 1 {
+            Change::Renamed {
+                from: "TYER".to_string(),
+                to: "TDRC".to_string(),
+            }
+        } else {
+            Change::Merged {
+                from,
+                to: "TDRC".to_string(),
+            }
+        });
+    } else {
+        for frame in [tyer, tdat, time].into_iter().flatten() {
+            changes.push(Change::Dropped {
+                id: frame.id().to_string(),
+            });
+        }
+    }
+
+    if let Some(tory) = take(&mut frames, "TORY") {
+        added.push(Frame::text(
+            "TDOR",
+            tory.content().text().unwrap_or_default(),
+        ));
+        changes.push(Change::Renamed {
+            from: "TORY".to_string(),
+            to: "TDOR".to_string(),
+        });
+    }
+
+    if let Some(ipls) = take(&mut frames, "IPLS") {
+        added.push(people_frame("TIPL", people(Some(ipls))));
+        changes.push(Change::Renamed {
+            from: "IPLS".to_string(),
+            to: "TIPL".to_string(),
+        });
+    }
+
+    for frame in frames {
+        if ONLY_IN_V23.contains(&frame.id()) {
+            changes.push(Change::Dropped {
+                id: frame.id().to_string(),
+            });
+        } else {
+            added.push(frame);
+        }
+    }
+    added
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use id3::frame::PictureType;
+
+    #[test]
+    fn parses_short_and_long_names() {
+        assert_eq!(parse("2.4"), Some(Version::Id3v24));
+        assert_eq!(parse("ID3v2.2"), Some(Version::Id3v22));
+        assert_eq!(parse(&Version::Id3v23.to_string()), Some(Version::Id3v23));
+        assert_eq!(parse("2.5"), None);
+    }
+
+    #[test]
+    fn recording_time_is_split_for_v23() {
+        let mut tag = Tag::with_version(Version::Id3v24);
+        tag.set_text("TDRC", "2021-03-04T05:06");
+        tag.set_text("TSOP", "Artist, The");
+        tag.set_text_values("TPE1", ["A", "B"]);
+
+        let conversion = convert(&tag, Version::Id3v23);
+        let converted = conversion.tag;
+        assert_eq!(
+            converted.get("TYER").unwrap().content().text(),
+            Some("2021")
+        );
+        assert_eq!(
+            converted.get("TDAT").unwrap().content().text(),
+            Some("0403")
+        );
+        assert_eq!(
+            converted.get("TIME").unwrap().content().text(),
+            Some("0506")
+        );
+        assert_eq!(converted.get("TPE1").unwrap().content().text(), Some("A/B"));
+        assert!(converted.get("TSOP").is_none());
+        assert!(conversion.changes.contains(&Change::Dropped {
+            id: "TSOP".to_string()
+        }));
+    }
+
+    #[test]
+    fn ambiguous_value_lists_are_reported() {
+        let mut tag = Tag::with_version(Version::Id3v24);
+        tag.set_text_values("TPE1", ["AC/DC", "Bob"]);
+        let changes = report(&tag, Version::Id3v23);
+        assert_eq!(
+            changes,
+            [Change::JoinedValues {
+                id: "TPE1".to_string(),
+                separator: "/",
+                exact: false,
+            }]
+        );
+    }
+
+    #[test]
+    fn v22_keeps_only_jpeg_and_png_pictures() {
+        let mut tag = Tag::with_version(Version::Id3v23);
+        let picture = Picture {
+            mime_type: "image/png".to_string(),
+            picture_type: PictureType::CoverFront,
+            description: String::new(),
+            data: vec![0xff, 0xd8, 0xff, 0xe0],
+        };
+        tag.add_frame(picture.clone());
+        tag.add_frame(Picture {
+            mime_type: "image/gif".to_string(),
+            picture_type: PictureType::CoverBack,
+            data: b"GIF89a".to_vec(),
+            ..picture
+        });
+
+        let conversion = convert(&tag, Version::Id3v22);
+        let pictures: Vec<_> = conversion.tag.pictures().collect();
+        assert_eq!(pictures.len(), 1);
+        assert_eq!(pictures[0].mime_type, "image/jpeg");
+        assert_eq!(
+            conversion.changes,
+            [Change::DroppedPicture {
+                mime_type: "image/gif".to_string()
+            }]
+        );
+        let mut out = Vec::new();
+        conversion.tag.write_to(&mut out, Version::Id3v22).unwrap();
+    }
+
+    #[test]
+    fn date_frames_are_merged_for_v24() {
+        let mut tag = Tag::with_version(Version::Id3v23);
+        tag.set_text("TYER", "2021");
+        tag.set_text("TDAT", "0403");
+        tag.set_text("TIME", "0506");
+
+        let conversion = convert(&tag, Version::Id3v24);
+        assert_eq!(
+            conversion.tag.get("TDRC").unwrap().content().text(),
+            Some("2021-03-04T05:06")
+        );
+        assert_eq!(
+            conversion.changes,
+            [Change::Merged {
+                from: vec!["TYER".into(), "TDAT".into(), "TIME".into()],
+                to: "TDRC".into()
+            }]
+        );
+    }
+
+    #[test]
+    fn same_version_needs_no_changes() {
+        let mut tag = Tag::with_version(Version::Id3v23);
+        tag.set_title("Title");
+        tag.set_text("TYER", "2021");
+        assert!(report(&tag, Version::Id3v23).is_empty());
+    }
+
+    #[test]
+    fn frames_without_v22_id_are_dropped() {
+        let mut tag = Tag::with_version(Version::Id3v23);
+        tag.set_title("Title");
+        tag.set_text("TSRC", "ISRC");
+        tag.add_frame(Frame::link("WPAY", "https://example.com"));
+        crate::chapters::add_chapter(&mut tag, 0, 1000, "Intro");
+
+        let conversion = convert(&tag, Version::Id3v22);
+        let ids: Vec<&str> = conversion.tag.frames().map(|f| f.id()).collect();
+        assert_eq!(ids, ["TIT2", "TSRC"]);
+        assert_eq!(conversion.changes.len(), 2);
+    }
+}
@@ -0,0 +1,75 @@
+use id3::Version;
+use rid3_core::catalog;
+use web_sys::HtmlSelectElement;
+use yew::prelude::*;
+
+#[derive(Properties, PartialEq)]
+pub struct AddFrameProps {
+    /// The version whose frames are offered.
+    pub version: Version,
+    /// Keys of the frames already in the tag, see [`rid3_core::frame::key`].
+    pub present: Vec<String>,
+    pub on_add: Callback<String>,
+}
+
+/// Picks a frame ID from every frame valid for the version and adds an
+/// empty frame with it.
+#[function_component(AddFrame)]
+pub fn add_frame(
+    AddFrameProps {
+        version,
+        present,
+        on_add,
+    }: &AddFrameProps,
+) -> Html {
+    let selected = use_state(String::new);
+
+    let on_select = {
+        let selected = selected.clone();
+        Callback::from(move |e: Event| {
+            let select: HtmlSelectElement = e.target_unchecked_into();
+            selected.set(select.value());
+        })
+    };
+    let on_click = {
+        let selected = selected.clone();
+        let on_add = on_add.clone();
+        Callback::from(move |_: MouseEvent| {
+            if !selected.is_empty() {
+                on_add.emit((*selected).clone());
+                selected.set(String::new());
+            }
+        })
+    };
+
+    html! {
+        <div class="field has-addons">
+            <div class="control">
+                <div class="select is-small">
+                    <select onchange={on_select}>
+                        <option value="" selected={selected.is_empty()}>{"Add frame…"}</option>
+                        { for catalog::frames_for(*version).map(|info| {
+                            // Frames that need data, or that would replace one
+                            // already in the tag, are listed but cannot be picked.
+                            let available = catalog::new_frame(info.id).is_some_and(|frame| {
+                                !present.contains(&rid3_core::frame::key(&frame))
+                            });
+                            html! {
+                                <option
+                                    value={info.id}
+                                    disabled={!available}
+                                    selected={*selected == info.id}
+                                >
+                                    { format!("{} – {}", info.id, info.description) }
+                                </option>
+                            }
+                        }) }
+                    </select>
+                </div>
+            </div>
+            <div class="control">
+                <button class="button is-small" disabled={selected.is_empty()} onclick={on_click}>{"Add"}</button>
+            </div>
+        </div>
+    }
+}
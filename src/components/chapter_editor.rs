@@ -0,0 +1,452 @@
+use gloo_file::callbacks::FileReader;
+use gloo_file::File;
+use id3::frame::{Chapter, Picture, PictureType};
+use rid3_core::chapter_check::{Fix, Problem};
+use rid3_core::chapter_files::{self, PodcastChapters};
+use rid3_core::chapters::{self, format_time, parse_time};
+use rid3_core::{art, artwork, links};
+use web_sys::{HtmlInputElement, HtmlMediaElement};
+use yew::prelude::*;
+
+use super::picture_manager::read_image;
+use crate::download::{download_picture, picture_src};
+use crate::error::AppError;
+
+/// How far one click moves a chapter's start.
+const NUDGE_MS: i64 = 100;
+
+#[derive(Properties, PartialEq)]
+pub struct ChapterEditorProps {
+    /// Every `CHAP` frame in the tag, in tag order.
+    pub chapters: Vec<Chapter>,
+    /// What is wrong with the chapters, see [`rid3_core::chapter_check::check`].
+    pub problems: Vec<Problem>,
+    /// Keys of the frames edited since the tag was loaded or last saved.
+    pub changed: Vec<String>,
+    /// The player, whose length new chapters end at.
+    pub audio_ref: NodeRef,
+    /// The saved MP3's name, which downloaded pictures are named after.
+    pub download_name: String,
+    /// Receives an edited chapter, which keeps its element ID, with its
+    /// index in `chapters`.
+    pub on_change: Callback<(usize, Chapter)>,
+    /// Moves the start of the chapter at the given index by the given
+    /// number of milliseconds, along with the end of the chapter before it.
+    pub on_nudge: Callback<(usize, i64)>,
+    /// Applies a fix to the problems it is offered for.
+    pub on_fix: Callback<Fix>,
+    /// Replaces the chapters with those read from a chapters file.
+    pub on_import: Callback<PodcastChapters>,
+    /// Downloads the chapters as Podcasting 2.0 JSON.
+    pub on_export_json: Callback<MouseEvent>,
+    /// Downloads the chapters as Podlove Simple Chapters.
+    pub on_export_podlove: Callback<MouseEvent>,
+    /// Adds a chapter with the given start and end in milliseconds and title.
+    pub on_add: Callback<(u32, u32, String)>,
+    /// Removes the chapter at the given index in `chapters`.
+    pub on_remove: Callback<usize>,
+    /// Plays from the given position, in seconds.
+    pub on_seek: Callback<f64>,
+    pub on_error: Callback<AppError>,
+}
+
+/// Edits the tag's chapters: their times, title, link and picture. New
+/// chapters get the next free element ID and start where the last one ends.
+/// The chapters can also be swapped for, or saved as, a podcast chapters file.
+#[function_component(ChapterEditor)]
+pub fn chapter_editor(
+    ChapterEditorProps {
+        chapters,
+        problems,
+        changed,
+        audio_ref,
+        download_name,
+        on_change,
+        on_nudge,
+        on_fix,
+        on_import,
+        on_export_json,
+        on_export_podlove,
+        on_add,
+        on_remove,
+        on_seek,
+        on_error,
+    }: &ChapterEditorProps,
+) -> Html {
+    let new_title = use_state(String::new);
+    let reader = use_mut_ref(|| None::<FileReader>);
+
+    let mut fixes: Vec<Fix> = Vec::new();
+    for fix in problems.iter().map(Problem::fix) {
+        if !fixes.contains(&fix) {
+            fixes.push(fix);
+        }
+    }
+
+    let on_new_title = {
+        let new_title = new_title.clone();
+        Callback::from(move |e: InputEvent| {
+            let input: HtmlInputElement = e.target_unchecked_into();
+            new_title.set(input.value());
+        })
+    };
+    let on_add_click = {
+        let start = chapters.iter().map(|c| c.end_time).max().unwrap_or(0);
+        let audio_ref = audio_ref.clone();
+        let on_add = on_add.clone();
+        let new_title = new_title.clone();
+        Callback::from(move |_: MouseEvent| {
+            let duration = audio_ref
+                .cast::<HtmlMediaElement>()
+                .map(|audio| audio.duration())
+                .filter(|d| d.is_finite())
+                .map_or(0, |d| (d * 1000.0).round() as u32);
+            on_add.emit((start, duration.max(start), (*new_title).clone()));
+            new_title.set(String::new());
+        })
+    };
+
+    let on_import_file = {
+        let on_import = on_import.clone();
+        let on_error = on_error.clone();
+        Callback::from(move |e: Event| {
+            let input: HtmlInputElement = e.target_unchecked_into();
+            let Some(file) = input.files().and_then(|files| files.get(0)) else {
+                return;
+            };
+            let on_import = on_import.clone();
+            let on_error = on_error.clone();
+            let task = gloo_file::callbacks::read_as_text(&File::from(file), move |text| {
+                let read = text
+                    .map_err(AppError::from)
+                    .and_then(|text| chapter_files::parse(&text).map_err(AppError::from));
+                match read {
+                    Ok(chapters) => on_import.emit(chapters),
+                    Err(err) => on_error.emit(err),
+                }
+            });
+            *reader.borrow_mut() = Some(task);
+        })
+    };
+
+    html! {
+        <div class="container">
+            <div class="card">
+                <header class="card-header">
+                    <p class="card-header-title">{"Chapters"}</p>
+                </header>
+                <div class="card-content">
+                    if !problems.is_empty() {
+                        <div class="notification is-danger is-light">
+                            <ul>
+                                { for problems.iter().map(|p| html! { <li>{ p.to_string() }</li> }) }
+                            </ul>
+                            <div class="buttons mt-2">
+                                { for fixes.into_iter().map(|fix| html! {
+                                    <button class="button is-small" onclick={on_fix.reform(move |_| fix)}>
+                                        { fix.to_string() }
+                                    </button>
+                                }) }
+                            </div>
+                        </div>
+                    }
+                    <table class="table is-fullwidth">
+                        <thead>
+                            <tr>
+                                <th>{"ID"}</th>
+                                <th>{"Title and link"}</th>
+                                <th>{"Start"}</th>
+                                <th>{"End"}</th>
+                                <th>{"Picture"}</th>
+                                <th></th>
+                            </tr>
+                        </thead>
+                        <tbody>
+                            { for chapters.iter().enumerate().map(|(i, c)| {
+                                let element_id = c.element_id.clone();
+                                // Element IDs should be unique, but rows need unique keys even when they aren't.
+                                let repeats = chapters[..i].iter().filter(|o| o.element_id == element_id).count();
+                                let row_key = match repeats {
+                                    0 => element_id.clone(),
+                                    n => format!("{} ({})", element_id, n + 1),
+                                };
+                                let row_problems: Vec<String> = problems
+                                    .iter()
+                                    .filter(|p| p.chapter_id() == Some(element_id.as_str()))
+                                    .map(Problem::to_string)
+                                    .collect();
+                                let edited = changed.contains(&format!("CHAP:{}", element_id));
+                                html! {
+                                    <ChapterRow
+                                        key={row_key}
+                                        chapter={c.clone()}
+                                        problems={row_problems}
+                                        edited={edited}
+                                        download_name={download_name.clone()}
+                                        on_change={on_change.reform(move |chapter| (i, chapter))}
+                                        on_nudge={on_nudge.reform(move |delta_ms| (i, delta_ms))}
+                                        on_remove={on_remove.reform(move |_| i)}
+                                        on_seek={on_seek.clone()}
+                                        on_error={on_error.clone()}
+                                    />
+                                }
+                            }) }
+                        </tbody>
+                    </table>
+                    <div class="field is-grouped">
+                        <div class="control is-expanded">
+                            <input class="input is-small" type="text" placeholder="Title"
+                                value={(*new_title).clone()} oninput={on_new_title} />
+                        </div>
+                        <div class="control">
+                            <button class="button is-small" onclick={on_add_click}>{"Add chapter"}</button>
+                        </div>
+                    </div>
+                    <p class="help">{"New chapters start where the last one ends and run to the end of the file."}</p>
+                    <div class="field is-grouped mt-3">
+                        <div class="control">
+                            <div class="file is-small">
+                                <label class="file-label">
+                                    <input class="file-input" type="file" accept=".json,.xml,.psc,application/json,application/xml"
+                                        onchange={on_import_file} />
+                                    <span class="file-cta"><span class="file-label">{"Import chapters"}</span></span>
+                                </label>
+                            </div>
+                        </div>
+                        <div class="control">
+                            <button class="button is-small" onclick={on_export_json.clone()} disabled={chapters.is_empty()}>
+                                {"Export Podcasting 2.0 JSON"}
+                            </button>
+                        </div>
+                        <div class="control">
+                            <button class="button is-small" onclick={on_export_podlove.clone()} disabled={chapters.is_empty()}>
+                                {"Export Podlove XML"}
+                            </button>
+                        </div>
+                    </div>
+                    <p class="help">{"Importing replaces the chapters and tables of contents with those in a Podcasting 2.0 JSON or Podlove Simple Chapters file."}</p>
+                </div>
+            </div>
+        </div>
+    }
+}
+
+#[derive(Properties, PartialEq)]
+struct ChapterRowProps {
+    chapter: Chapter,
+    /// What is wrong with this chapter.
+    problems: Vec<String>,
+    edited: bool,
+    download_name: String,
+    on_change: Callback<Chapter>,
+    on_nudge: Callback<i64>,
+    on_remove: Callback<MouseEvent>,
+    on_seek: Callback<f64>,
+    on_error: Callback<AppError>,
+}
+
+#[function_component(ChapterRow)]
+fn chapter_row(
+    ChapterRowProps {
+        chapter,
+        problems,
+        edited,
+        download_name,
+        on_change,
+        on_nudge,
+        on_remove,
+        on_seek,
+        on_error,
+    }: &ChapterRowProps,
+) -> Html {
+    // Times that could not be read, kept so they can be corrected.
+    let bad_start = use_state(|| None::<String>);
+    let bad_end = use_state(|| None::<String>);
+    let reader = use_mut_ref(|| None::<FileReader>);
+
+    let on_time = |bad: &UseStateHandle<Option<String>>, set: fn(&mut Chapter, u32)| {
+        let bad = bad.clone();
+        let chapter = chapter.clone();
+        let on_change = on_change.clone();
+        Callback::from(move |e: Event| {
+            let input: HtmlInputElement = e.target_unchecked_into();
+            match parse_time(&input.value()) {
+                Some(ms) => {
+                    bad.set(None);
+                    let mut edited = chapter.clone();
+                    set(&mut edited, ms);
+                    on_change.emit(edited);
+                }
+                None => bad.set(Some(input.value())),
+            }
+        })
+    };
+    let on_start = on_time(&bad_start, |c, ms| c.start_time = ms);
+    let on_end = on_time(&bad_end, |c, ms| c.end_time = ms);
+    let on_title = edit(chapter, on_change, |c, e: Event| {
+        let input: HtmlInputElement = e.target_unchecked_into();
+        chapters::set_chapter_title(c, &input.value());
+    });
+    let on_url = edit(chapter, on_change, |c, e: Event| {
+        let input: HtmlInputElement = e.target_unchecked_into();
+        chapters::set_chapter_url(c, input.value().trim());
+    });
+    let picture = chapters::chapter_picture(chapter).cloned();
+    let on_upload = {
+        let picture = picture.clone();
+        let set = edit(chapter, on_change, move |c, (mime_type, data)| {
+            let (picture_type, description) = match &picture {
+                Some(p) => (p.picture_type, p.description.clone()),
+                None => (PictureType::Other, String::new()),
+            };
+            chapters::set_chapter_picture(
+                c,
+                Some(Picture {
+                    mime_type,
+                    picture_type,
+                    description,
+                    data,
+                }),
+            );
+        });
+        read_image(reader, set, on_error)
+    };
+    let on_remove_picture = edit(chapter, on_change, |c, _: MouseEvent| {
+        chapters::set_chapter_picture(c, None);
+    });
+    let on_download = {
+        let picture = picture.clone();
+        let download_name = download_name.clone();
+        let prefix = artwork::chapter_prefix(chapter);
+        let on_error = on_error.clone();
+        Callback::from(move |_: MouseEvent| {
+            if let Some(picture) = &picture {
+                if let Err(err) = download_picture(picture, &download_name, &prefix) {
+                    on_error.emit(err);
+                }
+            }
+        })
+    };
+    let start_time = chapter.start_time;
+    // Moves the start and plays from there, so the new position can be heard.
+    let nudge = |delta_ms: i64| {
+        let end_time = chapter.end_time;
+        let on_nudge = on_nudge.clone();
+        let on_seek = on_seek.clone();
+        Callback::from(move |_: MouseEvent| {
+            let moved = (i64::from(start_time) + delta_ms).clamp(0, i64::from(end_time));
+            on_nudge.emit(delta_ms);
+            on_seek.emit(moved as f64 / 1000.0);
+        })
+    };
+    let url = chapters::chapter_url(chapter)
+        .unwrap_or_default()
+        .to_string();
+    let problem = links::problem(&url);
+
+    html! {
+        <tr class={classes!(if !problems.is_empty() {
+            Some("has-background-danger-light")
+        } else {
+            edited.then_some("has-background-warning-light")
+        })}>
+            <td>
+                { &chapter.element_id }
+                { for problems.iter().map(|p| html! { <p class="help is-danger">{ p }</p> }) }
+            </td>
+            <td>
+                <input class="input is-small" type="text" placeholder="Title"
+                    value={chapters::chapter_title(chapter).unwrap_or_default().to_string()}
+                    onchange={on_title} />
+                <input class={classes!("input", "is-small", problem.map(|_| "is-danger"))}
+                    type="url" placeholder="https://" value={url} onchange={on_url} />
+                if let Some(problem) = problem {
+                    <p class="help is-danger">{ problem }</p>
+                }
+            </td>
+            <td>
+                <TimeInput value={chapter.start_time} bad={(*bad_start).clone()} onchange={on_start} />
+                <div class="buttons are-small has-addons mt-1">
+                    <button class="button" title="Start 100 ms earlier" onclick={nudge(-NUDGE_MS)}>{"−100 ms"}</button>
+                    <button class="button" title="Start 100 ms later" onclick={nudge(NUDGE_MS)}>{"+100 ms"}</button>
+                </div>
+            </td>
+            <td>
+                <TimeInput value={chapter.end_time} bad={(*bad_end).clone()} onchange={on_end} />
+            </td>
+            <td>
+                if let Some(picture) = &picture {
+                    <figure class="image is-64x64">
+                        <img src={picture_src(picture)} />
+                    </figure>
+                }
+                <div class="buttons are-small">
+                    <div class="file is-small">
+                        <label class="file-label">
+                            <input class="file-input" type="file" accept="image/*" onchange={on_upload} />
+                            <span class="file-cta">
+                                <span class="file-label">{ if picture.is_some() { "Replace" } else { "Upload" } }</span>
+                            </span>
+                        </label>
+                    </div>
+                    if picture.as_ref().is_some_and(|p| !art::is_linked(p)) {
+                        <button class="button" onclick={on_download}>{"Download"}</button>
+                    }
+                    if picture.is_some() {
+                        <button class="button" onclick={on_remove_picture}>{"Remove"}</button>
+                    }
+                </div>
+            </td>
+            <td>
+                <button class="button is-small is-info" title="Play from here"
+                    onclick={on_seek.reform(move |_| start_time as f64 / 1000.0)}>{">"}</button>
+                <button class="delete" title="Remove chapter" onclick={on_remove.clone()}></button>
+            </td>
+        </tr>
+    }
+}
+
+#[derive(Properties, PartialEq)]
+struct TimeInputProps {
+    /// The time in milliseconds.
+    value: u32,
+    /// Text that could not be read as a time, shown instead of `value`.
+    bad: Option<String>,
+    onchange: Callback<Event>,
+}
+
+/// A time field that shows `HH:MM:SS.mmm` and takes any time [`parse_time`] reads.
+#[function_component(TimeInput)]
+fn time_input(
+    TimeInputProps {
+        value,
+        bad,
+        onchange,
+    }: &TimeInputProps,
+) -> Html {
+    html! {
+        <>
+            <input class={classes!("input", "is-small", bad.is_some().then_some("is-danger"))}
+                type="text" size="12"
+                value={bad.clone().unwrap_or_else(|| format_time(*value))}
+                onchange={onchange.clone()} />
+            if bad.is_some() {
+                <p class="help is-danger">{"Use HH:MM:SS.mmm or milliseconds"}</p>
+            }
+        </>
+    }
+}
+
+/// A handler that applies `f` to a copy of `chapter` and passes it on.
+fn edit<E: 'static>(
+    chapter: &Chapter,
+    on_change: &Callback<Chapter>,
+    f: impl Fn(&mut Chapter, E) + 'static,
+) -> Callback<E> {
+    let chapter = chapter.clone();
+    on_change.reform(move |e| {
+        let mut edited = chapter.clone();
+        f(&mut edited, e);
+        edited
+    })
+}
@@ -0,0 +1,204 @@
+use id3::frame::{Comment, ExtendedLink, ExtendedText, Lyrics, Picture, Popularimeter, Unknown};
+use id3::{Content, Frame, Version};
+use rid3_core::{art, frame, values};
+use web_sys::wasm_bindgen::JsCast;
+use web_sys::{HtmlInputElement, HtmlSelectElement, HtmlTextAreaElement};
+use yew::prelude::*;
+
+use super::value_list::ValueList;
+use crate::download::picture_src;
+
+#[derive(Properties, PartialEq)]
+pub struct FrameEditorProps {
+    pub frame: Frame,
+    /// The version the tag will be saved as.
+    pub version: Version,
+    /// Receives the frame's new content, always of the same type as before.
+    pub on_change: Callback<Content>,
+}
+
+/// Edits a frame through fields that match its content type. Content the
+/// editor does not understand is shown read-only, so it is never rewritten.
+#[function_component(FrameEditor)]
+pub fn frame_editor(
+    FrameEditorProps {
+        frame,
+        version,
+        on_change,
+    }: &FrameEditorProps,
+) -> Html {
+    match frame.content() {
+        Content::Text(text)
+            if values::LIST_FRAMES.contains(&frame.id()) || text.contains(values::SEPARATOR) =>
+        {
+            html! {
+                <ValueList
+                    values={values::split(text)}
+                    version={*version}
+                    on_change={on_change.reform(|v: Vec<String>| Content::Text(values::join(&v)))}
+                />
+            }
+        }
+        Content::Text(text) => html! {
+            <input class="input is-small" type="text" value={text.clone()}
+                onchange={field(on_change, text, Content::Text, |t, v| *t = v)} />
+        },
+        Content::Link(link) => html! {
+            <input class="input is-small" type="url" value={link.clone()}
+                onchange={field(on_change, link, Content::Link, |l, v| *l = v)} />
+        },
+        Content::Comment(comment) => html! {
+            <>
+                <div class="field has-addons">
+                    <div class="control">
+                        <input class="input is-small" type="text" size="3" maxlength="3" title="Language"
+                            value={comment.lang.clone()}
+                            onchange={field(on_change, comment, Content::Comment, |c: &mut Comment, v| c.lang = v)} />
+                    </div>
+                    <div class="control is-expanded">
+                        <input class="input is-small" type="text" placeholder="Description"
+                            value={comment.description.clone()}
+                            onchange={field(on_change, comment, Content::Comment, |c: &mut Comment, v| c.description = v)} />
+                    </div>
+                </div>
+                <input class="input is-small" type="text" value={comment.text.clone()}
+                    onchange={field(on_change, comment, Content::Comment, |c: &mut Comment, v| c.text = v)} />
+            </>
+        },
+        Content::Lyrics(lyrics) => html! {
+            <>
+                <div class="field has-addons">
+                    <div class="control">
+                        <input class="input is-small" type="text" size="3" maxlength="3" title="Language"
+                            value={lyrics.lang.clone()}
+                            onchange={field(on_change, lyrics, Content::Lyrics, |l: &mut Lyrics, v| l.lang = v)} />
+                    </div>
+                    <div class="control is-expanded">
+                        <input class="input is-small" type="text" placeholder="Description"
+                            value={lyrics.description.clone()}
+                            onchange={field(on_change, lyrics, Content::Lyrics, |l: &mut Lyrics, v| l.description = v)} />
+                    </div>
+                </div>
+                <textarea class="textarea is-small" rows="4" value={lyrics.text.clone()}
+                    onchange={field(on_change, lyrics, Content::Lyrics, |l: &mut Lyrics, v| l.text = v)} />
+            </>
+        },
+        Content::ExtendedText(extended) => html! {
+            <>
+                <input class="input is-small" type="text" placeholder="Description"
+                    value={extended.description.clone()}
+                    onchange={field(on_change, extended, Content::ExtendedText, |e: &mut ExtendedText, v| e.description = v)} />
+                <input class="input is-small" type="text" value={extended.value.clone()}
+                    onchange={field(on_change, extended, Content::ExtendedText, |e: &mut ExtendedText, v| e.value = v)} />
+            </>
+        },
+        Content::ExtendedLink(extended) => html! {
+            <>
+                <input class="input is-small" type="text" placeholder="Description"
+                    value={extended.description.clone()}
+                    onchange={field(on_change, extended, Content::ExtendedLink, |e: &mut ExtendedLink, v| e.description = v)} />
+                <input class="input is-small" type="url" value={extended.link.clone()}
+                    onchange={field(on_change, extended, Content::ExtendedLink, |e: &mut ExtendedLink, v| e.link = v)} />
+            </>
+        },
+        Content::Popularimeter(popm) => html! {
+            <div class="field has-addons">
+                <div class="control is-expanded">
+                    <input class="input is-small" type="email" placeholder="E-mail"
+                        value={popm.user.clone()}
+                        onchange={field(on_change, popm, Content::Popularimeter, |p: &mut Popularimeter, v| p.user = v)} />
+                </div>
+                <div class="control">
+                    <input class="input is-small" type="number" min="0" max="255" title="Rating"
+                        value={popm.rating.to_string()}
+                        onchange={field(on_change, popm, Content::Popularimeter, |p: &mut Popularimeter, v| {
+                            if let Ok(rating) = v.parse() {
+                                p.rating = rating;
+                            }
+                        })} />
+                </div>
+                <div class="control">
+                    <input class="input is-small" type="number" min="0" title="Play count"
+                        value={popm.counter.to_string()}
+                        onchange={field(on_change, popm, Content::Popularimeter, |p: &mut Popularimeter, v| {
+                            if let Ok(counter) = v.parse() {
+                                p.counter = counter;
+                            }
+                        })} />
+                </div>
+            </div>
+        },
+        Content::Picture(picture) => html! {
+            <div class="media">
+                <figure class="media-left image is-64x64">
+                    <img src={picture_src(picture)} />
+                </figure>
+                <div class="media-content">
+                    <div class="select is-small">
+                        <select onchange={field(on_change, picture, Content::Picture, |p: &mut Picture, v| {
+                            if let Some(t) = v.parse::<usize>().ok().and_then(|i| art::PICTURE_TYPES.get(i)) {
+                                p.picture_type = *t;
+                            }
+                        })}>
+                            { for art::PICTURE_TYPES.iter().enumerate().map(|(i, t)| html! {
+                                <option value={i.to_string()} selected={*t == picture.picture_type}>{ t.to_string() }</option>
+                            }) }
+                        </select>
+                    </div>
+                    <input class="input is-small" type="text" placeholder="Description"
+                        value={picture.description.clone()}
+                        onchange={field(on_change, picture, Content::Picture, |p: &mut Picture, v| p.description = v)} />
+                    <input class="input is-small" type="text" title="MIME type"
+                        value={picture.mime_type.clone()}
+                        onchange={field(on_change, picture, Content::Picture, |p: &mut Picture, v| p.mime_type = v)} />
+                </div>
+            </div>
+        },
+        Content::Unknown(unknown) => html! {
+            <>
+                <textarea class="textarea is-small is-family-monospace" rows="2"
+                    value={frame::to_hex(&unknown.data)}
+                    onchange={field(on_change, unknown, Content::Unknown, |u: &mut Unknown, v| {
+                        if let Some(data) = frame::parse_hex(&v) {
+                            u.data = data;
+                        }
+                    })} />
+                <p class="help">{ format!("{} bytes, raw hex", unknown.data.len()) }</p>
+            </>
+        },
+        _ => html! {
+            <span class="has-text-grey" title="This frame type can't be edited here">
+                { frame::display_value(frame) }
+            </span>
+        },
+    }
+}
+
+/// A change handler for one field of `value`: `set` writes the entered text
+/// into a copy, which `wrap` turns back into frame content.
+fn field<T: Clone + 'static>(
+    on_change: &Callback<Content>,
+    value: &T,
+    wrap: fn(T) -> Content,
+    set: fn(&mut T, String),
+) -> Callback<Event> {
+    let value = value.clone();
+    on_change.reform(move |e: Event| {
+        let mut edited = value.clone();
+        set(&mut edited, event_value(&e));
+        wrap(edited)
+    })
+}
+
+fn event_value(e: &Event) -> String {
+    let Some(target) = e.target() else {
+        return String::new();
+    };
+    if let Some(textarea) = target.dyn_ref::<HtmlTextAreaElement>() {
+        textarea.value()
+    } else if let Some(select) = target.dyn_ref::<HtmlSelectElement>() {
+        select.value()
+    } else {
+        target.unchecked_into::<HtmlInputElement>().value()
+    }
+}
@@ -0,0 +1,45 @@
+use yew::prelude::*;
+
+#[derive(Properties, PartialEq)]
+pub struct HistoryPanelProps {
+    /// Edits that can be undone, oldest first.
+    pub undo: Vec<String>,
+    /// Edits that can be redone, next redo first.
+    pub redo: Vec<String>,
+    pub on_undo: Callback<MouseEvent>,
+    pub on_redo: Callback<MouseEvent>,
+}
+
+#[function_component(HistoryPanel)]
+pub fn history_panel(
+    HistoryPanelProps {
+        undo,
+        redo,
+        on_undo,
+        on_redo,
+    }: &HistoryPanelProps,
+) -> Html {
+    html! {
+        <div class="container">
+            <div class="card">
+                <header class="card-header">
+                    <p class="card-header-title">{"History"}</p>
+                </header>
+                <div class="card-content">
+                    <div class="buttons">
+                        <button class="button is-small" title="Ctrl+Z" disabled={undo.is_empty()} onclick={on_undo}>{"Undo"}</button>
+                        <button class="button is-small" title="Ctrl+Shift+Z" disabled={redo.is_empty()} onclick={on_redo}>{"Redo"}</button>
+                    </div>
+                    if undo.is_empty() && redo.is_empty() {
+                        <p class="has-text-grey">{"No edits yet."}</p>
+                    } else {
+                        <ol>
+                            { for undo.iter().map(|label| html! { <li>{ label }</li> }) }
+                            { for redo.iter().map(|label| html! { <li class="has-text-grey-light">{ label }</li> }) }
+                        </ol>
+                    }
+                </div>
+            </div>
+        </div>
+    }
+}
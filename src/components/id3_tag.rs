@@ -1,52 +1,134 @@
 use gloo::console::log;
-use id3::{frame::Chapter, Tag};
-use web_sys::Event;
+use id3::{Content, Tag, Version};
+use rid3_core::diff::FrameChange;
+use rid3_core::layout::Trailer;
+use rid3_core::{art, catalog, frame, links, version, TagSource};
+use web_sys::{Event, HtmlInputElement};
 use yew::classes;
 use yew::prelude::*;
 
-use base64::engine::general_purpose::STANDARD as BASE64;
-use base64::engine::Engine as _;
+use super::add_frame::AddFrame;
+use super::frame_editor::FrameEditor;
+use super::review_changes::ReviewChanges;
+use super::version_select::VersionSelect;
+
+use crate::download::picture_src;
 
 #[derive(Properties, PartialEq)]
 pub struct ID3TagProps {
     pub tag: Option<Tag>,
-    pub on_value_change: Callback<Event>,
+    pub source: TagSource,
+    /// Frames edited since the tag was loaded or last saved.
+    pub edits: Vec<FrameChange>,
+    /// Trailing tags kept or removed since then, with whether they are kept.
+    pub trailer_edits: Vec<(Trailer, bool)>,
+    pub version: Version,
+    pub on_version_change: Callback<Version>,
+    /// Trailing tags found in the file and whether they are kept on save.
+    pub trailers: Vec<(Trailer, bool)>,
+    pub on_keep_trailer: Callback<(Trailer, bool)>,
+    /// A `%FRAME%` template for the saved file's name; empty keeps the uploaded name.
+    pub template: String,
+    pub download_name: String,
+    pub on_template_change: Callback<String>,
+    /// Receives a frame's key and its new content.
+    pub on_frame_change: Callback<(String, Content)>,
+    /// Adds an empty frame with the given ID.
+    pub on_add_frame: Callback<String>,
+    /// Removes the frame with the given key.
+    pub on_remove_frame: Callback<String>,
     pub save_clicked: Callback<MouseEvent>,
     pub clear_clicked: Callback<MouseEvent>,
-    pub on_seek_position_change: Callback<f64>,
 }
 
 #[function_component(ID3Tag)]
 pub fn tag(
     ID3TagProps {
         tag,
-        on_value_change,
+        source,
+        edits,
+        trailer_edits,
+        version,
+        on_version_change,
+        trailers,
+        on_keep_trailer,
+        template,
+        download_name,
+        on_template_change,
+        on_frame_change,
+        on_add_frame,
+        on_remove_frame,
         save_clicked,
         clear_clicked,
-        on_seek_position_change,
     }: &ID3TagProps,
 ) -> Html {
-    let mut chaps = Vec::new();
+    let present: Vec<String> = tag
+        .iter()
+        .flat_map(|t| t.frames().map(frame::key))
+        .collect();
     let mut frames = Vec::new();
     let mut pic = String::new();
+    let changes = tag
+        .as_ref()
+        .map(|t| version::report(t, *version))
+        .unwrap_or_default();
+
+    let on_template_input = on_template_change.reform(|e: InputEvent| {
+        let input: HtmlInputElement = e.target_unchecked_into();
+        input.value()
+    });
+
+    let changed: Vec<String> = edits.iter().map(FrameChange::key).collect();
+
+    // Saving shows the edits and conversions for review first.
+    let confirming = use_state(|| false);
+    let on_review = {
+        let confirming = confirming.clone();
+        Callback::from(move |_: MouseEvent| confirming.set(true))
+    };
+    let on_save = {
+        let confirming = confirming.clone();
+        let save_clicked = save_clicked.clone();
+        let needs_confirmation = !changes.is_empty() || !edits.is_empty();
+        Callback::from(move |e: MouseEvent| {
+            if needs_confirmation {
+                confirming.set(true);
+            } else {
+                save_clicked.emit(e);
+            }
+        })
+    };
+    let on_confirm = {
+        let confirming = confirming.clone();
+        let save_clicked = save_clicked.clone();
+        Callback::from(move |e: MouseEvent| {
+            confirming.set(false);
+            save_clicked.emit(e);
+        })
+    };
+    let on_cancel = {
+        let confirming = confirming.clone();
+        Callback::from(move |_: MouseEvent| confirming.set(false))
+    };
     if let Some(tag) = tag {
         for f in tag.frames() {
             log!(format!("{:?}", f.id()));
-            if f.id() == "APIC" {
-                if let Some(p) = f.content().picture() {
-                    log!(format!("{:?}", p.mime_type));
-                    pic = BASE64.encode(&p.data);
-                }
-            } else if f.id() != "CHAP" {
+            if f.id() != "CHAP" && f.id() != "APIC" {
                 log!(format!("xxx {:?}", f));
             }
         }
+        if let Some(p) = art::cover(tag) {
+            log!(format!("{:?}", p.mime_type));
+            pic = picture_src(p).unwrap_or_default();
+        }
         frames = tag
             .frames()
+            .filter(|f| {
+                !matches!(f.id(), "APIC" | "CHAP" | "CTOC" | "TXXX" | "USLT" | "SYLT")
+                    && !links::is_url_frame(f.id())
+            })
             .cloned()
-            .filter(|f| f.id() != "CHAP" && f.id() != "APIC")
             .collect();
-        chaps = tag.chapters().cloned().collect();
     }
 
     html! {
@@ -57,6 +139,15 @@ pub fn tag(
                 </div>
 
                 <div class="card-content">
+                    if !matches!(source, TagSource::Id3v2(_)) {
+                        <div class="notification is-info is-light">
+                            if *source == TagSource::Id3v1 {
+                                {"This file has no ID3v2 tag. A new tag was started from its ID3v1 fields."}
+                            } else {
+                                {"This file has no ID3 tag. A new, empty tag was started."}
+                            }
+                        </div>
+                    }
                     <div class="columns">
                         <div class="column">
                             <ChapterArt pic={pic.clone()}/>
@@ -67,27 +158,66 @@ pub fn tag(
                                     <tr>
                                         <th>{"ID3 Tag"}</th>
                                         <th>{"value"}</th>
+                                        <th></th>
                                     </tr>
                                 </thead>
-                                <Frames frames={frames} on_value_change={on_value_change}/>
+                                <Frames frames={frames} version={*version} changed={changed.clone()} on_change={on_frame_change} on_remove={on_remove_frame}/>
                             </table>
+                            <AddFrame version={*version} present={present} on_add={on_add_frame} />
                         </div>
                         <div class="column">
-                            <table class="table">
-                                <thead>
-                                    <tr>
-                                        <th>{"Chapters"}</th>
-                                        <th>{"Name"}</th>
-                                        <th>{"Times"}</th>
-                                        <th>{"Art"}</th>
-                                        <th>{"Controls"}</th>
-                                    </tr>
-                                </thead>
-                                <Chapters chapters={chaps} on_seek_position_change={on_seek_position_change}/>
-                            </table>
-                            <button class="button is-info" onclick={save_clicked}>{"Save"}</button>
+                            <div class="field">
+                                <label class="label">{"File name"}</label>
+                                <div class="control">
+                                    <input
+                                        class="input is-small"
+                                        type="text"
+                                        placeholder="%TPE1% - %TRCK% - %TIT2%.mp3"
+                                        value={template.clone()}
+                                        oninput={on_template_input}
+                                    />
+                                </div>
+                                <p class="help">{ download_name.clone() }</p>
+                            </div>
+                            <div class="field is-grouped">
+                                <label class="label mr-2">{"Save as"}</label>
+                                <VersionSelect version={*version} on_change={on_version_change} />
+                            </div>
+                            { for trailers.iter().map(|(trailer, keep)| {
+                                let trailer = *trailer;
+                                let onchange = on_keep_trailer.reform(move |e: Event| {
+                                    let input: HtmlInputElement = e.target_unchecked_into();
+                                    (trailer, input.checked())
+                                });
+                                html! {
+                                    <label class="checkbox mr-3">
+                                        <input type="checkbox" checked={*keep} {onchange} />
+                                        { format!(" Keep {}", trailer) }
+                                    </label>
+                                }
+                            }) }
+                            if !changes.is_empty() {
+                                <p class="help is-warning">
+                                    { format!("{} frame change(s) needed for {}", changes.len(), version) }
+                                </p>
+                            }
+                            if !edits.is_empty() || !trailer_edits.is_empty() {
+                                <p class="help">{ format!("{} unsaved change(s)", edits.len() + trailer_edits.len()) }</p>
+                            }
+                            <button class="button is-info" onclick={on_save}>{"Save"}</button>
+                            <button class="button" onclick={on_review} disabled={edits.is_empty() && trailer_edits.is_empty() && changes.is_empty()}>{"Review changes"}</button>
                             <button class="button" onclick={clear_clicked}>{" Clear "}</button>
                             //<button class="is-info" onclick={save_clicked}>{"Save"}</button>
+                            if *confirming {
+                                <ReviewChanges
+                                    edits={edits.clone()}
+                                    trailers={trailer_edits.clone()}
+                                    conversion={changes.clone()}
+                                    version={*version}
+                                    on_confirm={on_confirm}
+                                    on_cancel={on_cancel}
+                                />
+                            }
                         </div>
                     </div>
                 </div>
@@ -99,33 +229,38 @@ pub fn tag(
 #[derive(Properties, PartialEq)]
 struct FramesProps {
     frames: Vec<id3::frame::Frame>,
-    on_value_change: Callback<Event>,
+    version: Version,
+    /// Keys of the frames edited since loading, see [`frame::key`].
+    changed: Vec<String>,
+    on_change: Callback<(String, Content)>,
+    on_remove: Callback<String>,
 }
 
 #[function_component(Frames)]
 fn tags(
     FramesProps {
         frames,
-        on_value_change,
+        version,
+        changed,
+        on_change,
+        on_remove,
     }: &FramesProps,
 ) -> Html {
     frames.iter().map(|f| {
         let name = String::from(f.id());
-        let value: String;
-        if name == "USLT" {
-            value = f.content().lyrics().unwrap().text.to_string();
-        } else if name == "COMM" {
-            value = f.content().comment().unwrap().text.to_string();
-        } else if name == "CTOC" {
-            value = f.content().table_of_contents().unwrap().elements.join(", ")
-        } else {
-            value = String::from(f.content().text().unwrap_or(""));
-        }
+        let key = frame::key(f);
+        let on_frame_change = {
+            let key = key.clone();
+            on_change.reform(move |content| (key.clone(), content))
+        };
+        let edited = changed.contains(&key);
+        let description = catalog::describe(f.id()).unwrap_or_default();
 
         html! {
-            <tr>
-                <td><span>{ name.clone() }</span></td>
-                <td><input type="text" name={ name } value={ value } onchange={on_value_change}/></td>
+            <tr class={classes!(edited.then_some("has-background-warning-light"))}>
+                <td><span title={description}>{ name }</span></td>
+                <td><FrameEditor frame={f.clone()} version={*version} on_change={on_frame_change} /></td>
+                <td><button class="delete" title="Remove frame" onclick={on_remove.reform(move |_| key.clone())}></button></td>
             </tr>
         }
      }).collect()
@@ -134,7 +269,7 @@ fn tags(
 // ChapterArtProps
 #[derive(Properties, PartialEq)]
 struct ChapterArtProps {
-    pic: String, // base64 encoded image
+    pic: String, // image source, see `picture_src`
 }
 
 #[function_component(ChapterArt)]
@@ -159,12 +294,12 @@ fn chapter_art(ChapterArtProps { pic }: &ChapterArtProps) -> Html {
     } else {
         html! {
             <>
-                <img src={format!("data:image/png;base64,{}", pic.clone())} width="200" onclick={toggle_modal.clone()} />
+                <img src={pic.clone()} width="200" onclick={toggle_modal.clone()} />
                 <div class={classes!((*modal_classes).clone())}>
                 <div class="modal-background" onclick={toggle_modal.clone()}></div>
                   <div class="modal-content">
                     <p class="image">
-                      <img src={format!("data:image/png;base64,{}", pic.clone())} />
+                      <img src={pic.clone()} />
                     </p>
                   </div>
                   <button class="modal-close is-large" aria-label="close" onclick={toggle_modal}></button>
@@ -173,67 +308,3 @@ fn chapter_art(ChapterArtProps { pic }: &ChapterArtProps) -> Html {
         }
     }
 }
-
-#[derive(Properties, PartialEq)]
-struct ChaptersProps {
-    chapters: Vec<Chapter>,
-    pub on_seek_position_change: Callback<f64>,
-}
-
-#[function_component(Chapters)]
-fn chapters(
-    ChaptersProps {
-        chapters,
-        on_seek_position_change,
-    }: &ChaptersProps,
-) -> Html {
-    let mut c = Vec::new();
-    for chapter in chapters {
-        let id = chapter.element_id.clone();
-        let start_time = chapter.start_time;
-        let end_time = chapter.end_time;
-        let mut name = "";
-        let mut link: Option<String> = None;
-        let mut pic: Option<String> = None;
-        chapter.frames.iter().for_each(|f| match f.id() {
-            "TIT2" => {
-                name = f.content().text().unwrap();
-            }
-            "APIC" => {
-                if let Some(p) = f.content().picture() {
-                    log!(format!("APIC.len == {:?}", p.data.len()));
-                    pic = Some(BASE64.encode(&p.data));
-                }
-            }
-            "WXXX" => {
-                link = Some(f.content().extended_link().unwrap().link.to_string());
-            }
-            _ => {}
-        });
-
-        c.push(html! {
-            <tr>
-                <td>{ id }</td>
-                <td>
-                    if let Some(link) = link.clone() {
-                        <a href={link}>{name}</a>
-                    } else {
-                        { name }
-                    }
-                </td>
-                <td>{ start_time/1000 } {"-"} { end_time/1000 }</td>
-                <td>
-                    if pic.is_some() {
-                        <ChapterArt pic={pic.clone().unwrap()}/>
-                    }
-                </td>
-                <td>
-                    <button class="button is-info" onclick={on_seek_position_change.reform(move |_| (start_time/1000) as f64)}>{">"}</button>
-                </td>
-            </tr>
-        });
-    }
-    html! {
-        { for c }
-    }
-}
@@ -0,0 +1,150 @@
+use id3::frame::Picture;
+use rid3_core::art::{self, ImageFormat};
+use rid3_core::artwork::{self, Rework, Reworked};
+use web_sys::{HtmlInputElement, HtmlSelectElement};
+use yew::prelude::*;
+
+use crate::download::picture_src;
+use crate::error::AppError;
+
+#[derive(Properties, PartialEq)]
+pub struct ImagePanelProps {
+    pub picture: Picture,
+    /// Receives the picture with the reworked image in place of the old one.
+    pub on_replace: Callback<Picture>,
+    pub on_error: Callback<AppError>,
+}
+
+/// Crops, scales down and recompresses a picture as a JPEG. The result is
+/// previewed with its size before it replaces the picture.
+#[function_component(ImagePanel)]
+pub fn image_panel(
+    ImagePanelProps {
+        picture,
+        on_replace,
+        on_error,
+    }: &ImagePanelProps,
+) -> Html {
+    let options = use_state(Rework::default);
+    let preview = use_state(|| None::<Reworked>);
+
+    let dimensions = artwork::dimensions(&picture.data).ok();
+    let set_option = |set: fn(&mut Rework, &HtmlInputElement)| {
+        let options = options.clone();
+        let preview = preview.clone();
+        Callback::from(move |e: Event| {
+            let input: HtmlInputElement = e.target_unchecked_into();
+            let mut changed = *options;
+            set(&mut changed, &input);
+            options.set(changed);
+            preview.set(None);
+        })
+    };
+    let on_crop = set_option(|o, input| o.crop_square = input.checked());
+    let on_max_edge = {
+        let options = options.clone();
+        let preview = preview.clone();
+        Callback::from(move |e: Event| {
+            let select: HtmlSelectElement = e.target_unchecked_into();
+            options.set(Rework {
+                max_edge: select.value().parse().ok(),
+                ..*options
+            });
+            preview.set(None);
+        })
+    };
+    let on_quality = set_option(|o, input| {
+        if let Ok(quality) = input.value().parse() {
+            o.quality = quality;
+        }
+    });
+    let on_preview = {
+        let data = picture.data.clone();
+        let options = options.clone();
+        let preview = preview.clone();
+        let on_error = on_error.clone();
+        Callback::from(
+            move |_: MouseEvent| match artwork::rework(&data, &options) {
+                Ok(reworked) => preview.set(Some(reworked)),
+                Err(err) => on_error.emit(err.into()),
+            },
+        )
+    };
+    let format = art::format(picture).map_or("Unknown format".to_string(), |f| f.to_string());
+    let reworked_picture = preview.as_ref().map(|reworked| Picture {
+        mime_type: ImageFormat::Jpeg.mime_type().to_string(),
+        data: reworked.data.clone(),
+        ..picture.clone()
+    });
+    let on_apply = {
+        let reworked_picture = reworked_picture.clone();
+        let preview = preview.clone();
+        let on_replace = on_replace.clone();
+        Callback::from(move |_: MouseEvent| {
+            if let Some(picture) = &reworked_picture {
+                on_replace.emit(picture.clone());
+                preview.set(None);
+            }
+        })
+    };
+
+    html! {
+        <div class="box">
+            <p>
+                { match dimensions {
+                    Some(d) => format!("{} · {} · {}", d, format, artwork::format_size(picture.data.len())),
+                    None => format!("{} · {}", format, artwork::format_size(picture.data.len())),
+                } }
+                if dimensions.is_some_and(|d| !d.is_square()) {
+                    <span class="tag is-warning ml-2">{"Not square"}</span>
+                }
+            </p>
+            <div class="field is-grouped is-grouped-multiline mt-2">
+                <div class="control">
+                    <label class="checkbox">
+                        <input type="checkbox" checked={options.crop_square} onchange={on_crop} />
+                        {" Crop to square"}
+                    </label>
+                </div>
+                <div class="control">
+                    <div class="select is-small">
+                        <select title="Longest edge" onchange={on_max_edge}>
+                            <option value="" selected={options.max_edge.is_none()}>{"Keep size"}</option>
+                            { for artwork::MAX_EDGES.iter().map(|edge| html! {
+                                <option value={edge.to_string()} selected={options.max_edge == Some(*edge)}>
+                                    { format!("At most {} px", edge) }
+                                </option>
+                            }) }
+                        </select>
+                    </div>
+                </div>
+                <div class="control">
+                    <label class="label is-small">{ format!("JPEG quality {}", options.quality) }</label>
+                    <input type="range" min="1" max="100" value={options.quality.to_string()} onchange={on_quality} />
+                </div>
+                <div class="control">
+                    <button class="button is-small" onclick={on_preview}>{"Preview"}</button>
+                </div>
+            </div>
+            if let Some(dimensions) = dimensions {
+                <p class="help">{ format!("Will be {} as a JPEG", dimensions.reworked(&options)) }</p>
+            }
+            if let (Some(reworked), Some(reworked_picture)) = (&*preview, &reworked_picture) {
+                <div class="media mt-2">
+                    <figure class="media-left image is-128x128">
+                        <img src={picture_src(reworked_picture)} />
+                    </figure>
+                    <div class="media-content">
+                        <p>{ format!(
+                            "{} · JPEG · {} (was {})",
+                            reworked.dimensions,
+                            artwork::format_size(reworked.data.len()),
+                            artwork::format_size(picture.data.len()),
+                        ) }</p>
+                        <button class="button is-small is-primary mt-2" onclick={on_apply}>{"Replace picture"}</button>
+                    </div>
+                </div>
+            }
+        </div>
+    }
+}
@@ -0,0 +1,28 @@
+use rid3_core::lyrics;
+use yew::prelude::*;
+
+#[derive(Properties, PartialEq)]
+pub struct LanguageSelectProps {
+    pub lang: String,
+    pub onchange: Callback<Event>,
+}
+
+/// The ISO-639-2 languages, plus `lang` itself if it isn't one of them.
+#[function_component(LanguageSelect)]
+pub fn language_select(LanguageSelectProps { lang, onchange }: &LanguageSelectProps) -> Html {
+    let known = lyrics::language_name(lang).is_some();
+    html! {
+        <div class="select is-small">
+            <select {onchange}>
+                if !known {
+                    <option value={lang.clone()} selected=true>{ lang.clone() }</option>
+                }
+                { for lyrics::LANGUAGES.iter().map(|(code, name)| html! {
+                    <option value={*code} selected={code.eq_ignore_ascii_case(lang)}>
+                        { format!("{} – {}", code, name) }
+                    </option>
+                }) }
+            </select>
+        </div>
+    }
+}
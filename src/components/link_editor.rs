@@ -0,0 +1,202 @@
+use id3::frame::{ExtendedLink, Frame};
+use id3::Content;
+use rid3_core::{catalog, frame, links};
+use web_sys::{HtmlInputElement, HtmlSelectElement};
+use yew::prelude::*;
+
+#[derive(Properties, PartialEq)]
+pub struct LinkEditorProps {
+    /// Every URL link frame in the tag, `WXXX` included.
+    pub frames: Vec<Frame>,
+    /// Keys of the frames edited since the tag was loaded or last saved.
+    pub changed: Vec<String>,
+    /// Receives a frame's key and its new `Link` or `ExtendedLink` content.
+    pub on_change: Callback<(String, Content)>,
+    /// Adds a link frame with the given ID and URL.
+    pub on_add: Callback<(String, String)>,
+    pub on_add_extended: Callback<ExtendedLink>,
+    pub on_remove: Callback<String>,
+}
+
+/// Edits the tag's URL link frames. URLs are checked as they are entered,
+/// and web addresses can be opened in a new tab.
+#[function_component(LinkEditor)]
+pub fn link_editor(
+    LinkEditorProps {
+        frames,
+        changed,
+        on_change,
+        on_add,
+        on_add_extended,
+        on_remove,
+    }: &LinkEditorProps,
+) -> Html {
+    let new_id = use_state(|| "WXXX".to_string());
+    let new_description = use_state(String::new);
+    let new_url = use_state(String::new);
+
+    let present: Vec<String> = frames.iter().map(frame::key).collect();
+    let new_key = if *new_id == "WXXX" {
+        frame::key(&Frame::from(ExtendedLink {
+            description: (*new_description).clone(),
+            link: String::new(),
+        }))
+    } else {
+        frame::key(&Frame::link(&*new_id, &*new_url))
+    };
+    let exists = present.contains(&new_key);
+    let new_problem = links::problem(&new_url);
+
+    let on_new_id = {
+        let new_id = new_id.clone();
+        Callback::from(move |e: Event| {
+            let select: HtmlSelectElement = e.target_unchecked_into();
+            new_id.set(select.value());
+        })
+    };
+    let on_new_description = {
+        let new_description = new_description.clone();
+        Callback::from(move |e: InputEvent| {
+            let input: HtmlInputElement = e.target_unchecked_into();
+            new_description.set(input.value());
+        })
+    };
+    let on_new_url = {
+        let new_url = new_url.clone();
+        Callback::from(move |e: InputEvent| {
+            let input: HtmlInputElement = e.target_unchecked_into();
+            new_url.set(input.value());
+        })
+    };
+    let on_add_click = {
+        let on_add = on_add.clone();
+        let on_add_extended = on_add_extended.clone();
+        let new_id = new_id.clone();
+        let new_description = new_description.clone();
+        let new_url = new_url.clone();
+        Callback::from(move |_: MouseEvent| {
+            if *new_id == "WXXX" {
+                on_add_extended.emit(ExtendedLink {
+                    description: (*new_description).clone(),
+                    link: (*new_url).clone(),
+                });
+            } else {
+                on_add.emit(((*new_id).clone(), (*new_url).clone()));
+            }
+            new_description.set(String::new());
+            new_url.set(String::new());
+        })
+    };
+
+    html! {
+        <div class="container">
+            <div class="card">
+                <header class="card-header">
+                    <p class="card-header-title">{"Links"}</p>
+                </header>
+                <div class="card-content">
+                    <table class="table is-fullwidth">
+                        <tbody>
+                            { for frames.iter().zip(present.iter()).map(|(f, k)| {
+                                let reform = |wrap: fn(&Frame, String) -> Option<Content>| {
+                                    let (f, k) = (f.clone(), k.clone());
+                                    let on_change = on_change.clone();
+                                    Callback::from(move |e: Event| {
+                                        let input: HtmlInputElement = e.target_unchecked_into();
+                                        if let Some(content) = wrap(&f, input.value()) {
+                                            on_change.emit((k.clone(), content));
+                                        }
+                                    })
+                                };
+                                let (description, url) = match f.content() {
+                                    Content::ExtendedLink(extended) => {
+                                        (Some(extended.description.clone()), extended.link.clone())
+                                    }
+                                    content => (None, content.link().unwrap_or_default().to_string()),
+                                };
+                                let on_url = reform(|f, v| match f.content() {
+                                    Content::ExtendedLink(extended) => Some(Content::ExtendedLink(ExtendedLink {
+                                        link: v,
+                                        ..extended.clone()
+                                    })),
+                                    Content::Link(_) => Some(Content::Link(v)),
+                                    _ => None,
+                                });
+                                let on_description = reform(|f, v| {
+                                    f.content().extended_link().map(|extended| Content::ExtendedLink(ExtendedLink {
+                                        description: v,
+                                        ..extended.clone()
+                                    }))
+                                });
+                                let problem = links::problem(&url);
+                                html! {
+                                    <tr class={classes!(changed.contains(k).then_some("has-background-warning-light"))}>
+                                        <td>
+                                            <span title={format!("{} – {}", f.id(), catalog::describe(f.id()).unwrap_or_default())}>
+                                                { links::label(f.id()).unwrap_or(f.id()) }
+                                            </span>
+                                        </td>
+                                        <td>
+                                            if let Some(description) = description {
+                                                <input class="input is-small" type="text" placeholder="Description"
+                                                    value={description} onchange={on_description} />
+                                            }
+                                            <input class={classes!("input", "is-small", problem.map(|_| "is-danger"))}
+                                                type="url" placeholder="https://" value={url.clone()} onchange={on_url} />
+                                            if let Some(problem) = problem {
+                                                <p class="help is-danger">{ problem }</p>
+                                            }
+                                        </td>
+                                        <td>
+                                            if links::is_openable(&url) {
+                                                <a class="button is-small" href={url} target="_blank" rel="noopener noreferrer">{"Open"}</a>
+                                            }
+                                        </td>
+                                        <td>
+                                            <button class="delete" title="Remove link" onclick={on_remove.reform({
+                                                let k = k.clone();
+                                                move |_| k.clone()
+                                            })}></button>
+                                        </td>
+                                    </tr>
+                                }
+                            }) }
+                        </tbody>
+                    </table>
+                    <div class="field is-grouped">
+                        <div class="control">
+                            <div class="select is-small">
+                                <select onchange={on_new_id}>
+                                    { for links::URL_FRAMES.iter().map(|(id, label)| html! {
+                                        <option value={*id} selected={*new_id == *id}>{ *label }</option>
+                                    }) }
+                                </select>
+                            </div>
+                        </div>
+                        if *new_id == "WXXX" {
+                            <div class="control">
+                                <input class="input is-small" type="text" placeholder="Description"
+                                    value={(*new_description).clone()} oninput={on_new_description} />
+                            </div>
+                        }
+                        <div class="control is-expanded">
+                            <input class={classes!("input", "is-small", new_problem.map(|_| "is-danger"))}
+                                type="url" placeholder="https://" value={(*new_url).clone()} oninput={on_new_url} />
+                        </div>
+                        <div class="control">
+                            <button class="button is-small" disabled={exists || new_url.is_empty() || new_problem.is_some()}
+                                onclick={on_add_click}>
+                                {"Add link"}
+                            </button>
+                        </div>
+                    </div>
+                    if let Some(problem) = new_problem {
+                        <p class="help is-danger">{ problem }</p>
+                    } else if exists {
+                        <p class="help">{"The tag already has this link. Edit it above instead."}</p>
+                    }
+                </div>
+            </div>
+        </div>
+    }
+}
@@ -0,0 +1,192 @@
+use gloo_file::callbacks::FileReader;
+use gloo_file::File;
+use id3::frame::{Frame, Lyrics};
+use rid3_core::{filename, frame, lyrics};
+use web_sys::{HtmlInputElement, HtmlSelectElement, HtmlTextAreaElement};
+use yew::prelude::*;
+
+use super::language_select::LanguageSelect;
+use crate::download::download;
+use crate::error::AppError;
+
+#[derive(Properties, PartialEq)]
+pub struct LyricsEditorProps {
+    /// Every `USLT` frame in the tag.
+    pub lyrics: Vec<Lyrics>,
+    /// The saved MP3's name, which exported lyrics files are named after.
+    pub download_name: String,
+    /// Receives the key of the edited `USLT` frame and its new content.
+    pub on_change: Callback<(String, Lyrics)>,
+    pub on_add: Callback<Lyrics>,
+    pub on_remove: Callback<String>,
+    pub on_error: Callback<AppError>,
+}
+
+/// Edits the tag's unsynchronised lyrics, one `USLT` frame per language and
+/// description, with plain-text import and export.
+#[function_component(LyricsEditor)]
+pub fn lyrics_editor(
+    LyricsEditorProps {
+        lyrics,
+        download_name,
+        on_change,
+        on_add,
+        on_remove,
+        on_error,
+    }: &LyricsEditorProps,
+) -> Html {
+    let new_lang = use_state(|| "eng".to_string());
+    let new_description = use_state(String::new);
+    // Keeps an import running until the file has been read.
+    let reader = use_mut_ref(|| None::<FileReader>);
+
+    let keys: Vec<String> = lyrics.iter().map(key).collect();
+    let new_key = key(&Lyrics {
+        lang: (*new_lang).clone(),
+        description: (*new_description).clone(),
+        text: String::new(),
+    });
+    let on_new_lang = {
+        let new_lang = new_lang.clone();
+        Callback::from(move |e: Event| {
+            let select: HtmlSelectElement = e.target_unchecked_into();
+            new_lang.set(select.value());
+        })
+    };
+    let on_new_description = {
+        let new_description = new_description.clone();
+        Callback::from(move |e: InputEvent| {
+            let input: HtmlInputElement = e.target_unchecked_into();
+            new_description.set(input.value());
+        })
+    };
+    let on_add_click = {
+        let on_add = on_add.clone();
+        let new_lang = new_lang.clone();
+        let new_description = new_description.clone();
+        Callback::from(move |_: MouseEvent| {
+            on_add.emit(Lyrics {
+                lang: (*new_lang).clone(),
+                description: (*new_description).clone(),
+                text: String::new(),
+            });
+            new_description.set(String::new());
+        })
+    };
+
+    html! {
+        <div class="container">
+            <div class="card">
+                <header class="card-header">
+                    <p class="card-header-title">{"Lyrics"}</p>
+                </header>
+                <div class="card-content">
+                    { for lyrics.iter().zip(keys.iter()).map(|(l, k)| {
+                        let edit = |set: fn(&mut Lyrics, String)| {
+                            let (l, k) = (l.clone(), k.clone());
+                            on_change.reform(move |value: String| {
+                                let mut edited = l.clone();
+                                set(&mut edited, value);
+                                (k.clone(), edited)
+                            })
+                        };
+                        let on_lang = edit(|l, v| l.lang = v).reform(|e: Event| {
+                            let select: HtmlSelectElement = e.target_unchecked_into();
+                            select.value()
+                        });
+                        let on_description = edit(|l, v| l.description = v).reform(|e: Event| {
+                            let input: HtmlInputElement = e.target_unchecked_into();
+                            input.value()
+                        });
+                        let on_text = edit(|l, v| l.text = v).reform(|e: Event| {
+                            let textarea: HtmlTextAreaElement = e.target_unchecked_into();
+                            textarea.value()
+                        });
+                        let on_import = {
+                            let set_text = edit(|l, v| l.text = lyrics::from_txt(&v));
+                            let on_error = on_error.clone();
+                            let reader = reader.clone();
+                            Callback::from(move |e: Event| {
+                                let input: HtmlInputElement = e.target_unchecked_into();
+                                let Some(file) = input.files().and_then(|files| files.get(0)) else {
+                                    return;
+                                };
+                                let set_text = set_text.clone();
+                                let on_error = on_error.clone();
+                                let task = gloo_file::callbacks::read_as_text(&File::from(file), move |text| {
+                                    match text {
+                                        Ok(text) => set_text.emit(text),
+                                        Err(err) => on_error.emit(err.into()),
+                                    }
+                                });
+                                *reader.borrow_mut() = Some(task);
+                            })
+                        };
+                        let on_export = {
+                            let l = l.clone();
+                            let name = filename::sibling(download_name, &lyrics::file_suffix(&l.lang, &l.description), "txt");
+                            let on_error = on_error.clone();
+                            Callback::from(move |_: MouseEvent| {
+                                if let Err(err) = download(lyrics::to_txt(&l).as_bytes(), &name, "text/plain") {
+                                    on_error.emit(err);
+                                }
+                            })
+                        };
+                        html! {
+                            <div class="box">
+                                <div class="field is-grouped">
+                                    <div class="control">
+                                        <LanguageSelect lang={l.lang.clone()} onchange={on_lang} />
+                                    </div>
+                                    <div class="control is-expanded">
+                                        <input class="input is-small" type="text" placeholder="Description"
+                                            value={l.description.clone()} onchange={on_description} />
+                                    </div>
+                                    <div class="control">
+                                        <div class="file is-small">
+                                            <label class="file-label">
+                                                <input class="file-input" type="file" accept=".txt,text/plain" onchange={on_import} />
+                                                <span class="file-cta"><span class="file-label">{"Import .txt"}</span></span>
+                                            </label>
+                                        </div>
+                                    </div>
+                                    <div class="control">
+                                        <button class="button is-small" onclick={on_export}>{"Export .txt"}</button>
+                                    </div>
+                                    <div class="control">
+                                        <button class="delete" title="Remove lyrics" onclick={on_remove.reform({
+                                            let k = k.clone();
+                                            move |_| k.clone()
+                                        })}></button>
+                                    </div>
+                                </div>
+                                <textarea class="textarea" rows="10" value={l.text.clone()} onchange={on_text} />
+                            </div>
+                        }
+                    }) }
+                    <div class="field is-grouped">
+                        <div class="control">
+                            <LanguageSelect lang={(*new_lang).clone()} onchange={on_new_lang} />
+                        </div>
+                        <div class="control is-expanded">
+                            <input class="input is-small" type="text" placeholder="Description"
+                                value={(*new_description).clone()} oninput={on_new_description} />
+                        </div>
+                        <div class="control">
+                            <button class="button is-small" disabled={keys.contains(&new_key)} onclick={on_add_click}>
+                                {"Add lyrics"}
+                            </button>
+                        </div>
+                    </div>
+                    if keys.contains(&new_key) {
+                        <p class="help">{"There are already lyrics with this language and description."}</p>
+                    }
+                </div>
+            </div>
+        </div>
+    }
+}
+
+fn key(lyrics: &Lyrics) -> String {
+    frame::key(&Frame::from(lyrics.clone()))
+}
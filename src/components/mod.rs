@@ -1,7 +1,31 @@
+mod add_frame;
+mod chapter_editor;
 mod file_loader;
+mod frame_editor;
+mod history_panel;
 mod id3_tag;
+mod image_panel;
+mod language_select;
+mod link_editor;
+mod lyrics_editor;
 mod mp3_audio;
+mod picture_manager;
 mod popup;
+mod review_changes;
+mod sylt_editor;
+mod toc_editor;
+mod user_text_editor;
+mod value_list;
+mod version_select;
+pub use chapter_editor::ChapterEditor;
 pub use file_loader::FileLoader;
+pub use history_panel::HistoryPanel;
 pub use id3_tag::ID3Tag;
+pub use link_editor::LinkEditor;
+pub use lyrics_editor::LyricsEditor;
 pub use mp3_audio::MP3Audio;
+pub use picture_manager::PictureManager;
+pub use popup::Popup;
+pub use sylt_editor::SyltEditor;
+pub use toc_editor::TocEditor;
+pub use user_text_editor::UserTextEditor;
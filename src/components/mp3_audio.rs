@@ -1,39 +1,150 @@
 // use _MP3AudioProps::seek_position;
 use gloo::console::log;
+use gloo::events::EventListener;
+use id3::frame::Chapter;
+use rid3_core::chapters;
 
 use web_sys::wasm_bindgen::JsCast;
-use web_sys::Element;
+use web_sys::{Element, HtmlElement, HtmlInputElement, HtmlMediaElement, KeyboardEvent};
 use yew::prelude::*;
 use yew_hooks::{use_media_with_options, UseMediaOptions};
+
+use crate::is_text_field;
 //MP3AudioProps
 #[derive(Properties, PartialEq)]
 pub struct MP3AudioProps {
     pub url: String,
     pub seek_position: UseStateHandle<Option<f64>>,
     pub file_name: String,
+    /// The `<audio>` element, shared so other editors can read the playhead.
+    pub audio_ref: NodeRef,
+    /// The tag's chapters, so the one just marked can be given a title.
+    pub chapters: Vec<Chapter>,
+    /// Starts a chapter at the given position, in a file of the given length,
+    /// both in milliseconds.
+    pub on_mark: Callback<(u32, u32)>,
+    /// Receives the marked chapter with its new title, and its index in `chapters`.
+    pub on_chapter_change: Callback<(usize, Chapter)>,
+    /// Receives the length of the audio in seconds once the player knows it.
+    pub on_duration: Callback<f64>,
 }
 
 #[function_component(MP3Audio)]
-pub fn mp3_audio(MP3AudioProps { url, seek_position, file_name }: &MP3AudioProps) -> Html {
+pub fn mp3_audio(MP3AudioProps { url, seek_position, file_name, audio_ref, chapters, on_mark, on_chapter_change, on_duration }: &MP3AudioProps) -> Html {
     let options = UseMediaOptions {
         ontimeupdate: None,
         ..Default::default()
     };
-    let node_audio = use_node_ref();
+    let node_audio = audio_ref.clone();
     let audio = use_media_with_options(node_audio.clone(), url.clone(), options);
 
     {
         let audio = audio.clone();
         let seek_position = seek_position.clone();
         use_effect_with(seek_position, move |seek_position| {
-            if let Some(position) = seek_position.as_ref() {
-                log!("Seeking to {:?}", *position);
-                audio.seek(*position);
+            if let Some(position) = **seek_position {
+                log!("Seeking to {:?}", position);
+                audio.seek(position);
                 audio.play();
+                // Cleared so that seeking to the same place again, as when a
+                // chapter is nudged back and forth, plays from there again.
+                seek_position.set(None);
             }
         });
     }
 
+    {
+        let on_duration = on_duration.clone();
+        use_effect_with(*audio.duration, move |duration| {
+            if duration.is_finite() && *duration > 0.0 {
+                on_duration.emit(*duration);
+            }
+        });
+    }
+
+    // Where the last chapter was marked, while its title is asked for.
+    let marked = use_state(|| None::<u32>);
+    let title_ref = use_node_ref();
+    let marked_chapter = marked.and_then(|at| chapters.iter().position(|c| c.start_time == at)).map(|i| (i, chapters[i].clone()));
+
+    let mark = {
+        let audio_ref = audio_ref.clone();
+        let on_mark = on_mark.clone();
+        let marked = marked.setter();
+        Callback::from(move |_: ()| {
+            let Some(player) = audio_ref.cast::<HtmlMediaElement>() else {
+                return;
+            };
+            let duration = Some(player.duration()).filter(|d| d.is_finite()).unwrap_or(0.0);
+            let at = (player.current_time() * 1000.0).round() as u32;
+            on_mark.emit((at, (duration * 1000.0).round() as u32));
+            marked.set(Some(at));
+        })
+    };
+    // M marks a chapter, unless a text field has the keyboard.
+    use_effect_with(mark.clone(), |mark| {
+        let mark = mark.clone();
+        let listener = EventListener::new(&gloo::utils::window(), "keydown", move |e| {
+            let Some(e) = e.dyn_ref::<KeyboardEvent>() else {
+                return;
+            };
+            if e.ctrl_key() || e.meta_key() || e.alt_key() || !e.key().eq_ignore_ascii_case("m") {
+                return;
+            }
+            if e.target()
+                .and_then(|t| t.dyn_into::<HtmlElement>().ok())
+                .is_some_and(|t| is_text_field(&t))
+            {
+                return;
+            }
+            e.prevent_default();
+            mark.emit(());
+        });
+        move || drop(listener)
+    });
+    {
+        let title_ref = title_ref.clone();
+        use_effect_with(marked_chapter.as_ref().map(|(_, c)| c.element_id.clone()), move |_| {
+            if let Some(input) = title_ref.cast::<HtmlInputElement>() {
+                let _ = input.focus();
+            }
+        });
+    }
+    let set_title = {
+        let marked_chapter = marked_chapter.clone();
+        let on_chapter_change = on_chapter_change.clone();
+        move |title: String| {
+            if let Some((index, chapter)) = &marked_chapter {
+                let mut titled = chapter.clone();
+                chapters::set_chapter_title(&mut titled, &title);
+                on_chapter_change.emit((*index, titled));
+            }
+        }
+    };
+    let on_title = {
+        let set_title = set_title.clone();
+        Callback::from(move |e: Event| {
+            let input: HtmlInputElement = e.target_unchecked_into();
+            set_title(input.value());
+        })
+    };
+    let on_title_key = {
+        let marked = marked.clone();
+        Callback::from(move |e: KeyboardEvent| match e.key().as_str() {
+            "Enter" => {
+                let input: HtmlInputElement = e.target_unchecked_into();
+                set_title(input.value());
+                marked.set(None);
+            }
+            "Escape" => marked.set(None),
+            _ => {}
+        })
+    };
+    let on_done = {
+        let marked = marked.clone();
+        Callback::from(move |_: MouseEvent| marked.set(None))
+    };
+
     let onplay = {
         let audio = audio.clone();
         Callback::from(move |_| {
@@ -80,7 +191,21 @@ pub fn mp3_audio(MP3AudioProps { url, seek_position, file_name }: &MP3AudioProps
                         ></progress>
                         <button class="button" onclick={onplay} disabled={*audio.playing}>{ "Play" }</button>
                         <button class="button" onclick={onpause} disabled={!*audio.playing}>{ "Pause" }</button>
+                        <button class="button" onclick={mark.reform(|_: MouseEvent| ())} title="Shortcut: M">{ "Mark chapter" }</button>
                         <div>{format!("{:02}:{:02}", (*audio.time / 60.0) as i32, (*audio.time % 60.0) as i32)}</div>
+                        if let Some((_, chapter)) = marked_chapter {
+                            <div class="field has-addons mt-2">
+                                <div class="control is-expanded">
+                                    <input ref={title_ref} class="input" type="text"
+                                        placeholder={format!("Title of the chapter at {}", chapters::format_time(chapter.start_time))}
+                                        value={chapters::chapter_title(&chapter).unwrap_or_default().to_string()}
+                                        onchange={on_title} onkeydown={on_title_key} />
+                                </div>
+                                <div class="control">
+                                    <button class="button" onclick={on_done}>{ "Done" }</button>
+                                </div>
+                            </div>
+                        }
                     </div>
                 </div>
             </div>
@@ -0,0 +1,408 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use gloo_file::callbacks::FileReader;
+use gloo_file::File;
+use id3::frame::{Frame, Picture, PictureType};
+use rid3_core::{art, artwork, frame};
+use web_sys::{HtmlInputElement, HtmlSelectElement};
+use yew::prelude::*;
+
+use super::image_panel::ImagePanel;
+use crate::download::{download_picture, picture_src};
+use crate::error::AppError;
+
+#[derive(Properties, PartialEq)]
+pub struct PictureManagerProps {
+    /// Every `APIC` frame in the main tag.
+    pub pictures: Vec<Picture>,
+    /// Keys of the frames edited since the tag was loaded or last saved.
+    pub changed: Vec<String>,
+    /// The saved MP3's name, which downloaded pictures are named after.
+    pub download_name: String,
+    /// How many pictures, chapter pictures included, `on_download_all` saves.
+    pub artwork: usize,
+    /// Saves every picture in the tag as one zip.
+    pub on_download_all: Callback<MouseEvent>,
+    /// Receives the key of the edited `APIC` frame and its new content.
+    pub on_change: Callback<(String, Picture)>,
+    pub on_add: Callback<Picture>,
+    pub on_remove: Callback<String>,
+    pub on_error: Callback<AppError>,
+}
+
+/// Lists the tag's pictures and adds, replaces and removes them. Types and
+/// descriptions that would clash with another picture can't be picked.
+#[function_component(PictureManager)]
+pub fn picture_manager(
+    PictureManagerProps {
+        pictures,
+        changed,
+        download_name,
+        artwork,
+        on_download_all,
+        on_change,
+        on_add,
+        on_remove,
+        on_error,
+    }: &PictureManagerProps,
+) -> Html {
+    let new_type = use_state(|| PictureType::CoverFront);
+    let new_description = use_state(String::new);
+    // Keeps an upload running until the file has been read.
+    let reader = use_mut_ref(|| None::<FileReader>);
+
+    let conflict = art::conflict(pictures, *new_type, &new_description);
+    let on_new_type = {
+        let new_type = new_type.clone();
+        Callback::from(move |e: Event| {
+            let select: HtmlSelectElement = e.target_unchecked_into();
+            if let Some(t) = picture_type(&select.value()) {
+                new_type.set(t);
+            }
+        })
+    };
+    let on_new_description = {
+        let new_description = new_description.clone();
+        Callback::from(move |e: InputEvent| {
+            let input: HtmlInputElement = e.target_unchecked_into();
+            new_description.set(input.value());
+        })
+    };
+    let on_upload = {
+        let new_type = new_type.clone();
+        let new_description = new_description.clone();
+        let on_add = on_add.reform(move |(mime_type, data)| Picture {
+            mime_type,
+            picture_type: *new_type,
+            description: (*new_description).clone(),
+            data,
+        });
+        read_image(reader, on_add, on_error)
+    };
+
+    html! {
+        <div class="container">
+            <div class="card">
+                <header class="card-header">
+                    <p class="card-header-title">{"Pictures"}</p>
+                </header>
+                <div class="card-content">
+                    <table class="table is-fullwidth">
+                        <thead>
+                            <tr>
+                                <th></th>
+                                <th>{"Type and description"}</th>
+                                <th>{"Format"}</th>
+                                <th></th>
+                            </tr>
+                        </thead>
+                        <tbody>
+                            { for pictures.iter().enumerate().map(|(i, p)| {
+                                let key = key(p);
+                                let others: Vec<Picture> = pictures
+                                    .iter()
+                                    .enumerate()
+                                    .filter(|(j, _)| *j != i)
+                                    .map(|(_, p)| p.clone())
+                                    .collect();
+                                html! {
+                                    <PictureRow
+                                        picture={p.clone()}
+                                        others={others}
+                                        edited={changed.contains(&key)}
+                                        download_name={download_name.clone()}
+                                        on_change={on_change.reform({
+                                            let key = key.clone();
+                                            move |p| (key.clone(), p)
+                                        })}
+                                        on_remove={on_remove.reform(move |_| key.clone())}
+                                        on_error={on_error.clone()}
+                                    />
+                                }
+                            }) }
+                        </tbody>
+                    </table>
+                    <div class="field is-grouped">
+                        <div class="control">
+                            <TypeSelect selected={*new_type} others={pictures.clone()}
+                                description={(*new_description).clone()} onchange={on_new_type} />
+                        </div>
+                        <div class="control is-expanded">
+                            <input class="input is-small" type="text" placeholder="Description"
+                                value={(*new_description).clone()} oninput={on_new_description} />
+                        </div>
+                        <div class="control">
+                            <div class="file is-small">
+                                <label class="file-label">
+                                    <input class="file-input" type="file" accept="image/*"
+                                        disabled={conflict.is_some()} onchange={on_upload} />
+                                    <span class="file-cta"><span class="file-label">{"Upload image"}</span></span>
+                                </label>
+                            </div>
+                        </div>
+                    </div>
+                    if let Some(conflict) = conflict {
+                        <p class="help is-danger">{ conflict.to_string() }</p>
+                    }
+                    <button class="button is-small mt-3" disabled={*artwork == 0} onclick={on_download_all.clone()}>
+                        { format!("Download all artwork ({})", artwork) }
+                    </button>
+                </div>
+            </div>
+        </div>
+    }
+}
+
+#[derive(Properties, PartialEq)]
+struct PictureRowProps {
+    picture: Picture,
+    /// The tag's other pictures, which this one must not clash with.
+    others: Vec<Picture>,
+    edited: bool,
+    download_name: String,
+    on_change: Callback<Picture>,
+    on_remove: Callback<MouseEvent>,
+    on_error: Callback<AppError>,
+}
+
+#[function_component(PictureRow)]
+fn picture_row(
+    PictureRowProps {
+        picture,
+        others,
+        edited,
+        download_name,
+        on_change,
+        on_remove,
+        on_error,
+    }: &PictureRowProps,
+) -> Html {
+    // A description that was refused, and why.
+    let refused = use_state(|| None::<(String, art::Conflict)>);
+    let reader = use_mut_ref(|| None::<FileReader>);
+    let editing = use_state(|| false);
+
+    let on_type = {
+        let picture = picture.clone();
+        on_change.reform(move |e: Event| {
+            let select: HtmlSelectElement = e.target_unchecked_into();
+            Picture {
+                picture_type: picture_type(&select.value()).unwrap_or(picture.picture_type),
+                ..picture.clone()
+            }
+        })
+    };
+    let on_description = {
+        let picture = picture.clone();
+        let others = others.clone();
+        let on_change = on_change.clone();
+        let refused = refused.clone();
+        Callback::from(move |e: Event| {
+            let input: HtmlInputElement = e.target_unchecked_into();
+            let description = input.value();
+            match art::conflict(&others, picture.picture_type, &description) {
+                Some(conflict) => refused.set(Some((description, conflict))),
+                None => {
+                    refused.set(None);
+                    on_change.emit(Picture {
+                        description,
+                        ..picture.clone()
+                    });
+                }
+            }
+        })
+    };
+    let on_replace = {
+        let picture = picture.clone();
+        let on_change = on_change.reform(move |(mime_type, data)| Picture {
+            mime_type,
+            data,
+            ..picture.clone()
+        });
+        read_image(reader, on_change, on_error)
+    };
+    let mismatch = art::check_mime(picture);
+    let on_fix_mime = {
+        let picture = picture.clone();
+        on_change.reform(move |_: MouseEvent| {
+            let mut fixed = picture.clone();
+            art::fix_mime(&mut fixed);
+            fixed
+        })
+    };
+    let linked_url = art::linked_url(picture);
+    let on_download = {
+        let picture = picture.clone();
+        let download_name = download_name.clone();
+        let on_error = on_error.clone();
+        Callback::from(move |_: MouseEvent| {
+            if let Err(err) = download_picture(&picture, &download_name, "") {
+                on_error.emit(err);
+            }
+        })
+    };
+    let dimensions = artwork::dimensions(&picture.data).ok();
+    let on_edit = {
+        let editing = editing.clone();
+        Callback::from(move |_: MouseEvent| editing.set(!*editing))
+    };
+    let description = match &*refused {
+        Some((description, _)) => description.clone(),
+        None => picture.description.clone(),
+    };
+
+    html! {
+        <>
+        <tr class={classes!(edited.then_some("has-background-warning-light"))}>
+            <td>
+                <figure class="image is-64x64">
+                    <img src={picture_src(picture)} />
+                </figure>
+            </td>
+            <td>
+                <TypeSelect selected={picture.picture_type} others={others.clone()}
+                    description={picture.description.clone()} onchange={on_type} />
+                <input class={classes!("input", "is-small", refused.is_some().then_some("is-danger"))}
+                    type="text" placeholder="Description" value={description} onchange={on_description} />
+                if let Some((_, conflict)) = &*refused {
+                    <p class="help is-danger">{ conflict.to_string() }</p>
+                }
+            </td>
+            <td>
+                if let Some(url) = linked_url {
+                    <p>{"Linked image"}</p>
+                    <p class="help">{ url }</p>
+                } else {
+                    <p>{ &picture.mime_type }</p>
+                    <p class="help">
+                        if let Some(dimensions) = dimensions {
+                            { format!("{} · ", dimensions) }
+                        }
+                        { artwork::format_size(picture.data.len()) }
+                    </p>
+                }
+                if let Some(mismatch) = mismatch {
+                    <p class="help is-warning">{ mismatch.to_string() }</p>
+                    <button class="button is-small is-warning" onclick={on_fix_mime}>
+                        { format!("Use {}", mismatch.detected.mime_type()) }
+                    </button>
+                }
+            </td>
+            <td>
+                <div class="file is-small">
+                    <label class="file-label">
+                        <input class="file-input" type="file" accept="image/*" onchange={on_replace} />
+                        <span class="file-cta"><span class="file-label">{"Replace"}</span></span>
+                    </label>
+                </div>
+                if !art::is_linked(picture) {
+                    <button class="button is-small" onclick={on_download}>{"Download"}</button>
+                }
+                if dimensions.is_some() {
+                    <button class="button is-small" onclick={on_edit}>
+                        { if *editing { "Close" } else { "Resize…" } }
+                    </button>
+                }
+                <button class="delete" title="Remove picture" onclick={on_remove.clone()}></button>
+            </td>
+        </tr>
+        if *editing && dimensions.is_some() {
+            <tr>
+                <td colspan="4">
+                    <ImagePanel picture={picture.clone()} on_replace={on_change.clone()} on_error={on_error.clone()} />
+                </td>
+            </tr>
+        }
+        </>
+    }
+}
+
+#[derive(Properties, PartialEq)]
+struct TypeSelectProps {
+    selected: PictureType,
+    /// Pictures whose types can't be picked again with `description`.
+    others: Vec<Picture>,
+    description: String,
+    onchange: Callback<Event>,
+}
+
+/// Picks a picture type. Types that would clash with another picture are
+/// listed but disabled.
+#[function_component(TypeSelect)]
+fn type_select(
+    TypeSelectProps {
+        selected,
+        others,
+        description,
+        onchange,
+    }: &TypeSelectProps,
+) -> Html {
+    html! {
+        <div class="select is-small">
+            <select onchange={onchange.clone()}>
+                { for art::PICTURE_TYPES.iter().enumerate().map(|(i, t)| html! {
+                    <option
+                        value={i.to_string()}
+                        selected={t == selected}
+                        disabled={t != selected && art::conflict(others, *t, description).is_some()}
+                    >
+                        { t.to_string() }
+                    </option>
+                }) }
+            </select>
+        </div>
+    }
+}
+
+/// A change handler for an image file input that reads the chosen file and
+/// passes on its MIME type and bytes. The MIME type comes from the image's
+/// magic bytes, or the browser's guess when the format isn't recognised.
+pub(super) fn read_image(
+    reader: Rc<RefCell<Option<FileReader>>>,
+    on_read: Callback<(String, Vec<u8>)>,
+    on_error: &Callback<AppError>,
+) -> Callback<Event> {
+    let on_error = on_error.clone();
+    Callback::from(move |e: Event| {
+        let input: HtmlInputElement = e.target_unchecked_into();
+        let Some(file) = input.files().and_then(|files| files.get(0)) else {
+            return;
+        };
+        // Let the same file be picked again later.
+        input.set_value("");
+        let file = File::from(file);
+        let mime_type = match file.raw_mime_type() {
+            mime if mime.is_empty() => file
+                .name()
+                .rsplit_once('.')
+                .and_then(|(_, extension)| art::mime_for_extension(extension))
+                // ID3's MIME type for an image of unknown format.
+                .unwrap_or("image/")
+                .to_string(),
+            mime => mime,
+        };
+        let on_read = on_read.clone();
+        let on_error = on_error.clone();
+        let task = gloo_file::callbacks::read_as_bytes(&file, move |bytes| match bytes {
+            Ok(bytes) => match art::ImageFormat::detect(&bytes) {
+                Some(format) => on_read.emit((format.mime_type().to_string(), bytes)),
+                None => on_read.emit((mime_type, bytes)),
+            },
+            Err(err) => on_error.emit(err.into()),
+        });
+        *reader.borrow_mut() = Some(task);
+    })
+}
+
+fn picture_type(value: &str) -> Option<PictureType> {
+    value
+        .parse::<usize>()
+        .ok()
+        .and_then(|i| art::PICTURE_TYPES.get(i))
+        .copied()
+}
+
+fn key(picture: &Picture) -> String {
+    frame::key(&Frame::from(picture.clone()))
+}
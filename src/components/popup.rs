@@ -2,20 +2,21 @@ use yew::prelude::*;
 
 #[derive(Properties, PartialEq)]
 pub struct PopupProps {
+    pub message: String,
     pub on_close: Callback<MouseEvent>,
 }
 
 #[function_component(Popup)]
-pub fn popup_component(PopupProps { on_close }: &PopupProps) -> Html {
+pub fn popup_component(PopupProps { message, on_close }: &PopupProps) -> Html {
     let on_close_click = {
         let on_close = on_close.clone();
         Callback::from(move |event: MouseEvent| on_close.emit(event))
     };
 
     html! {
-        <div class="notification is-warning">
+        <div class="notification is-danger">
             <button class="delete" onclick={on_close_click}></button>
-            <p>{"This is a popup window!"}</p>
+            <p>{ message }</p>
         </div>
     }
 }
@@ -0,0 +1,81 @@
+use id3::Version;
+use rid3_core::diff::FrameChange;
+use rid3_core::layout::Trailer;
+use rid3_core::version::Change;
+use yew::prelude::*;
+
+#[derive(Properties, PartialEq)]
+pub struct ReviewChangesProps {
+    /// Frames edited since the tag was loaded or last saved.
+    pub edits: Vec<FrameChange>,
+    /// Trailing tags kept or removed since then, with whether they are kept.
+    pub trailers: Vec<(Trailer, bool)>,
+    /// Frames that change when the tag is converted to `version`.
+    pub conversion: Vec<Change>,
+    pub version: Version,
+    pub on_confirm: Callback<MouseEvent>,
+    pub on_cancel: Callback<MouseEvent>,
+}
+
+/// Lists the frames that were edited, the trailing tags that are now kept or
+/// removed, and the frames that will be converted, merged or dropped when the
+/// tag is written in another version, and asks before saving.
+#[function_component(ReviewChanges)]
+pub fn review_changes(
+    ReviewChangesProps {
+        edits,
+        trailers,
+        conversion,
+        version,
+        on_confirm,
+        on_cancel,
+    }: &ReviewChangesProps,
+) -> Html {
+    html! {
+        <div class="modal is-active">
+            <div class="modal-background" onclick={on_cancel.clone()}></div>
+            <div class="modal-card">
+                <header class="modal-card-head">
+                    <p class="modal-card-title">{ format!("Saving as {}", version) }</p>
+                </header>
+                <section class="modal-card-body">
+                    if edits.is_empty() {
+                        <p>{"No frames were edited."}</p>
+                    } else {
+                        <p>{"These frames were edited:"}</p>
+                        <ul>
+                            { for edits.iter().map(|c| {
+                                let class = match c {
+                                    FrameChange::Added(_) => "has-text-success",
+                                    FrameChange::Removed(_) => "has-text-danger",
+                                    FrameChange::Modified { .. } => "has-text-warning-dark",
+                                };
+                                html! { <li {class}>{ c.to_string() }</li> }
+                            }) }
+                        </ul>
+                    }
+                    if !trailers.is_empty() {
+                        <p class="mt-4">{"These trailing tags were changed:"}</p>
+                        <ul>
+                            { for trailers.iter().map(|(trailer, keep)| if *keep {
+                                html! { <li class="has-text-success">{ format!("Keeps the {}", trailer) }</li> }
+                            } else {
+                                html! { <li class="has-text-danger">{ format!("Removes the {}", trailer) }</li> }
+                            }) }
+                        </ul>
+                    }
+                    if !conversion.is_empty() {
+                        <p class="mt-4">{"These frames will change to fit the selected version:"}</p>
+                        <ul>
+                            { for conversion.iter().map(|c| html! { <li>{ c.to_string() }</li> }) }
+                        </ul>
+                    }
+                </section>
+                <footer class="modal-card-foot">
+                    <button class="button is-info" onclick={on_confirm.clone()}>{"Save"}</button>
+                    <button class="button" onclick={on_cancel.clone()}>{"Cancel"}</button>
+                </footer>
+            </div>
+        </div>
+    }
+}
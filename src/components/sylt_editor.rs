@@ -0,0 +1,419 @@
+use gloo_file::callbacks::FileReader;
+use gloo_file::File;
+use id3::frame::{Frame, SynchronisedLyrics, SynchronisedLyricsType, TimestampFormat};
+use rid3_core::chapters::parse_time;
+use rid3_core::{filename, frame, lyrics, sylt};
+use web_sys::{HtmlInputElement, HtmlMediaElement, HtmlSelectElement, HtmlTextAreaElement};
+use yew::prelude::*;
+
+use super::language_select::LanguageSelect;
+use crate::download::download;
+use crate::error::AppError;
+
+#[derive(Properties, PartialEq)]
+pub struct SyltEditorProps {
+    /// Every `SYLT` frame in the tag.
+    pub sylt: Vec<SynchronisedLyrics>,
+    /// How long an MPEG frame of this file plays, if its audio could be read.
+    pub frame_ms: Option<f64>,
+    /// The player whose position lines are stamped with.
+    pub audio_ref: NodeRef,
+    /// The saved MP3's name, which exported `.lrc` files are named after.
+    pub download_name: String,
+    /// Receives the key of the edited `SYLT` frame and its new content.
+    pub on_change: Callback<(String, SynchronisedLyrics)>,
+    pub on_add: Callback<SynchronisedLyrics>,
+    pub on_remove: Callback<String>,
+    /// Plays from the given position, in seconds.
+    pub on_seek: Callback<f64>,
+    pub on_error: Callback<AppError>,
+}
+
+/// Edits the tag's synchronised lyrics. Lines are pasted in and stamped one
+/// at a time with the player's position while the file plays.
+#[function_component(SyltEditor)]
+pub fn sylt_editor(
+    SyltEditorProps {
+        sylt,
+        frame_ms,
+        audio_ref,
+        download_name,
+        on_change,
+        on_add,
+        on_remove,
+        on_seek,
+        on_error,
+    }: &SyltEditorProps,
+) -> Html {
+    let new_lang = use_state(|| "eng".to_string());
+    let new_description = use_state(String::new);
+
+    let keys: Vec<String> = sylt.iter().map(key).collect();
+    let new_sylt = SynchronisedLyrics {
+        lang: (*new_lang).clone(),
+        timestamp_format: TimestampFormat::Ms,
+        content_type: SynchronisedLyricsType::Lyrics,
+        description: (*new_description).clone(),
+        content: Vec::new(),
+    };
+    let exists = keys.contains(&key(&new_sylt));
+    let on_new_lang = {
+        let new_lang = new_lang.clone();
+        Callback::from(move |e: Event| {
+            let select: HtmlSelectElement = e.target_unchecked_into();
+            new_lang.set(select.value());
+        })
+    };
+    let on_new_description = {
+        let new_description = new_description.clone();
+        Callback::from(move |e: InputEvent| {
+            let input: HtmlInputElement = e.target_unchecked_into();
+            new_description.set(input.value());
+        })
+    };
+    let on_add_click = {
+        let on_add = on_add.clone();
+        let new_description = new_description.clone();
+        Callback::from(move |_: MouseEvent| {
+            on_add.emit(new_sylt.clone());
+            new_description.set(String::new());
+        })
+    };
+
+    html! {
+        <div class="container">
+            <div class="card">
+                <header class="card-header">
+                    <p class="card-header-title">{"Synchronised lyrics"}</p>
+                </header>
+                <div class="card-content">
+                    { for sylt.iter().zip(keys.iter()).map(|(s, k)| {
+                        let k = k.clone();
+                        html! {
+                            <SyltFrame
+                                sylt={s.clone()}
+                                frame_ms={*frame_ms}
+                                audio_ref={audio_ref.clone()}
+                                download_name={download_name.clone()}
+                                on_change={on_change.reform({
+                                    let k = k.clone();
+                                    move |s| (k.clone(), s)
+                                })}
+                                on_remove={on_remove.reform(move |_| k.clone())}
+                                on_seek={on_seek.clone()}
+                                on_error={on_error.clone()}
+                            />
+                        }
+                    }) }
+                    <div class="field is-grouped">
+                        <div class="control">
+                            <LanguageSelect lang={(*new_lang).clone()} onchange={on_new_lang} />
+                        </div>
+                        <div class="control is-expanded">
+                            <input class="input is-small" type="text" placeholder="Description"
+                                value={(*new_description).clone()} oninput={on_new_description} />
+                        </div>
+                        <div class="control">
+                            <button class="button is-small" disabled={exists} onclick={on_add_click}>
+                                {"Add synchronised lyrics"}
+                            </button>
+                        </div>
+                    </div>
+                </div>
+            </div>
+        </div>
+    }
+}
+
+#[derive(Properties, PartialEq)]
+struct SyltFrameProps {
+    sylt: SynchronisedLyrics,
+    frame_ms: Option<f64>,
+    audio_ref: NodeRef,
+    download_name: String,
+    on_change: Callback<SynchronisedLyrics>,
+    on_remove: Callback<MouseEvent>,
+    on_seek: Callback<f64>,
+    on_error: Callback<AppError>,
+}
+
+#[function_component(SyltFrame)]
+fn sylt_frame(
+    SyltFrameProps {
+        sylt,
+        frame_ms,
+        audio_ref,
+        download_name,
+        on_change,
+        on_remove,
+        on_seek,
+        on_error,
+    }: &SyltFrameProps,
+) -> Html {
+    // The pasted lines and the index of the next one to stamp.
+    let pending = use_state(String::new);
+    let next = use_state(|| 0usize);
+    // Keeps an import running until the file has been read.
+    let reader = use_mut_ref(|| None::<FileReader>);
+
+    let format = sylt.timestamp_format;
+    // MPEG frame timestamps can only be worked out when the audio was read.
+    let frame_ms_or_none = *frame_ms;
+    let can_time = format == TimestampFormat::Ms || frame_ms.is_some();
+    let to_ms = move |ts: u32| {
+        sylt::convert_timestamp(
+            ts,
+            format,
+            TimestampFormat::Ms,
+            frame_ms_or_none.unwrap_or(1.0),
+        )
+    };
+    let from_ms = move |ms: u32| {
+        sylt::convert_timestamp(
+            ms,
+            TimestampFormat::Ms,
+            format,
+            frame_ms_or_none.unwrap_or(1.0),
+        )
+    };
+
+    let lines = sylt::lines(&pending);
+    let next_line = lines.get(*next).cloned();
+
+    let on_pending = {
+        let pending = pending.clone();
+        let next = next.clone();
+        Callback::from(move |e: InputEvent| {
+            let textarea: HtmlTextAreaElement = e.target_unchecked_into();
+            pending.set(textarea.value());
+            next.set(0);
+        })
+    };
+    let on_stamp = {
+        let audio_ref = audio_ref.clone();
+        let next = next.clone();
+        let on_error = on_error.clone();
+        let next_line = next_line.clone();
+        let stamp = edit(sylt, on_change, move |s, (ms, line): (u32, String)| {
+            s.content.push((from_ms(ms), line));
+            s.content.sort_by_key(|(ts, _)| *ts);
+        });
+        Callback::from(move |_: MouseEvent| {
+            let Some(line) = next_line.clone() else {
+                return;
+            };
+            let Some(audio) = audio_ref.cast::<HtmlMediaElement>() else {
+                on_error.emit(AppError::Browser(
+                    "the audio player is not available".into(),
+                ));
+                return;
+            };
+            let ms = (audio.current_time() * 1000.0).round() as u32;
+            stamp.emit((ms, line));
+            next.set(*next + 1);
+        })
+    };
+    let on_lang = edit(sylt, on_change, |s, e: Event| {
+        let select: HtmlSelectElement = e.target_unchecked_into();
+        s.lang = select.value();
+    });
+    let on_description = edit(sylt, on_change, |s, e: Event| {
+        let input: HtmlInputElement = e.target_unchecked_into();
+        s.description = input.value();
+    });
+    let on_content_type = edit(sylt, on_change, |s, e: Event| {
+        let select: HtmlSelectElement = e.target_unchecked_into();
+        if let Some(t) = select
+            .value()
+            .parse::<usize>()
+            .ok()
+            .and_then(|i| sylt::CONTENT_TYPES.get(i))
+        {
+            s.content_type = *t;
+        }
+    });
+    let on_format = edit(sylt, on_change, move |s, e: Event| {
+        let select: HtmlSelectElement = e.target_unchecked_into();
+        let Some(to) = select
+            .value()
+            .parse::<usize>()
+            .ok()
+            .and_then(|i| sylt::TIMESTAMP_FORMATS.get(i))
+        else {
+            return;
+        };
+        let Some(frame_ms) = frame_ms_or_none else {
+            return;
+        };
+        for (ts, _) in s.content.iter_mut() {
+            *ts = sylt::convert_timestamp(*ts, s.timestamp_format, *to, frame_ms);
+        }
+        s.timestamp_format = *to;
+    });
+    let on_import = {
+        let replace = edit(sylt, on_change, move |s, text: String| {
+            s.content = sylt::parse_lrc(&text)
+                .into_iter()
+                .map(|(ms, line)| (from_ms(ms), line))
+                .collect();
+        });
+        let on_error = on_error.clone();
+        let reader = reader.clone();
+        Callback::from(move |e: Event| {
+            let input: HtmlInputElement = e.target_unchecked_into();
+            let Some(file) = input.files().and_then(|files| files.get(0)) else {
+                return;
+            };
+            let replace = replace.clone();
+            let on_error = on_error.clone();
+            let task =
+                gloo_file::callbacks::read_as_text(&File::from(file), move |text| match text {
+                    Ok(text) => replace.emit(text),
+                    Err(err) => on_error.emit(err.into()),
+                });
+            *reader.borrow_mut() = Some(task);
+        })
+    };
+    let on_export = {
+        let timed: Vec<(u32, String)> = sylt
+            .content
+            .iter()
+            .map(|(ts, line)| (to_ms(*ts), line.clone()))
+            .collect();
+        let name = filename::sibling(
+            download_name,
+            &lyrics::file_suffix(&sylt.lang, &sylt.description),
+            "lrc",
+        );
+        let on_error = on_error.clone();
+        Callback::from(move |_: MouseEvent| {
+            if let Err(err) = download(sylt::to_lrc(&timed).as_bytes(), &name, "text/plain") {
+                on_error.emit(err);
+            }
+        })
+    };
+
+    html! {
+        <div class="box">
+            <div class="field is-grouped is-grouped-multiline">
+                <div class="control">
+                    <LanguageSelect lang={sylt.lang.clone()} onchange={on_lang} />
+                </div>
+                <div class="control is-expanded">
+                    <input class="input is-small" type="text" placeholder="Description"
+                        value={sylt.description.clone()} onchange={on_description} />
+                </div>
+                <div class="control">
+                    <div class="select is-small">
+                        <select onchange={on_content_type} title="Content type">
+                            { for sylt::CONTENT_TYPES.iter().enumerate().map(|(i, t)| html! {
+                                <option value={i.to_string()} selected={*t == sylt.content_type}>{ t.to_string() }</option>
+                            }) }
+                        </select>
+                    </div>
+                </div>
+                <div class="control">
+                    <div class="select is-small">
+                        <select onchange={on_format} title="Timestamp format" disabled={frame_ms.is_none()}>
+                            { for sylt::TIMESTAMP_FORMATS.iter().enumerate().map(|(i, f)| html! {
+                                <option value={i.to_string()} selected={*f == format}>{ f.to_string() }</option>
+                            }) }
+                        </select>
+                    </div>
+                </div>
+                <div class="control">
+                    <div class="file is-small">
+                        <label class="file-label">
+                            <input class="file-input" type="file" accept=".lrc,text/plain" onchange={on_import} disabled={!can_time} />
+                            <span class="file-cta"><span class="file-label">{"Import .lrc"}</span></span>
+                        </label>
+                    </div>
+                </div>
+                <div class="control">
+                    <button class="button is-small" onclick={on_export} disabled={!can_time}>{"Export .lrc"}</button>
+                </div>
+                <div class="control">
+                    <button class="delete" title="Remove synchronised lyrics" onclick={on_remove.clone()}></button>
+                </div>
+            </div>
+
+            <table class="table is-narrow is-fullwidth">
+                <tbody>
+                    { for sylt.content.iter().enumerate().map(|(i, (ts, line))| {
+                        let seconds = f64::from(to_ms(*ts)) / 1000.0;
+                        let on_time = edit(sylt, on_change, move |s, e: Event| {
+                            let input: HtmlInputElement = e.target_unchecked_into();
+                            let parsed = match s.timestamp_format {
+                                TimestampFormat::Ms => parse_time(&input.value()),
+                                TimestampFormat::Mpeg => input.value().trim().parse().ok(),
+                            };
+                            if let Some(ts) = parsed {
+                                s.content[i].0 = ts;
+                                s.content.sort_by_key(|(ts, _)| *ts);
+                            }
+                        });
+                        let on_line = edit(sylt, on_change, move |s, e: Event| {
+                            let input: HtmlInputElement = e.target_unchecked_into();
+                            s.content[i].1 = input.value();
+                        });
+                        let on_delete = edit(sylt, on_change, move |s, _: MouseEvent| {
+                            s.content.remove(i);
+                        });
+                        let time = match format {
+                            TimestampFormat::Ms => rid3_core::chapters::format_time(*ts),
+                            TimestampFormat::Mpeg => ts.to_string(),
+                        };
+                        html! {
+                            <tr>
+                                <td>
+                                    <button class="button is-small is-info" title="Play from here" disabled={!can_time}
+                                        onclick={on_seek.reform(move |_| seconds)}>{">"}</button>
+                                </td>
+                                <td><input class="input is-small" type="text" value={time} onchange={on_time} /></td>
+                                <td><input class="input is-small" type="text" value={line.clone()} onchange={on_line} /></td>
+                                <td><button class="delete" title="Remove line" onclick={on_delete}></button></td>
+                            </tr>
+                        }
+                    }) }
+                </tbody>
+            </table>
+
+            <div class="field">
+                <label class="label is-small">{"Lines to stamp"}</label>
+                <textarea class="textarea is-small" rows="4" placeholder="Paste one line per row"
+                    value={(*pending).clone()} oninput={on_pending} />
+            </div>
+            <button class="button is-primary" disabled={next_line.is_none() || !can_time} onclick={on_stamp}>
+                if let Some(line) = &next_line {
+                    { format!("Stamp \u{201c}{}\u{201d}", line) }
+                } else {
+                    {"Stamp next line"}
+                }
+            </button>
+            <p class="help">
+                { format!("{} of {} lines stamped. Play the file, focus this button and tap Space or Enter as each line starts.", (*next).min(lines.len()), lines.len()) }
+            </p>
+        </div>
+    }
+}
+
+/// A handler that applies `f` to a copy of `sylt` and passes it on.
+fn edit<E: 'static>(
+    sylt: &SynchronisedLyrics,
+    on_change: &Callback<SynchronisedLyrics>,
+    f: impl Fn(&mut SynchronisedLyrics, E) + 'static,
+) -> Callback<E> {
+    let sylt = sylt.clone();
+    on_change.reform(move |e| {
+        let mut edited = sylt.clone();
+        f(&mut edited, e);
+        edited
+    })
+}
+
+fn key(sylt: &SynchronisedLyrics) -> String {
+    frame::key(&Frame::with_content(
+        "SYLT",
+        id3::Content::SynchronisedLyrics(sylt.clone()),
+    ))
+}
@@ -0,0 +1,300 @@
+use id3::frame::{Chapter, TableOfContents};
+use rid3_core::chapters::{self, format_time};
+use rid3_core::toc;
+use web_sys::{HtmlInputElement, HtmlSelectElement};
+use yew::prelude::*;
+
+#[derive(Properties, PartialEq)]
+pub struct TocEditorProps {
+    /// Every `CTOC` frame in the tag.
+    pub tables: Vec<TableOfContents>,
+    /// Every `CHAP` frame in the tag.
+    pub chapters: Vec<Chapter>,
+    /// Keys of the frames edited since the tag was loaded or last saved.
+    pub changed: Vec<String>,
+    /// Receives an edited table, which keeps its element ID.
+    pub on_change: Callback<TableOfContents>,
+    /// Adds a table with the given title below the table with the given ID.
+    pub on_add: Callback<(String, String)>,
+    /// Removes the table with the given element ID.
+    pub on_remove: Callback<String>,
+    /// Moves the element with the first ID into the table with the second.
+    pub on_move: Callback<(String, String)>,
+    /// Creates a top-level table listing every chapter.
+    pub on_sync: Callback<MouseEvent>,
+}
+
+/// Shows the tag's tables of contents as a tree from the top level down,
+/// and edits their titles, flags and what each one lists.
+#[function_component(TocEditor)]
+pub fn toc_editor(
+    TocEditorProps {
+        tables,
+        chapters,
+        changed,
+        on_change,
+        on_add,
+        on_remove,
+        on_move,
+        on_sync,
+    }: &TocEditorProps,
+) -> Html {
+    if tables.is_empty() && chapters.is_empty() {
+        return html! {};
+    }
+    // Tables no other table lists, top level first.
+    let listed: Vec<&String> = tables.iter().flat_map(|t| &t.elements).collect();
+    let mut roots: Vec<&TableOfContents> = tables
+        .iter()
+        .filter(|t| t.top_level || !listed.contains(&&t.element_id))
+        .collect();
+    roots.sort_by_key(|t| !t.top_level);
+    let has_top_level = tables.iter().any(|t| t.top_level);
+    // Chapters no table lists can be moved into any table.
+    let unlisted: Vec<&Chapter> = chapters
+        .iter()
+        .filter(|c| !listed.contains(&&c.element_id))
+        .collect();
+    let all_ids: Vec<String> = tables.iter().map(|t| t.element_id.clone()).collect();
+
+    html! {
+        <div class="container">
+            <div class="card">
+                <header class="card-header">
+                    <p class="card-header-title">{"Table of contents"}</p>
+                </header>
+                <div class="card-content">
+                    if tables.is_empty() {
+                        <div class="notification is-info is-light">
+                            {"This tag has no table of contents, which some players need to show chapters. "}
+                            <button class="button is-small" onclick={on_sync.clone()}>{"Create one"}</button>
+                        </div>
+                    } else if !has_top_level {
+                        <div class="notification is-warning is-light">
+                            {"None of the tables is the top level, so chapters added now are not listed anywhere."}
+                        </div>
+                    }
+                    <div class="content">
+                        <ul>
+                            { for roots.into_iter().map(|t| html! {
+                                <TocNode
+                                    toc={t.clone()}
+                                    tables={tables.clone()}
+                                    chapters={chapters.clone()}
+                                    changed={changed.clone()}
+                                    ancestors={Vec::<String>::new()}
+                                    move_targets={if t.top_level { Vec::new() } else { other_ids(tables, &t.element_id) }}
+                                    on_change={on_change.clone()}
+                                    on_add={on_add.clone()}
+                                    on_remove={on_remove.clone()}
+                                    on_move={on_move.clone()}
+                                />
+                            }) }
+                        </ul>
+                        if !tables.is_empty() && !unlisted.is_empty() {
+                            <p class="mb-1">{"Unlisted chapters"}</p>
+                            <p class="help mt-0">{"Players show these only as they play, if at all. Move one into a table to list it."}</p>
+                            <ul>
+                                { for unlisted.into_iter().map(|c| chapter_item(c, &all_ids, on_move)) }
+                            </ul>
+                        }
+                    </div>
+                </div>
+            </div>
+        </div>
+    }
+}
+
+#[derive(Properties, PartialEq)]
+struct TocNodeProps {
+    toc: TableOfContents,
+    tables: Vec<TableOfContents>,
+    chapters: Vec<Chapter>,
+    changed: Vec<String>,
+    /// The tables this one is listed below, so a loop is drawn only once.
+    ancestors: Vec<String>,
+    /// Element IDs of the tables this one can be moved into.
+    move_targets: Vec<String>,
+    on_change: Callback<TableOfContents>,
+    on_add: Callback<(String, String)>,
+    on_remove: Callback<String>,
+    on_move: Callback<(String, String)>,
+}
+
+/// One table and, below it, what it lists.
+#[function_component(TocNode)]
+fn toc_node(
+    TocNodeProps {
+        toc,
+        tables,
+        chapters,
+        changed,
+        ancestors,
+        move_targets,
+        on_change,
+        on_add,
+        on_remove,
+        on_move,
+    }: &TocNodeProps,
+) -> Html {
+    let edited = changed.contains(&format!("CTOC:{}", toc.element_id));
+    let edit = |f: fn(&mut TableOfContents, &HtmlInputElement)| {
+        let toc = toc.clone();
+        on_change.reform(move |e: Event| {
+            let input: HtmlInputElement = e.target_unchecked_into();
+            let mut edited = toc.clone();
+            f(&mut edited, &input);
+            edited
+        })
+    };
+    let on_title = edit(|t, input| toc::set_toc_title(t, &input.value()));
+    let on_top_level = edit(|t, input| t.top_level = input.checked());
+    let on_ordered = edit(|t, input| t.ordered = input.checked());
+    let on_add_click = {
+        let element_id = toc.element_id.clone();
+        on_add.reform(move |_: MouseEvent| (element_id.clone(), String::new()))
+    };
+    let on_remove_click = {
+        let element_id = toc.element_id.clone();
+        on_remove.reform(move |_: MouseEvent| element_id.clone())
+    };
+    let mut below = ancestors.clone();
+    below.push(toc.element_id.clone());
+    // This table's children can be moved into any other table.
+    let targets = other_ids(tables, &toc.element_id);
+
+    html! {
+        <li>
+            <div class={classes!("field", "is-grouped", "is-grouped-multiline", edited.then_some("has-background-warning-light"))}>
+                <div class="control">
+                    <span class="tag is-dark">{ &toc.element_id }</span>
+                </div>
+                <div class="control">
+                    <input class="input is-small" type="text" placeholder="Title"
+                        value={toc::toc_title(toc).unwrap_or_default().to_string()} onchange={on_title} />
+                </div>
+                <div class="control">
+                    <label class="checkbox">
+                        <input type="checkbox" checked={toc.top_level} onchange={on_top_level} />
+                        {" Top level"}
+                    </label>
+                </div>
+                <div class="control">
+                    <label class="checkbox" title="Players show the children in this order">
+                        <input type="checkbox" checked={toc.ordered} onchange={on_ordered} />
+                        {" Ordered"}
+                    </label>
+                </div>
+                <div class="control">
+                    <button class="button is-small" onclick={on_add_click}>{"Add table below"}</button>
+                </div>
+                <div class="control">
+                    <MoveSelect element_id={toc.element_id.clone()} targets={move_targets.clone()} on_move={on_move.clone()} />
+                </div>
+                <div class="control">
+                    <button class="delete" title="Remove table" onclick={on_remove_click}></button>
+                </div>
+            </div>
+            <ul>
+                { for toc.elements.iter().map(|element_id| {
+                    if let Some(chapter) = chapters.iter().find(|c| c.element_id == *element_id) {
+                        chapter_item(chapter, &targets, on_move)
+                    } else if below.contains(element_id) {
+                        html! {
+                            <li class="has-text-danger">{ format!("{} (lists a table it is listed in)", element_id) }</li>
+                        }
+                    } else if let Some(child) = tables.iter().find(|t| t.element_id == *element_id) {
+                        html! {
+                            <TocNode
+                                toc={child.clone()}
+                                tables={tables.clone()}
+                                chapters={chapters.clone()}
+                                changed={changed.clone()}
+                                ancestors={below.clone()}
+                                move_targets={targets.clone()}
+                                on_change={on_change.clone()}
+                                on_add={on_add.clone()}
+                                on_remove={on_remove.clone()}
+                                on_move={on_move.clone()}
+                            />
+                        }
+                    } else {
+                        html! {
+                            <li class="has-text-danger">{ format!("{} (no such chapter or table)", element_id) }</li>
+                        }
+                    }
+                }) }
+            </ul>
+        </li>
+    }
+}
+
+/// A chapter's line in the tree, which can be moved into any of `targets`.
+fn chapter_item(
+    chapter: &Chapter,
+    targets: &[String],
+    on_move: &Callback<(String, String)>,
+) -> Html {
+    html! {
+        <li>
+            <span class="tag">{ &chapter.element_id }</span>
+            { format!(" {} ", chapters::chapter_title(chapter).unwrap_or("Untitled")) }
+            <span class="has-text-grey">{ format_time(chapter.start_time) }</span>
+            <MoveSelect element_id={chapter.element_id.clone()} targets={targets.to_vec()} on_move={on_move.clone()} />
+        </li>
+    }
+}
+
+#[derive(Properties, PartialEq)]
+struct MoveSelectProps {
+    element_id: String,
+    /// Element IDs of the tables it can be moved to.
+    targets: Vec<String>,
+    on_move: Callback<(String, String)>,
+}
+
+/// Moves a chapter or table into another table.
+#[function_component(MoveSelect)]
+fn move_select(
+    MoveSelectProps {
+        element_id,
+        targets,
+        on_move,
+    }: &MoveSelectProps,
+) -> Html {
+    let onchange = {
+        let element_id = element_id.clone();
+        let on_move = on_move.clone();
+        Callback::from(move |e: Event| {
+            let select: HtmlSelectElement = e.target_unchecked_into();
+            let to = select.value();
+            if !to.is_empty() {
+                on_move.emit((element_id.clone(), to));
+            }
+        })
+    };
+    let targets: Vec<&String> = targets.iter().filter(|t| *t != element_id).collect();
+    if targets.is_empty() {
+        return html! {};
+    }
+
+    html! {
+        <div class="select is-small ml-2">
+            <select {onchange}>
+                <option value="" selected=true>{"Move to…"}</option>
+                { for targets.into_iter().map(|t| html! {
+                    <option value={t.clone()}>{ t }</option>
+                }) }
+            </select>
+        </div>
+    }
+}
+
+/// The element IDs of every table but `element_id`.
+fn other_ids(tables: &[TableOfContents], element_id: &str) -> Vec<String> {
+    tables
+        .iter()
+        .map(|t| t.element_id.clone())
+        .filter(|id| id != element_id)
+        .collect()
+}
@@ -0,0 +1,150 @@
+use id3::frame::{ExtendedText, Frame};
+use rid3_core::{frame, user_text};
+use web_sys::HtmlInputElement;
+use yew::prelude::*;
+
+/// The `<datalist>` of common descriptions that description fields suggest from.
+const DESCRIPTIONS_LIST: &str = "txxx-descriptions";
+
+#[derive(Properties, PartialEq)]
+pub struct UserTextEditorProps {
+    /// Every `TXXX` frame in the tag.
+    pub texts: Vec<ExtendedText>,
+    /// Keys of the frames edited since the tag was loaded or last saved.
+    pub changed: Vec<String>,
+    /// Receives the key of the edited `TXXX` frame and its new content.
+    pub on_change: Callback<(String, ExtendedText)>,
+    pub on_add: Callback<ExtendedText>,
+    pub on_remove: Callback<String>,
+}
+
+/// Edits user-defined text frames as description/value pairs. Descriptions
+/// tell the frames apart, so a new one must not repeat an existing one.
+#[function_component(UserTextEditor)]
+pub fn user_text_editor(
+    UserTextEditorProps {
+        texts,
+        changed,
+        on_change,
+        on_add,
+        on_remove,
+    }: &UserTextEditorProps,
+) -> Html {
+    let new_description = use_state(String::new);
+    let new_value = use_state(String::new);
+
+    let keys: Vec<String> = texts.iter().map(key).collect();
+    let new_text = ExtendedText {
+        description: (*new_description).clone(),
+        value: (*new_value).clone(),
+    };
+    let exists = keys.contains(&key(&new_text));
+    let on_new_description = {
+        let new_description = new_description.clone();
+        Callback::from(move |e: InputEvent| {
+            let input: HtmlInputElement = e.target_unchecked_into();
+            new_description.set(input.value());
+        })
+    };
+    let on_new_value = {
+        let new_value = new_value.clone();
+        Callback::from(move |e: InputEvent| {
+            let input: HtmlInputElement = e.target_unchecked_into();
+            new_value.set(input.value());
+        })
+    };
+    let on_add_click = {
+        let on_add = on_add.clone();
+        let new_description = new_description.clone();
+        let new_value = new_value.clone();
+        Callback::from(move |_: MouseEvent| {
+            on_add.emit(new_text.clone());
+            new_description.set(String::new());
+            new_value.set(String::new());
+        })
+    };
+
+    html! {
+        <div class="container">
+            <div class="card">
+                <header class="card-header">
+                    <p class="card-header-title">{"User-defined text"}</p>
+                </header>
+                <div class="card-content">
+                    <datalist id={DESCRIPTIONS_LIST}>
+                        { for user_text::COMMON_DESCRIPTIONS.iter().map(|(description, hint)| html! {
+                            <option value={*description}>{ *hint }</option>
+                        }) }
+                    </datalist>
+                    <table class="table is-fullwidth">
+                        <thead>
+                            <tr>
+                                <th>{"Description"}</th>
+                                <th>{"Value"}</th>
+                                <th></th>
+                            </tr>
+                        </thead>
+                        <tbody>
+                            { for texts.iter().zip(keys.iter()).map(|(t, k)| {
+                                let edit = |set: fn(&mut ExtendedText, String)| {
+                                    let (t, k) = (t.clone(), k.clone());
+                                    on_change.reform(move |e: Event| {
+                                        let input: HtmlInputElement = e.target_unchecked_into();
+                                        let mut edited = t.clone();
+                                        set(&mut edited, input.value());
+                                        (k.clone(), edited)
+                                    })
+                                };
+                                html! {
+                                    <tr class={classes!(changed.contains(k).then_some("has-background-warning-light"))}>
+                                        <td>
+                                            <input class="input is-small" type="text" list={DESCRIPTIONS_LIST}
+                                                value={t.description.clone()}
+                                                onchange={edit(|t, v| t.description = v)} />
+                                        </td>
+                                        <td>
+                                            <input class="input is-small" type="text"
+                                                placeholder={user_text::hint(&t.description)}
+                                                value={t.value.clone()}
+                                                onchange={edit(|t, v| t.value = v)} />
+                                        </td>
+                                        <td>
+                                            <button class="delete" title="Remove frame" onclick={on_remove.reform({
+                                                let k = k.clone();
+                                                move |_| k.clone()
+                                            })}></button>
+                                        </td>
+                                    </tr>
+                                }
+                            }) }
+                        </tbody>
+                    </table>
+                    <div class="field is-grouped">
+                        <div class="control is-expanded">
+                            <input class="input is-small" type="text" list={DESCRIPTIONS_LIST}
+                                placeholder="Description" value={(*new_description).clone()}
+                                oninput={on_new_description} />
+                        </div>
+                        <div class="control is-expanded">
+                            <input class="input is-small" type="text"
+                                placeholder={user_text::hint(&new_description).unwrap_or("Value")}
+                                value={(*new_value).clone()} oninput={on_new_value} />
+                        </div>
+                        <div class="control">
+                            <button class="button is-small" disabled={exists} onclick={on_add_click}>
+                                {"Add TXXX"}
+                            </button>
+                        </div>
+                    </div>
+                    if exists {
+                        <p class="help">{"There is already a TXXX frame with this description."}</p>
+                    }
+                </div>
+            </div>
+        </div>
+    }
+}
+
+fn key(text: &ExtendedText) -> String {
+    frame::key(&Frame::from(text.clone()))
+}
@@ -0,0 +1,103 @@
+use id3::Version;
+use rid3_core::values;
+use web_sys::HtmlInputElement;
+use yew::prelude::*;
+
+#[derive(Properties, PartialEq)]
+pub struct ValueListProps {
+    pub values: Vec<String>,
+    /// The version the tag will be saved as.
+    pub version: Version,
+    pub on_change: Callback<Vec<String>>,
+}
+
+/// Edits the values of a multi-value text frame: change, add, remove and
+/// reorder them.
+#[function_component(ValueList)]
+pub fn value_list(
+    ValueListProps {
+        values,
+        version,
+        on_change,
+    }: &ValueListProps,
+) -> Html {
+    let on_edit = |f: fn(&mut Vec<String>, usize), i: usize| edit(values, on_change, f, i);
+    let joined = values::join(values);
+    let (_, exact) = values::join_for_v23(&joined);
+
+    html! {
+        <>
+            { for values.iter().enumerate().map(|(i, value)| {
+                let onchange = {
+                    let values = values.clone();
+                    on_change.reform(move |e: Event| {
+                        let input: HtmlInputElement = e.target_unchecked_into();
+                        let mut edited = values.clone();
+                        edited[i] = input.value();
+                        edited
+                    })
+                };
+                html! {
+                    <div class="field has-addons">
+                        <div class="control is-expanded">
+                            <input class="input is-small" type="text" value={value.clone()} {onchange} />
+                        </div>
+                        <div class="control">
+                            <button class="button is-small" title="Move up" disabled={i == 0}
+                                onclick={on_edit(|v, i| v.swap(i - 1, i), i)}>{"↑"}</button>
+                        </div>
+                        <div class="control">
+                            <button class="button is-small" title="Move down" disabled={i + 1 == values.len()}
+                                onclick={on_edit(|v, i| v.swap(i, i + 1), i)}>{"↓"}</button>
+                        </div>
+                        <div class="control">
+                            <button class="button is-small" title="Remove value" disabled={values.len() == 1}
+                                onclick={on_edit(|v, i| { v.remove(i); }, i)}>{"×"}</button>
+                        </div>
+                    </div>
+                }
+            }) }
+            <div class="buttons">
+                <button class="button is-small" onclick={on_edit(|v, _| v.push(String::new()), 0)}>{"Add value"}</button>
+                if let [single] = values.as_slice() {
+                    if let Some(split) = values::split_legacy(single) {
+                        <button class="button is-small" onclick={on_change.reform(move |_| split.clone())}>
+                            {"Split into values"}
+                        </button>
+                    }
+                }
+            </div>
+            if values.len() > 1 && *version != Version::Id3v24 {
+                if exact {
+                    <p class="help">
+                        { format!("Saved as {} these are joined with \"{}\".", version, values::V23_SEPARATOR) }
+                    </p>
+                } else {
+                    <p class="help is-danger">
+                        { format!(
+                            "Saved as {} these are joined with \"{}\", which a value already contains, so the list can't be told apart.",
+                            version,
+                            values::V23_SEPARATOR,
+                        ) }
+                    </p>
+                }
+            }
+        </>
+    }
+}
+
+/// A click handler applying `f` to a copy of the values, with the index of
+/// the value the button belongs to.
+fn edit(
+    values: &[String],
+    on_change: &Callback<Vec<String>>,
+    f: fn(&mut Vec<String>, usize),
+    i: usize,
+) -> Callback<MouseEvent> {
+    let values = values.to_vec();
+    on_change.reform(move |_: MouseEvent| {
+        let mut edited = values.clone();
+        f(&mut edited, i);
+        edited
+    })
+}
@@ -0,0 +1,35 @@
+use id3::Version;
+use rid3_core::version;
+use web_sys::HtmlSelectElement;
+use yew::prelude::*;
+
+#[derive(Properties, PartialEq)]
+pub struct VersionSelectProps {
+    pub version: Version,
+    pub on_change: Callback<Version>,
+}
+
+#[function_component(VersionSelect)]
+pub fn version_select(VersionSelectProps { version, on_change }: &VersionSelectProps) -> Html {
+    let onchange = {
+        let on_change = on_change.clone();
+        Callback::from(move |e: Event| {
+            let select: HtmlSelectElement = e.target_unchecked_into();
+            if let Some(version) = version::parse(&select.value()) {
+                on_change.emit(version);
+            }
+        })
+    };
+
+    html! {
+        <div class="select is-small">
+            <select {onchange}>
+                { for version::VERSIONS.iter().map(|v| html! {
+                    <option value={version::short_name(*v)} selected={v == version}>
+                        { v.to_string() }
+                    </option>
+                }) }
+            </select>
+        </div>
+    }
+}
@@ -0,0 +1,65 @@
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::engine::Engine as _;
+use id3::frame::Picture;
+use rid3_core::{art, filename, links};
+use web_sys::wasm_bindgen::JsCast;
+use web_sys::HtmlElement;
+
+use crate::error::AppError;
+
+/// The blob type for MP3 files.
+pub const MP3_TYPE: &str = "audio/mpeg3;audio/x-mpeg-3;video/mpeg;video/x-mpeg;text/xml";
+
+/// Creates an object URL for a blob of `mime_type` holding `bytes`.
+pub fn object_url(bytes: &[u8], mime_type: &str) -> Result<String, AppError> {
+    let uint8arr = js_sys::Uint8Array::new(&unsafe { js_sys::Uint8Array::view(bytes) }.into());
+    let array = js_sys::Array::new();
+    array.push(&uint8arr.buffer());
+
+    let bpb = web_sys::BlobPropertyBag::new();
+    bpb.set_type(mime_type);
+
+    let blob = web_sys::Blob::new_with_u8_array_sequence_and_options(&array, &bpb)?;
+    Ok(web_sys::Url::create_object_url_with_blob(&blob)?)
+}
+
+/// An `<img>` source for a picture: a data URL typed by the image's actual
+/// format, or the URL of a linked picture if it is a web address.
+pub fn picture_src(picture: &Picture) -> Option<String> {
+    match art::linked_url(picture) {
+        Some(url) => links::is_openable(&url).then_some(url),
+        None => Some(format!(
+            "data:{};base64,{}",
+            art::display_mime(picture),
+            BASE64.encode(&picture.data)
+        )),
+    }
+}
+
+/// Has the browser save a picture's image under the name of the MP3 called
+/// `mp3_name`, followed by `prefix` and the picture's type and description,
+/// e.g. `Song - Front cover.jpg`. Linked pictures have no image to save.
+pub fn download_picture(picture: &Picture, mp3_name: &str, prefix: &str) -> Result<(), AppError> {
+    let suffix = format!(" - {}{}", prefix, art::label(picture));
+    let file_name = filename::sibling(mp3_name, &suffix, art::extension(picture));
+    download(&picture.data, &file_name, art::display_mime(picture))
+}
+
+/// Has the browser save `bytes` as `file_name`.
+pub fn download(bytes: &[u8], file_name: &str, mime_type: &str) -> Result<(), AppError> {
+    let download_url = object_url(bytes, mime_type)?;
+    let window = web_sys::window().ok_or(AppError::Browser("window not available".into()))?;
+    let document = window
+        .document()
+        .ok_or(AppError::Browser("document not available".into()))?;
+    let element: HtmlElement = document.create_element("a")?.unchecked_into();
+    element.set_attribute("href", download_url.as_str())?;
+    element.set_attribute("download", file_name)?;
+    let body = document
+        .body()
+        .ok_or(AppError::Browser("document has no body".into()))?;
+    body.append_child(&element)?;
+    element.click();
+    body.remove_child(&element)?;
+    Ok(())
+}
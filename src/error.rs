@@ -0,0 +1,57 @@
+use std::fmt;
+
+use gloo_file::FileReadError;
+use web_sys::wasm_bindgen::JsValue;
+
+/// Everything that can go wrong while editing, kept in `AppState` so the
+/// `Popup` can show it. Messages are stored as text so the state stays `Clone`.
+#[derive(Clone, Debug, PartialEq)]
+pub enum AppError {
+    /// The browser could not read the file, or it contains no ID3 tag.
+    Read(String),
+    /// The ID3 tag is present but malformed.
+    Parse(String),
+    /// The new tag could not be written.
+    Write(String),
+    /// A browser API call failed.
+    Browser(String),
+    /// A picture could not be decoded or encoded.
+    Image(String),
+}
+
+impl fmt::Display for AppError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AppError::Read(message) => write!(f, "Could not read the file: {}", message),
+            AppError::Parse(message) => write!(f, "The ID3 tag is malformed: {}", message),
+            AppError::Write(message) => write!(f, "Could not save the file: {}", message),
+            AppError::Browser(message) => write!(f, "The browser reported an error: {}", message),
+            AppError::Image(message) => write!(f, "Could not process the picture: {}", message),
+        }
+    }
+}
+
+impl From<rid3_core::Error> for AppError {
+    fn from(err: rid3_core::Error) -> Self {
+        match err {
+            rid3_core::Error::Read(err) => AppError::Read(err.to_string()),
+            rid3_core::Error::Parse(err) => AppError::Parse(err.to_string()),
+            rid3_core::Error::Write(err) => AppError::Write(err.to_string()),
+            rid3_core::Error::Image(err) => AppError::Image(err.to_string()),
+            rid3_core::Error::Archive(err) => AppError::Write(err.to_string()),
+            rid3_core::Error::Chapters(message) => AppError::Read(message),
+        }
+    }
+}
+
+impl From<FileReadError> for AppError {
+    fn from(err: FileReadError) -> Self {
+        AppError::Read(err.to_string())
+    }
+}
+
+impl From<JsValue> for AppError {
+    fn from(value: JsValue) -> Self {
+        AppError::Browser(value.as_string().unwrap_or_else(|| format!("{:?}", value)))
+    }
+}
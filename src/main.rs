@@ -1,39 +1,272 @@
 use yew::prelude::*;
 
 mod components;
-use components::{FileLoader, ID3Tag, MP3Audio};
+use components::{
+    ChapterEditor, FileLoader, HistoryPanel, ID3Tag, LinkEditor, LyricsEditor, MP3Audio,
+    PictureManager, Popup, SyltEditor, TocEditor, UserTextEditor,
+};
+
+mod download;
+use download::{download, object_url, MP3_TYPE};
+
+mod error;
+use error::AppError;
 
 mod state;
 use state::{AppAction, AppState};
 
 use gloo::console::log;
-use gloo_file::File;
-use id3::Version;
-use std::io::Cursor;
-use web_sys::{Event, HtmlInputElement};
+use gloo::events::{EventListener, EventListenerOptions};
+use gloo_file::{File, FileList};
+use id3::Content;
+use rid3_core::chapter_files::{self, PodcastChapters};
+use rid3_core::diff::FrameChange;
+use rid3_core::history::History;
+use rid3_core::DEFAULT_VERSION;
+use rid3_core::{artwork, chapter_check, filename, links};
+use web_sys::wasm_bindgen::JsCast;
+use web_sys::{BeforeUnloadEvent, Event, HtmlElement, HtmlInputElement, KeyboardEvent};
 
 #[function_component]
 fn App() -> Html {
     let state = use_reducer(|| AppState {
         mp3: None,
-        tag: None,
+        file: None,
         frames: Vec::new(),
         reader_tasks: None,
         name: String::new(),
-        bytes: Vec::new(),
         url: String::new(),
+        version: DEFAULT_VERSION,
+        template: String::new(),
+        error: None,
+        history: History::default(),
+        original: None,
     });
 
     let seek_position = use_state(|| None);
+    let audio_ref = use_node_ref();
+    // The length of the audio in milliseconds, once the player knows it.
+    let duration = use_state(|| None::<u32>);
 
-    let on_title_change = {
+    let on_frame_change = {
         let state = state.clone();
-        Callback::from(move |e: Event| {
-            let state = state.clone();
-            let input: HtmlInputElement = e.target_unchecked_into();
-            let title = input.value();
-            let att = input.get_attribute("name").unwrap();
-            state.dispatch(AppAction::TitleChanged(att, title));
+        Callback::from(move |(key, content): (String, Content)| {
+            let action = match content {
+                Content::Text(text) => AppAction::SetText(key, text),
+                Content::Link(link) => AppAction::SetLink(key, link),
+                Content::Comment(comment) => AppAction::SetComment(key, comment),
+                Content::Lyrics(lyrics) => AppAction::SetLyrics(key, lyrics),
+                Content::ExtendedText(extended) => AppAction::SetExtendedText(key, extended),
+                Content::ExtendedLink(extended) => AppAction::SetExtendedLink(key, extended),
+                Content::Popularimeter(popm) => AppAction::SetPopularimeter(key, popm),
+                Content::Picture(picture) => AppAction::SetPicture(key, picture),
+                Content::Unknown(unknown) => AppAction::SetUnknown(key, unknown),
+                // The frame editor only edits the types above.
+                _ => return,
+            };
+            state.dispatch(action);
+        })
+    };
+
+    let on_add_frame = {
+        let state = state.clone();
+        Callback::from(move |id| {
+            state.dispatch(AppAction::AddFrame(id));
+        })
+    };
+
+    let on_remove_frame = {
+        let state = state.clone();
+        Callback::from(move |key| {
+            state.dispatch(AppAction::RemoveFrame(key));
+        })
+    };
+
+    let on_lyrics_change = {
+        let state = state.clone();
+        Callback::from(move |(key, lyrics)| {
+            state.dispatch(AppAction::SetLyrics(key, lyrics));
+        })
+    };
+
+    let on_add_lyrics = {
+        let state = state.clone();
+        Callback::from(move |lyrics| {
+            state.dispatch(AppAction::AddLyrics(lyrics));
+        })
+    };
+
+    let on_extended_text_change = {
+        let state = state.clone();
+        Callback::from(move |(key, extended)| {
+            state.dispatch(AppAction::SetExtendedText(key, extended));
+        })
+    };
+
+    let on_add_extended_text = {
+        let state = state.clone();
+        Callback::from(move |extended| {
+            state.dispatch(AppAction::AddExtendedText(extended));
+        })
+    };
+
+    let on_add_link = {
+        let state = state.clone();
+        Callback::from(move |(id, link)| {
+            state.dispatch(AppAction::AddLink(id, link));
+        })
+    };
+
+    let on_add_extended_link = {
+        let state = state.clone();
+        Callback::from(move |extended| {
+            state.dispatch(AppAction::AddExtendedLink(extended));
+        })
+    };
+
+    let on_picture_change = {
+        let state = state.clone();
+        Callback::from(move |(key, picture)| {
+            state.dispatch(AppAction::SetPicture(key, picture));
+        })
+    };
+
+    let on_add_picture = {
+        let state = state.clone();
+        Callback::from(move |picture| {
+            state.dispatch(AppAction::AddPicture(picture));
+        })
+    };
+
+    let on_sylt_change = {
+        let state = state.clone();
+        Callback::from(move |(key, sylt)| {
+            state.dispatch(AppAction::SetSynchronisedLyrics(key, sylt));
+        })
+    };
+
+    let on_add_sylt = {
+        let state = state.clone();
+        Callback::from(move |sylt| {
+            state.dispatch(AppAction::AddSynchronisedLyrics(sylt));
+        })
+    };
+
+    let on_chapter_change = {
+        let state = state.clone();
+        Callback::from(move |(index, chapter)| {
+            state.dispatch(AppAction::SetChapter(index, chapter));
+        })
+    };
+
+    let on_add_chapter = {
+        let state = state.clone();
+        Callback::from(move |(start_time, end_time, title)| {
+            state.dispatch(AppAction::AddChapter(start_time, end_time, title));
+        })
+    };
+
+    let on_mark_chapter = {
+        let state = state.clone();
+        Callback::from(move |(at, duration)| {
+            state.dispatch(AppAction::MarkChapter(at, duration));
+        })
+    };
+
+    let on_nudge_chapter = {
+        let state = state.clone();
+        Callback::from(move |(index, delta_ms)| {
+            state.dispatch(AppAction::NudgeChapter(index, delta_ms));
+        })
+    };
+
+    let on_remove_chapter = {
+        let state = state.clone();
+        Callback::from(move |index| {
+            state.dispatch(AppAction::RemoveChapter(index));
+        })
+    };
+
+    let on_toc_change = {
+        let state = state.clone();
+        Callback::from(move |table| {
+            state.dispatch(AppAction::SetTableOfContents(table));
+        })
+    };
+
+    let on_add_toc = {
+        let state = state.clone();
+        Callback::from(move |(parent, title)| {
+            state.dispatch(AppAction::AddTableOfContents(parent, title));
+        })
+    };
+
+    let on_remove_toc = {
+        let state = state.clone();
+        Callback::from(move |element_id| {
+            state.dispatch(AppAction::RemoveTableOfContents(element_id));
+        })
+    };
+
+    let on_move_element = {
+        let state = state.clone();
+        Callback::from(move |(element_id, to)| {
+            state.dispatch(AppAction::MoveElement(element_id, to));
+        })
+    };
+
+    let on_sync_toc = {
+        let state = state.clone();
+        Callback::from(move |_: MouseEvent| {
+            state.dispatch(AppAction::SyncTablesOfContents);
+        })
+    };
+
+    let on_fix_chapters = {
+        let state = state.clone();
+        let duration = *duration;
+        Callback::from(move |fix| {
+            state.dispatch(AppAction::FixChapters(fix, duration));
+        })
+    };
+
+    let on_import_chapters = {
+        let state = state.clone();
+        let duration = *duration;
+        Callback::from(move |chapters| {
+            state.dispatch(AppAction::ImportChapters(chapters, duration));
+        })
+    };
+
+    let export_chapters = |extension: &'static str,
+                           mime_type: &'static str,
+                           write: fn(&PodcastChapters) -> String| {
+        let state = state.clone();
+        Callback::from(move |_: MouseEvent| {
+            let Some(file) = state.file.as_ref() else {
+                return;
+            };
+            let file_name = filename::sibling(&state.download_name(), " - Chapters", extension);
+            let text = write(&chapter_files::export(&file.tag));
+            if let Err(err) = download(text.as_bytes(), &file_name, mime_type) {
+                state.dispatch(AppAction::Failed(err));
+            }
+        })
+    };
+    let on_export_json = export_chapters("json", chapter_files::JSON_MIME, chapter_files::to_json);
+    let on_export_podlove = export_chapters("xml", "application/xml", chapter_files::to_podlove);
+
+    let on_duration = {
+        let duration = duration.clone();
+        Callback::from(move |seconds: f64| {
+            duration.set(Some((seconds * 1000.0).round() as u32));
+        })
+    };
+
+    let on_error = {
+        let state = state.clone();
+        Callback::from(move |err| {
+            state.dispatch(AppAction::Failed(err));
         })
     };
 
@@ -41,15 +274,10 @@ fn App() -> Html {
         let state = state.clone();
         Callback::from(move |e: Event| {
             let state = state.clone();
-            let mut selected_files = Vec::new();
+            let mut selected_files: Vec<File> = Vec::new();
             let input: HtmlInputElement = e.target_unchecked_into();
             if let Some(files) = input.files() {
-                let files = js_sys::try_iter(&files)
-                    .unwrap()
-                    .unwrap()
-                    .map(|v| web_sys::File::from(v.unwrap()))
-                    .map(File::from);
-                selected_files.extend(files);
+                selected_files.extend(FileList::from(files).iter().cloned());
             }
 
             for sf in selected_files {
@@ -58,10 +286,12 @@ fn App() -> Html {
                     let state = state.clone();
                     let sd = state.clone();
                     let file_name = sf.name();
-                    let task = gloo_file::callbacks::read_as_bytes(&sf, move |bytes| {
-                        let contents = bytes.unwrap();
-                        state.dispatch(AppAction::MP3Ready(contents));
-                        state.dispatch(AppAction::SetFileName(file_name.clone()));
+                    let task = gloo_file::callbacks::read_as_bytes(&sf, move |bytes| match bytes {
+                        Ok(contents) => {
+                            state.dispatch(AppAction::MP3Ready(contents));
+                            state.dispatch(AppAction::SetFileName(file_name.clone()));
+                        }
+                        Err(err) => state.dispatch(AppAction::Failed(err.into())),
                     });
 
                     sd.dispatch(AppAction::AddReader(task));
@@ -74,87 +304,188 @@ fn App() -> Html {
         let state = state.clone();
         Callback::from(move |_: MouseEvent| {
             log!("save clicked");
-            let tag = state.tag.clone().unwrap();
-            log!(format!("1 {:?}", tag));
-
-            let mut b = state.bytes.clone();
-            log!(format!("2 {:?}", b.len()));
+            let Some(file) = state.file.as_ref() else {
+                return;
+            };
+            log!(format!("1 {:?}", file.tag));
 
-            let curs = Cursor::new(&mut b);
-
-            // find og tag
-            // let location = id3::stream::tag::locate_id3v2(curs);
-            // log!(format!("3 {:?}", location));
+            let result = file
+                .to_bytes(state.version)
+                .map_err(AppError::from)
+                .and_then(|bytes| {
+                    log!(format!("3 {:?}", bytes.len()));
+                    download(&bytes, &state.download_name(), MP3_TYPE)
+                });
+            match result {
+                Ok(()) => state.dispatch(AppAction::Saved),
+                Err(err) => state.dispatch(AppAction::Failed(err)),
+            }
+        })
+    };
 
-            tag.write_to(curs, Version::Id3v23).unwrap();
+    let on_download_artwork = {
+        let state = state.clone();
+        Callback::from(move |_: MouseEvent| {
+            let Some(file) = state.file.as_ref() else {
+                return;
+            };
+            let file_name = filename::sibling(&state.download_name(), " - Artwork", "zip");
+            let result = artwork::zip(&file.tag)
+                .map_err(AppError::from)
+                .and_then(|bytes| download(&bytes, &file_name, "application/zip"));
+            if let Err(err) = result {
+                state.dispatch(AppAction::Failed(err));
+            }
+        })
+    };
 
-            // tag.write_to(&mut b, Version::Id3v23).unwrap();
-            let bytes = b.as_slice();
-            log!(format!("3 {:?}", bytes.len()));
+    let clear_clicked = {
+        let state = state.clone();
+        Callback::from(move |_: MouseEvent| {
+            log!("clear clicked");
+            if state.has_unsaved_changes()
+                && !gloo::dialogs::confirm("Discard the unsaved changes to this file?")
+            {
+                return;
+            }
+            state.dispatch(AppAction::ClearClicked);
+        })
+    };
 
-            let uint8arr =
-                js_sys::Uint8Array::new(&unsafe { js_sys::Uint8Array::view(bytes) }.into());
-            log!(format!("4 {:?}", uint8arr.length()));
-            let array = js_sys::Array::new();
-            array.push(&uint8arr.buffer());
-            log!(format!("5 {:?}", array));
+    let on_version_change = {
+        let state = state.clone();
+        Callback::from(move |version| {
+            state.dispatch(AppAction::SetVersion(version));
+        })
+    };
 
-            let bpb = web_sys::BlobPropertyBag::new();
-            bpb.set_type("audio/mpeg3;audio/x-mpeg-3;video/mpeg;video/x-mpeg;text/xml");
+    let on_keep_trailer = {
+        let state = state.clone();
+        Callback::from(move |(trailer, keep)| {
+            state.dispatch(AppAction::SetKeepTrailer(trailer, keep));
+        })
+    };
 
-            let blob = web_sys::Blob::new_with_u8_array_sequence_and_options(&array, &bpb).unwrap();
-            let download_url = web_sys::Url::create_object_url_with_blob(&blob).unwrap(); // Zero bytes
+    let on_template_change = {
+        let state = state.clone();
+        Callback::from(move |template| {
+            state.dispatch(AppAction::SetTemplate(template));
+        })
+    };
 
-            log!(format!("{:?}", download_url));
-            // change_location(download_url.as_str());
+    let dismiss_error = {
+        let state = state.clone();
+        Callback::from(move |_: MouseEvent| {
+            state.dispatch(AppAction::DismissError);
+        })
+    };
 
-            let window: web_sys::Window = web_sys::window().expect("window not available");
-            let element = window.document().unwrap().create_element("a").unwrap();
-            element
-                .set_attribute("href", download_url.as_str())
-                .unwrap();
-            element.set_attribute("download", "test.mp3").unwrap();
-            window
-                .document()
-                .unwrap()
-                .body()
-                .unwrap()
-                .append_child(&element)
-                .unwrap();
-            // element.;
-            // window
-            //     .location()
-            //     .set_href(download_url.as_str())
-            //     .expect("location change failed");
+    let on_undo = {
+        let state = state.clone();
+        Callback::from(move |_: MouseEvent| {
+            state.dispatch(AppAction::Undo);
         })
     };
 
-    let clear_clicked = {
+    let on_redo = {
         let state = state.clone();
         Callback::from(move |_: MouseEvent| {
-            log!("clear clicked");
-            state.dispatch(AppAction::ClearClicked);
+            state.dispatch(AppAction::Redo);
         })
     };
 
-    let mut blob_url: Option<String> = None;
+    // Ctrl+Z / Ctrl+Shift+Z, except in text fields, which keep their own undo.
+    {
+        let dispatcher = state.dispatcher();
+        use_effect_with((), move |_| {
+            let listener = EventListener::new(&gloo::utils::window(), "keydown", move |e| {
+                let Some(e) = e.dyn_ref::<KeyboardEvent>() else {
+                    return;
+                };
+                if !(e.ctrl_key() || e.meta_key()) || !e.key().eq_ignore_ascii_case("z") {
+                    return;
+                }
+                if e.target()
+                    .and_then(|t| t.dyn_into::<HtmlElement>().ok())
+                    .is_some_and(|t| is_text_field(&t))
+                {
+                    return;
+                }
+                e.prevent_default();
+                dispatcher.dispatch(if e.shift_key() {
+                    AppAction::Redo
+                } else {
+                    AppAction::Undo
+                });
+            });
+            move || drop(listener)
+        });
+    }
 
-    // create a blob of the mp3 file bytes
-    if state.bytes.len() > 0 {
-        let uint8arr = js_sys::Uint8Array::new(
-            &unsafe { js_sys::Uint8Array::view(&state.bytes.clone()) }.into(),
-        );
-        let array = js_sys::Array::new();
-        array.push(&uint8arr.buffer());
+    // Ask before leaving the page while there are unsaved edits.
+    use_effect_with(state.has_unsaved_changes(), |unsaved| {
+        let listener = unsaved.then(|| {
+            EventListener::new_with_options(
+                &gloo::utils::window(),
+                "beforeunload",
+                EventListenerOptions::enable_prevent_default(),
+                |e| {
+                    e.prevent_default();
+                    if let Some(e) = e.dyn_ref::<BeforeUnloadEvent>() {
+                        e.set_return_value("You have unsaved changes.");
+                    }
+                },
+            )
+        });
+        move || drop(listener)
+    });
 
-        let bpb = web_sys::BlobPropertyBag::new();
-        bpb.set_type("audio/mpeg3;audio/x-mpeg-3;video/mpeg;video/x-mpeg;text/xml");
-        let blob = web_sys::Blob::new_with_u8_array_sequence_and_options(&array, &bpb).unwrap();
-        let download_url = web_sys::Url::create_object_url_with_blob(&blob).unwrap();
-        // Zero bytes
-        log!(format!("{:?}", download_url));
-        blob_url = Some(download_url);
+    // The player's source: a blob of the MP3's bytes, made once per loaded
+    // file. Edits and saves share the bytes, so they keep the URL and don't
+    // reload the player.
+    let blob_url = {
+        let bytes = state
+            .file
+            .as_ref()
+            .map(|f| (f.bytes().as_ptr() as usize, f.bytes().len()));
+        let file = state.file.clone();
+        use_memo(bytes, move |_| {
+            let file = file?;
+            match object_url(file.bytes(), MP3_TYPE) {
+                Ok(download_url) => {
+                    log!(format!("{:?}", download_url));
+                    Some(download_url)
+                }
+                Err(err) => {
+                    log!(format!("{}", err));
+                    None
+                }
+            }
+        })
     };
+    use_effect_with(blob_url.clone(), |url| {
+        let url = (**url).clone();
+        move || {
+            if let Some(url) = url {
+                let _ = web_sys::Url::revoke_object_url(&url);
+            }
+        }
+    });
+
+    // Keys of the frames edited since the tag was loaded or last saved.
+    let changed: Vec<String> = state.changes().iter().map(FrameChange::key).collect();
+
+    let trailers: Vec<_> = state
+        .file
+        .as_ref()
+        .map(|f| {
+            f.layout()
+                .trailers()
+                .into_iter()
+                .map(|t| (t, f.keeps(t)))
+                .collect()
+        })
+        .unwrap_or_default();
 
     let on_seek = {
         let seek_position = seek_position.clone();
@@ -166,6 +497,9 @@ fn App() -> Html {
     html! {
         <>
             <div class="container">
+                if let Some(error) = &state.error {
+                    <Popup message={error.to_string()} on_close={dismiss_error} />
+                }
                 <div class="card">
                     <header class="card-header">
                         <p class="card-header-title">{"Upload File"}</p>
@@ -179,21 +513,119 @@ fn App() -> Html {
                 </div>
             </div>
 
-            if blob_url.is_some() {
+            if let Some(url) = (*blob_url).clone() {
                 <MP3Audio
-                    url={blob_url.unwrap()}
+                    url={url}
                     seek_position={seek_position}
                     file_name={state.name.clone()}
+                    audio_ref={audio_ref.clone()}
+                    chapters={state.file.as_ref().map(|f| f.tag.chapters().cloned().collect::<Vec<_>>()).unwrap_or_default()}
+                    on_mark={on_mark_chapter}
+                    on_chapter_change={on_chapter_change.clone()}
+                    on_duration={on_duration}
                 />
                 // <a href={blob_url.clone().unwrap()} download="test.mp3">{"Download"}</a>
-
-                <ID3Tag tag={state.tag.clone()} on_value_change={on_title_change} save_clicked={save_clicked} clear_clicked={clear_clicked} on_seek_position_change={on_seek}/>
+            }
+            if let Some(file) = &state.file {
+                <ID3Tag tag={Some(file.tag.clone())} source={file.source()} edits={state.changes()} trailer_edits={state.trailer_changes()} version={state.version} on_version_change={on_version_change} trailers={trailers} on_keep_trailer={on_keep_trailer} template={state.template.clone()} download_name={state.download_name()} on_template_change={on_template_change} on_frame_change={on_frame_change.clone()} on_add_frame={on_add_frame} on_remove_frame={on_remove_frame.clone()} save_clicked={save_clicked} clear_clicked={clear_clicked}/>
+                <ChapterEditor
+                    chapters={file.tag.chapters().cloned().collect::<Vec<_>>()}
+                    problems={chapter_check::check(&file.tag, *duration)}
+                    changed={changed.clone()}
+                    audio_ref={audio_ref.clone()}
+                    download_name={state.download_name()}
+                    on_change={on_chapter_change}
+                    on_nudge={on_nudge_chapter}
+                    on_fix={on_fix_chapters}
+                    on_import={on_import_chapters}
+                    on_export_json={on_export_json}
+                    on_export_podlove={on_export_podlove}
+                    on_add={on_add_chapter}
+                    on_remove={on_remove_chapter}
+                    on_seek={on_seek.clone()}
+                    on_error={on_error.clone()}
+                />
+                <TocEditor
+                    tables={file.tag.tables_of_contents().cloned().collect::<Vec<_>>()}
+                    chapters={file.tag.chapters().cloned().collect::<Vec<_>>()}
+                    changed={changed.clone()}
+                    on_change={on_toc_change}
+                    on_add={on_add_toc}
+                    on_remove={on_remove_toc}
+                    on_move={on_move_element}
+                    on_sync={on_sync_toc}
+                />
+                <PictureManager
+                    pictures={file.tag.pictures().cloned().collect::<Vec<_>>()}
+                    changed={changed.clone()}
+                    download_name={state.download_name()}
+                    artwork={artwork::exports(&file.tag).len()}
+                    on_download_all={on_download_artwork}
+                    on_change={on_picture_change}
+                    on_add={on_add_picture}
+                    on_remove={on_remove_frame.clone()}
+                    on_error={on_error.clone()}
+                />
+                <UserTextEditor
+                    texts={file.tag.extended_texts().cloned().collect::<Vec<_>>()}
+                    changed={changed.clone()}
+                    on_change={on_extended_text_change}
+                    on_add={on_add_extended_text}
+                    on_remove={on_remove_frame.clone()}
+                />
+                <LinkEditor
+                    frames={file.tag.frames().filter(|f| links::is_url_frame(f.id())).cloned().collect::<Vec<_>>()}
+                    changed={changed.clone()}
+                    on_change={on_frame_change.clone()}
+                    on_add={on_add_link}
+                    on_add_extended={on_add_extended_link}
+                    on_remove={on_remove_frame.clone()}
+                />
+                <LyricsEditor
+                    lyrics={file.tag.lyrics().cloned().collect::<Vec<_>>()}
+                    download_name={state.download_name()}
+                    on_change={on_lyrics_change}
+                    on_add={on_add_lyrics}
+                    on_remove={on_remove_frame.clone()}
+                    on_error={on_error.clone()}
+                />
+                <SyltEditor
+                    sylt={file.tag.synchronised_lyrics().cloned().collect::<Vec<_>>()}
+                    frame_ms={file.frame_header().map(|h| h.frame_ms())}
+                    audio_ref={audio_ref}
+                    download_name={state.download_name()}
+                    on_change={on_sylt_change}
+                    on_add={on_add_sylt}
+                    on_remove={on_remove_frame}
+                    on_seek={on_seek}
+                    on_error={on_error}
+                />
                 <div>{ state.url.clone() }</div>
             }
+            if state.file.is_some() || state.history.can_undo() {
+                <HistoryPanel
+                    undo={state.history.undo_labels().map(String::from).collect::<Vec<_>>()}
+                    redo={state.history.redo_labels().map(String::from).collect::<Vec<_>>()}
+                    on_undo={on_undo}
+                    on_redo={on_redo}
+                />
+            }
         </>
     }
 }
 
+/// Whether `element` takes typed text, so keys like Ctrl+Z belong to it.
+fn is_text_field(element: &HtmlElement) -> bool {
+    match element.tag_name().as_str() {
+        "TEXTAREA" => true,
+        "INPUT" => !matches!(
+            element.get_attribute("type").as_deref(),
+            Some("checkbox" | "radio" | "button" | "file")
+        ),
+        _ => element.is_content_editable(),
+    }
+}
+
 fn _change_location(url: &str) {
     let window: web_sys::Window = web_sys::window().expect("window not available");
     window
@@ -1,28 +1,231 @@
 use gloo::console::log;
 use gloo_file::{callbacks::FileReader, File};
-use id3::{Frame, Tag, TagLike};
-use std::io::Cursor;
+use id3::frame::{
+    Chapter, Comment, ExtendedLink, ExtendedText, Lyrics, Picture, Popularimeter,
+    SynchronisedLyrics, TableOfContents, Unknown,
+};
+use id3::{Content, Frame, Version};
+use rid3_core::chapter_check::{self, Fix};
+use rid3_core::chapter_files::{self, PodcastChapters};
+use rid3_core::diff::{diff, FrameChange};
+use rid3_core::history::History;
+use rid3_core::layout::Trailer;
+use rid3_core::{art, catalog, chapters, filename, frame, toc, Mp3File, DEFAULT_VERSION};
 use std::rc::Rc;
 use yew::prelude::*;
 
+use crate::error::AppError;
+
 #[derive(Clone, Debug)]
 pub struct AppState {
     pub mp3: Option<File>,
-    pub tag: Option<Tag>,
+    pub file: Option<Rc<Mp3File>>,
     pub frames: Vec<Frame>,
     pub reader_tasks: Option<Rc<FileReader>>,
     pub name: String,
-    pub bytes: Vec<u8>,
     pub url: String,
+    pub version: Version,
+    pub template: String,
+    pub error: Option<AppError>,
+    /// Earlier versions of `file`, for undo and redo.
+    pub history: History<Option<Rc<Mp3File>>>,
+    /// The file as it was loaded or last saved.
+    pub original: Option<Rc<Mp3File>>,
+}
+
+impl AppState {
+    /// The name for the saved file: the filled-in template, or the name of
+    /// the uploaded file when there is no template.
+    pub fn download_name(&self) -> String {
+        match &self.file {
+            Some(file) if !self.template.trim().is_empty() => {
+                filename::render(&self.template, &file.tag)
+            }
+            _ if !self.name.is_empty() => self.name.clone(),
+            _ => filename::sanitize(""),
+        }
+    }
+
+    /// The frames that differ from the tag as it was loaded or last saved.
+    pub fn changes(&self) -> Vec<FrameChange> {
+        match (&self.original, &self.file) {
+            (Some(original), Some(file)) => diff(&original.tag, &file.tag),
+            _ => Vec::new(),
+        }
+    }
+
+    /// The trailing tags that are kept or removed on save unlike when the
+    /// file was loaded or last saved, with whether they are now kept.
+    pub fn trailer_changes(&self) -> Vec<(Trailer, bool)> {
+        match (&self.original, &self.file) {
+            (Some(original), Some(file)) => file
+                .layout()
+                .trailers()
+                .into_iter()
+                .filter(|t| original.keeps(*t) != file.keeps(*t))
+                .map(|t| (t, file.keeps(t)))
+                .collect(),
+            _ => Vec::new(),
+        }
+    }
+
+    /// Whether the loaded file has edits that were not saved.
+    pub fn has_unsaved_changes(&self) -> bool {
+        match (&self.original, &self.file) {
+            (Some(original), Some(file)) => {
+                original.tag != file.tag || !self.trailer_changes().is_empty()
+            }
+            _ => false,
+        }
+    }
+
+    /// Applies `edit` to a copy of the loaded file, recording the file as it
+    /// was under `label` so the edit can be undone. Edits that change
+    /// nothing are not recorded.
+    fn edit(self: Rc<Self>, label: impl Into<String>, edit: impl FnOnce(&mut Mp3File)) -> Rc<Self> {
+        let Some(file) = self.file.clone() else {
+            return self;
+        };
+        let mut edited = (*file).clone();
+        edit(&mut edited);
+        if edited == *file {
+            return self;
+        }
+        let mut history = self.history.clone();
+        history.record(label, Some(file));
+        self.restore(Some(Rc::new(edited)), history)
+    }
+
+    /// The element ID of the chapter at `index` in tag order, for history labels.
+    fn chapter_id(&self, index: usize) -> String {
+        self.file
+            .as_ref()
+            .and_then(|f| f.tag.chapters().nth(index))
+            .map(|c| c.element_id.clone())
+            .unwrap_or_default()
+    }
+
+    /// [`Self::edit`] for chapters and tables of contents, which brings the
+    /// tables up to date afterwards, see [`toc::sync`].
+    fn edit_chapters(
+        self: Rc<Self>,
+        label: impl Into<String>,
+        edit: impl FnOnce(&mut Mp3File),
+    ) -> Rc<Self> {
+        self.edit(label, |f| {
+            // Chapters hidden before the edit were left out on purpose.
+            let hidden = toc::hidden(&f.tag);
+            edit(f);
+            toc::sync_except(&mut f.tag, &hidden);
+        })
+    }
+
+    /// Replaces the content of the frame with `key`, see [`frame::set_content`].
+    fn set_content(self: Rc<Self>, key: String, content: Content) -> Rc<Self> {
+        self.edit(format!("Changed {}", key), |f| {
+            frame::set_content(&mut f.tag, &key, content);
+        })
+    }
+
+    /// The same state with `file` and `history` swapped in.
+    fn restore(
+        self: Rc<Self>,
+        file: Option<Rc<Mp3File>>,
+        history: History<Option<Rc<Mp3File>>>,
+    ) -> Rc<Self> {
+        Rc::new(AppState {
+            mp3: self.mp3.clone(),
+            file,
+            frames: self.frames.clone(),
+            reader_tasks: self.reader_tasks.clone(),
+            name: self.name.clone(),
+            url: self.url.clone(),
+            version: self.version,
+            template: self.template.clone(),
+            error: self.error.clone(),
+            history,
+            original: self.original.clone(),
+        })
+    }
 }
 
 pub enum AppAction {
     MP3Ready(Vec<u8>),
     AddReader(FileReader),
-    TitleChanged(String, String),
+    // Edits of a frame's content, each carrying the frame's key (see
+    // [`frame::key`]). A frame whose content is of another type is left alone.
+    SetText(String, String),
+    SetLink(String, String),
+    SetComment(String, Comment),
+    SetLyrics(String, Lyrics),
+    SetSynchronisedLyrics(String, SynchronisedLyrics),
+    SetExtendedText(String, ExtendedText),
+    SetExtendedLink(String, ExtendedLink),
+    SetPopularimeter(String, Popularimeter),
+    SetPicture(String, Picture),
+    SetUnknown(String, Unknown),
+    /// Adds an empty frame with this ID.
+    AddFrame(String),
+    /// Adds a `USLT` frame unless one with the same language and description exists.
+    AddLyrics(Lyrics),
+    /// Adds a `TXXX` frame unless one with the same description exists.
+    AddExtendedText(ExtendedText),
+    /// Adds a URL link frame with this ID and URL, unless the tag has one with
+    /// the same key, see [`frame::key`].
+    AddLink(String, String),
+    /// Adds a `WXXX` frame unless one with the same description exists.
+    AddExtendedLink(ExtendedLink),
+    /// Adds an `APIC` frame unless it clashes with a picture in the tag,
+    /// see [`art::conflict`].
+    AddPicture(Picture),
+    /// Adds a `SYLT` frame unless one with the same language and description exists.
+    AddSynchronisedLyrics(SynchronisedLyrics),
+    /// Removes the frame with this key, see [`frame::key`].
+    RemoveFrame(String),
+    /// Replaces the chapter at this index in tag order. Chapters are told
+    /// apart by position, as their element IDs may repeat.
+    SetChapter(usize, Chapter),
+    /// Adds a chapter with a new element ID, start and end time in
+    /// milliseconds, and title.
+    AddChapter(u32, u32, String),
+    /// Removes the chapter at this index in tag order.
+    RemoveChapter(usize),
+    /// Starts a chapter at this many milliseconds into a file of the given
+    /// length, see [`chapters::mark_chapter`].
+    MarkChapter(u32, u32),
+    /// Moves the start of the chapter at this index in tag order by this
+    /// many milliseconds, see [`chapters::nudge_chapter`].
+    NudgeChapter(usize, i64),
+    /// Replaces the `CTOC` frame with the same element ID.
+    SetTableOfContents(TableOfContents),
+    /// Adds a table of contents with this title below the one with this element ID.
+    AddTableOfContents(String, String),
+    /// Removes the table of contents with this element ID.
+    RemoveTableOfContents(String),
+    /// Moves the chapter or table with the first element ID into the table
+    /// of contents with the second.
+    MoveElement(String, String),
+    /// Creates a top-level table of contents listing every chapter if the
+    /// tag has chapters but no tables, see [`toc::sync`].
+    SyncTablesOfContents,
+    /// Applies a fix for chapter problems to a file whose audio is this many
+    /// milliseconds long, if known. See [`chapter_check::apply`].
+    FixChapters(Fix, Option<u32>),
+    /// Replaces the chapters and tables of contents with those read from a
+    /// chapters file, for audio this many milliseconds long, if known.
+    ImportChapters(PodcastChapters, Option<u32>),
     // URLCreated(String),
     ClearClicked,
     SetFileName(String),
+    SetVersion(Version),
+    SetKeepTrailer(Trailer, bool),
+    SetTemplate(String),
+    Undo,
+    Redo,
+    /// The edited tag was saved; it becomes the one changes are shown against.
+    Saved,
+    Failed(AppError),
+    DismissError,
 }
 
 impl Reducible for AppState {
@@ -34,19 +237,40 @@ impl Reducible for AppState {
                 log!("add reader");
                 std::rc::Rc::new(AppState {
                     mp3: self.mp3.clone(),
-                    tag: self.tag.clone(),
+                    file: self.file.clone(),
                     frames: self.frames.clone(),
                     reader_tasks: Some(Rc::new(reader)),
                     name: self.name.clone(),
-                    bytes: self.bytes.clone(),
                     url: self.url.clone(),
+                    version: self.version,
+                    template: self.template.clone(),
+                    error: self.error.clone(),
+                    history: self.history.clone(),
+                    original: self.original.clone(),
                 })
             }
             AppAction::MP3Ready(contents) => {
                 log!("mp3 ready");
                 log!(format!("{:?}", contents.len()).as_str());
-                let data = Cursor::new(contents.as_slice());
-                let tag = id3::Tag::read_from2(data).unwrap();
+                let file = match Mp3File::from_bytes(contents) {
+                    Ok(file) => file,
+                    Err(err) => {
+                        log!(format!("{}", err).as_str());
+                        return std::rc::Rc::new(AppState {
+                            mp3: None,
+                            file: None,
+                            frames: Vec::new(),
+                            reader_tasks: None,
+                            name: String::new(),
+                            url: String::new(),
+                            version: DEFAULT_VERSION,
+                            template: self.template.clone(),
+                            error: Some(err.into()),
+                            history: History::default(),
+                            original: None,
+                        });
+                    }
+                };
                 // log!(format!("{:?}", tag.version()).as_str());
 
                 // for chapter in tag.chapters() {
@@ -59,62 +283,279 @@ impl Reducible for AppState {
                 //     }
                 // }
 
+                let version = file.source_version();
+                let file = Rc::new(file);
                 std::rc::Rc::new(AppState {
                     mp3: self.mp3.clone(),
-                    tag: Some(tag),
+                    file: Some(file.clone()),
                     frames: self.frames.clone(),
                     reader_tasks: self.reader_tasks.clone(),
                     name: self.name.clone(),
-                    bytes: contents,
                     url: self.url.clone(),
+                    version,
+                    template: self.template.clone(),
+                    error: None,
+                    history: History::default(),
+                    original: Some(file),
                 })
             }
-            AppAction::TitleChanged(att, title) => {
-                log!("title changed");
-                let mut t = self.tag.clone().unwrap();
-                // t.set_album(title.clone());
-                t.set_text(att.as_str(), title.clone());
-                // t.add_frame(Frame::with_content("TALB", Content::Text(title.clone())));
-                log!(format!("{:?}", t).as_str());
-                std::rc::Rc::new(AppState {
-                    mp3: self.mp3.clone(),
-                    tag: Some(t),
-                    frames: self.frames.clone(),
-                    reader_tasks: self.reader_tasks.clone(),
-                    name: title,
-                    bytes: self.bytes.clone(),
-                    url: self.url.clone(),
+            AppAction::SetText(key, text) => self.set_content(key, Content::Text(text)),
+            AppAction::SetLink(key, link) => self.set_content(key, Content::Link(link)),
+            AppAction::SetComment(key, comment) => self.set_content(key, Content::Comment(comment)),
+            AppAction::SetLyrics(key, lyrics) => self.set_content(key, Content::Lyrics(lyrics)),
+            AppAction::SetSynchronisedLyrics(key, sylt) => {
+                self.set_content(key, Content::SynchronisedLyrics(sylt))
+            }
+            AppAction::SetExtendedText(key, extended) => {
+                self.set_content(key, Content::ExtendedText(extended))
+            }
+            AppAction::SetExtendedLink(key, extended) => {
+                self.set_content(key, Content::ExtendedLink(extended))
+            }
+            AppAction::SetPopularimeter(key, popm) => {
+                self.set_content(key, Content::Popularimeter(popm))
+            }
+            AppAction::SetPicture(key, picture) => self.set_content(key, Content::Picture(picture)),
+            AppAction::SetUnknown(key, unknown) => self.set_content(key, Content::Unknown(unknown)),
+            AppAction::AddFrame(id) => {
+                let Some(new) = catalog::new_frame(&id) else {
+                    return self;
+                };
+                self.edit(format!("Added {}", id), |f| {
+                    frame::add(&mut f.tag, new);
+                })
+            }
+            AppAction::AddLyrics(lyrics) => self.edit("Added USLT", |f| {
+                frame::add(&mut f.tag, Frame::from(lyrics));
+            }),
+            AppAction::AddExtendedText(extended) => self.edit("Added TXXX", |f| {
+                frame::add(&mut f.tag, Frame::from(extended));
+            }),
+            AppAction::AddLink(id, link) => self.edit(format!("Added {}", id), |f| {
+                frame::add(&mut f.tag, Frame::link(id, link));
+            }),
+            AppAction::AddExtendedLink(extended) => self.edit("Added WXXX", |f| {
+                frame::add(&mut f.tag, Frame::from(extended));
+            }),
+            AppAction::AddPicture(picture) => {
+                let Some(file) = &self.file else {
+                    return self;
+                };
+                if art::conflict(
+                    file.tag.pictures(),
+                    picture.picture_type,
+                    &picture.description,
+                )
+                .is_some()
+                {
+                    return self;
+                }
+                self.edit("Added APIC", |f| {
+                    frame::add(&mut f.tag, Frame::from(picture));
+                })
+            }
+            AppAction::AddSynchronisedLyrics(sylt) => self.edit("Added SYLT", |f| {
+                frame::add(
+                    &mut f.tag,
+                    Frame::with_content("SYLT", Content::SynchronisedLyrics(sylt)),
+                );
+            }),
+            AppAction::RemoveFrame(key) => self.edit(format!("Removed {}", key), |f| {
+                frame::remove(&mut f.tag, &key);
+            }),
+            AppAction::SetChapter(index, chapter) => {
+                self.edit_chapters(format!("Changed CHAP:{}", chapter.element_id), |f| {
+                    chapters::set_chapter_at(&mut f.tag, index, chapter);
                 })
             }
+            AppAction::AddChapter(start_time, end_time, title) => {
+                self.edit_chapters("Added CHAP", |f| {
+                    chapters::add_chapter(&mut f.tag, start_time, end_time, &title);
+                })
+            }
+            AppAction::MarkChapter(at, duration) => self.edit_chapters(
+                format!("Marked chapter at {}", chapters::format_time(at)),
+                |f| {
+                    chapters::mark_chapter(&mut f.tag, at, duration);
+                },
+            ),
+            AppAction::NudgeChapter(index, delta_ms) => {
+                let label = format!("Moved CHAP:{}", self.chapter_id(index));
+                self.edit_chapters(label, |f| {
+                    chapters::nudge_chapter(&mut f.tag, index, delta_ms);
+                })
+            }
+            AppAction::RemoveChapter(index) => {
+                let label = format!("Removed CHAP:{}", self.chapter_id(index));
+                self.edit_chapters(label, |f| {
+                    chapters::remove_chapter_at(&mut f.tag, index);
+                })
+            }
+            AppAction::SetTableOfContents(table) => {
+                self.edit_chapters(format!("Changed CTOC:{}", table.element_id), |f| {
+                    toc::set_toc(&mut f.tag, table);
+                })
+            }
+            AppAction::AddTableOfContents(parent, title) => self.edit_chapters("Added CTOC", |f| {
+                toc::add_toc(&mut f.tag, &parent, &title);
+            }),
+            AppAction::RemoveTableOfContents(element_id) => {
+                self.edit_chapters(format!("Removed CTOC:{}", element_id), |f| {
+                    toc::remove_toc(&mut f.tag, &element_id);
+                })
+            }
+            AppAction::MoveElement(element_id, to) => {
+                self.edit_chapters(format!("Moved {} to CTOC:{}", element_id, to), |f| {
+                    toc::move_element(&mut f.tag, &element_id, &to);
+                })
+            }
+            AppAction::SyncTablesOfContents => self.edit_chapters("Updated CTOC", |_| {}),
+            AppAction::FixChapters(fix, duration) => self.edit_chapters(fix.to_string(), |f| {
+                chapter_check::apply(&mut f.tag, fix, duration);
+            }),
+            // The import lists chapters itself, leaving out those it should.
+            AppAction::ImportChapters(chapters, duration) => self.edit("Imported chapters", |f| {
+                chapter_files::import(&mut f.tag, &chapters, duration);
+            }),
             // AppAction::URLCreated(url) => {
             //     log!("title changed");
             //     std::rc::Rc::new(AppState {
             //         mp3: self.mp3.clone(),
-            //         tag: self.tag.clone(),
+            //         file: self.file.clone(),
             //         frames: self.frames.clone(),
             //         reader_tasks: self.reader_tasks.clone(),
             //         name: self.name.clone(),
-            //         bytes: self.bytes.clone(),
             //         url,
+            //         version: self.version,
+            //         template: self.template.clone(),
+            //         error: self.error.clone(),
             //     })
             // }
-            AppAction::ClearClicked => std::rc::Rc::new(AppState {
-                mp3: None,
-                tag: None,
-                frames: Vec::new(),
-                reader_tasks: None,
-                name: String::new(),
-                bytes: Vec::new(),
-                url: String::new(),
-            }),
+            AppAction::ClearClicked => {
+                // The cleared file stays in the history so Clear can be undone.
+                let mut history = self.history.clone();
+                if self.file.is_some() {
+                    history.record("Cleared file", self.file.clone());
+                }
+                std::rc::Rc::new(AppState {
+                    mp3: None,
+                    file: None,
+                    frames: Vec::new(),
+                    reader_tasks: None,
+                    name: self.name.clone(),
+                    url: String::new(),
+                    // Kept, like `original`, so undoing Clear saves in the same
+                    // version. Loading a file sets it anew.
+                    version: self.version,
+                    template: self.template.clone(),
+                    error: None,
+                    history,
+                    // Kept so changes still show if Clear is undone.
+                    original: self.original.clone(),
+                })
+            }
             AppAction::SetFileName(name) => std::rc::Rc::new(AppState {
                 mp3: self.mp3.clone(),
-                tag: self.tag.clone(),
+                file: self.file.clone(),
                 frames: self.frames.clone(),
                 reader_tasks: self.reader_tasks.clone(),
                 name,
-                bytes: self.bytes.clone(),
                 url: self.url.clone(),
+                version: self.version,
+                template: self.template.clone(),
+                error: self.error.clone(),
+                history: self.history.clone(),
+                original: self.original.clone(),
+            }),
+            AppAction::SetVersion(version) => std::rc::Rc::new(AppState {
+                mp3: self.mp3.clone(),
+                file: self.file.clone(),
+                frames: self.frames.clone(),
+                reader_tasks: self.reader_tasks.clone(),
+                name: self.name.clone(),
+                url: self.url.clone(),
+                version,
+                template: self.template.clone(),
+                error: self.error.clone(),
+                history: self.history.clone(),
+                original: self.original.clone(),
+            }),
+            AppAction::SetKeepTrailer(trailer, keep) => {
+                let label = if keep {
+                    format!("Kept {}", trailer)
+                } else {
+                    format!("Removed {}", trailer)
+                };
+                self.edit(label, |f| f.set_keep(trailer, keep))
+            }
+            AppAction::Undo => {
+                let mut history = self.history.clone();
+                let Some(file) = history.undo(self.file.clone()) else {
+                    return self;
+                };
+                self.restore(file, history)
+            }
+            AppAction::Redo => {
+                let mut history = self.history.clone();
+                let Some(file) = history.redo(self.file.clone()) else {
+                    return self;
+                };
+                self.restore(file, history)
+            }
+            AppAction::SetTemplate(template) => std::rc::Rc::new(AppState {
+                mp3: self.mp3.clone(),
+                file: self.file.clone(),
+                frames: self.frames.clone(),
+                reader_tasks: self.reader_tasks.clone(),
+                name: self.name.clone(),
+                url: self.url.clone(),
+                version: self.version,
+                template,
+                error: self.error.clone(),
+                history: self.history.clone(),
+                original: self.original.clone(),
+            }),
+            AppAction::Saved => std::rc::Rc::new(AppState {
+                mp3: self.mp3.clone(),
+                file: self.file.clone(),
+                frames: self.frames.clone(),
+                reader_tasks: self.reader_tasks.clone(),
+                name: self.name.clone(),
+                url: self.url.clone(),
+                version: self.version,
+                template: self.template.clone(),
+                error: self.error.clone(),
+                history: self.history.clone(),
+                original: self.file.clone(),
+            }),
+            AppAction::Failed(error) => {
+                log!(format!("{}", error).as_str());
+                std::rc::Rc::new(AppState {
+                    mp3: self.mp3.clone(),
+                    file: self.file.clone(),
+                    frames: self.frames.clone(),
+                    reader_tasks: self.reader_tasks.clone(),
+                    name: self.name.clone(),
+                    url: self.url.clone(),
+                    version: self.version,
+                    template: self.template.clone(),
+                    error: Some(error),
+                    history: self.history.clone(),
+                    original: self.original.clone(),
+                })
+            }
+            AppAction::DismissError => std::rc::Rc::new(AppState {
+                mp3: self.mp3.clone(),
+                file: self.file.clone(),
+                frames: self.frames.clone(),
+                reader_tasks: self.reader_tasks.clone(),
+                name: self.name.clone(),
+                url: self.url.clone(),
+                version: self.version,
+                template: self.template.clone(),
+                error: None,
+                history: self.history.clone(),
+                original: self.original.clone(),
             }),
         }
     }